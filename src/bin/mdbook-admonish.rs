@@ -1,25 +1,62 @@
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use mdbook::preprocess::{CmdPreprocessor, Preprocessor};
+use mdbook_admonish::custom::CustomFormat;
 use mdbook_admonish::Admonish;
 use serde::Deserialize;
 use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process;
 
 /// mdbook preprocessor to add support for admonitions
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Log more output. May be repeated, up to `-vv`.
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less output. May be repeated, up to `-qq`.
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    quiet: u8,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+impl Cli {
+    /// The log level selected by the verbosity flags, `info` by default.
+    fn log_level(&self) -> log::LevelFilter {
+        const LEVELS: [log::LevelFilter; 5] = [
+            log::LevelFilter::Error,
+            log::LevelFilter::Warn,
+            log::LevelFilter::Info,
+            log::LevelFilter::Debug,
+            log::LevelFilter::Trace,
+        ];
+        let level = (2 + i16::from(self.verbose) - i16::from(self.quiet)).clamp(0, 4);
+        LEVELS[level as usize]
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Check whether a renderer is supported by this preprocessor
-    Supports { renderer: String },
+    Supports {
+        renderer: String,
+
+        /// Log the render mode that would be used for this renderer.
+        ///
+        /// Reads configuration from `book.toml` in the current directory, or `--config`.
+        #[arg(long)]
+        verbose: bool,
+
+        /// Configuration file to read, overriding `book.toml` in the book
+        /// directory. Relative paths in it are resolved from its directory.
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
 
     #[cfg(feature = "cli-install")]
     /// Install the required assset files and include it in the config
@@ -34,6 +71,11 @@ enum Commands {
         /// If not set, defaults to the current directory.
         #[arg(long)]
         css_dir: Option<PathBuf>,
+
+        /// Configuration file to read, overriding `book.toml` in the book
+        /// directory. Relative paths in it are resolved from its directory.
+        #[arg(long)]
+        config: Option<PathBuf>,
     },
 
     /// Generate CSS file for custom directives.
@@ -46,13 +88,57 @@ enum Commands {
 
         /// File to write generated css to.
         output: PathBuf,
+
+        /// Format to write: `css`, `scss` variables or `json` design tokens.
+        #[arg(long, default_value = "css")]
+        format: CustomFormat,
+
+        /// Also generate the colors and icons of the builtin directives, as in
+        /// `mdbook-admonish.css`.
+        #[arg(long)]
+        include_builtin: bool,
+
+        /// Configuration file to read, overriding `book.toml` in the book
+        /// directory. Relative paths in it are resolved from its directory.
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Render the admonitions in markdown read from stdin, and write the result to stdout.
+    ///
+    /// Useful for previewing a single block without building a book.
+    Render {
+        /// Configuration file (`book.toml`) to read settings from.
+        ///
+        /// If not set, the default settings are used.
+        #[arg(long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Generate a markdown page listing all available directives.
+    GenerateLegend {
+        /// Root directory for the book, should contain the configuration file (`book.toml`)
+        ///
+        /// If not set, defaults to the current directory.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// File to write generated markdown to.
+        output: PathBuf,
+
+        /// Configuration file to read, overriding `book.toml` in the book
+        /// directory. Relative paths in it are resolved from its directory.
+        #[arg(long)]
+        config: Option<PathBuf>,
     },
 }
 
 fn main() {
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
-
     let cli = Cli::parse();
+    env_logger::init_from_env(
+        env_logger::Env::default().default_filter_or(cli.log_level().to_string()),
+    );
+
     if let Err(error) = run(cli) {
         log::error!("Fatal error: {}", error);
         for error in error.chain() {
@@ -65,40 +151,85 @@ fn main() {
 fn run(cli: Cli) -> Result<()> {
     match cli.command {
         None => handle_preprocessing(),
-        Some(Commands::Supports { renderer }) => {
-            handle_supports(renderer);
+        Some(Commands::Supports {
+            renderer,
+            verbose,
+            config,
+        }) => {
+            handle_supports(renderer, verbose, config_path(None, config));
         }
         #[cfg(feature = "cli-install")]
-        Some(Commands::Install { dir, css_dir }) => install::handle_install(
-            dir.unwrap_or_else(|| PathBuf::from(".")),
-            css_dir.unwrap_or_else(|| PathBuf::from(".")),
-        ),
-        Some(Commands::GenerateCustom { dir, output }) => {
-            handle_generate_custom(dir.unwrap_or_else(|| PathBuf::from(".")), output)
+        Some(Commands::Install {
+            dir,
+            css_dir,
+            config,
+        }) => {
+            let config = config_path(dir.as_deref(), config);
+            install::handle_install(
+                dir.unwrap_or_else(|| PathBuf::from(".")),
+                css_dir.unwrap_or_else(|| PathBuf::from(".")),
+                config,
+            )
         }
+        Some(Commands::GenerateCustom {
+            dir,
+            output,
+            format,
+            include_builtin,
+            config,
+        }) => handle_generate_custom(
+            config_path(dir.as_deref(), config),
+            output,
+            format,
+            include_builtin,
+        ),
+        Some(Commands::GenerateLegend {
+            dir,
+            output,
+            config,
+        }) => handle_generate_legend(config_path(dir.as_deref(), config), output),
+        Some(Commands::Render { config }) => handle_render(config),
     }
 }
 
 fn handle_preprocessing() -> std::result::Result<(), mdbook::errors::Error> {
     let (ctx, book) = CmdPreprocessor::parse_input(io::stdin())?;
 
-    if ctx.mdbook_version != mdbook::MDBOOK_VERSION {
-        eprintln!(
-            "Warning: The mdbook-admonish preprocessor was built against version \
-             {} of mdbook, but we're being called from version {}",
-            mdbook::MDBOOK_VERSION,
-            ctx.mdbook_version
-        );
+    if let Some(warning) = mdbook_version_warning(&ctx.mdbook_version) {
+        log::warn!("{warning}");
     }
 
-    let processed_book = Admonish.run(&ctx, book)?;
+    let processed_book = Admonish::new().run(&ctx, book)?;
     serde_json::to_writer(io::stdout(), &processed_book)?;
 
     Ok(())
 }
 
-fn handle_supports(renderer: String) -> ! {
-    let supported = Admonish.supports_renderer(&renderer);
+/// Warning for being called from a different mdbook version than we were built against.
+fn mdbook_version_warning(mdbook_version: &str) -> Option<String> {
+    (mdbook_version != mdbook::MDBOOK_VERSION).then(|| {
+        format!(
+            "The mdbook-admonish preprocessor was built against version \
+             {} of mdbook, but we're being called from version {mdbook_version}",
+            mdbook::MDBOOK_VERSION,
+        )
+    })
+}
+
+fn handle_supports(renderer: String, verbose: bool, config_path: PathBuf) -> ! {
+    let supported = Admonish::new().supports_renderer(&renderer);
+
+    if verbose {
+        log::info!("Renderer '{renderer}' supported: {supported}");
+        match read_admonish_config_string(&config_path).and_then(|config| {
+            mdbook_admonish::render_mode_from_config(book_dir(&config_path), &config, &renderer)
+        }) {
+            Ok(render_mode) => {
+                log::info!("Renderer '{renderer}' will use render mode '{render_mode}'")
+            }
+            Err(error) => log::warn!("Could not determine render mode: {error:#}"),
+        }
+    }
 
     // Signal whether the renderer is supported by exiting with 1 or 0.
     if supported {
@@ -110,10 +241,31 @@ fn handle_supports(renderer: String) -> ! {
 
 #[derive(Deserialize)]
 struct Config {
+    #[serde(default)]
+    book: BookTable,
+
     #[serde(default)]
     preprocessor: Preprocessors,
 }
 
+#[derive(Deserialize)]
+struct BookTable {
+    #[serde(default = "default_book_src")]
+    src: PathBuf,
+}
+
+impl Default for BookTable {
+    fn default() -> Self {
+        Self {
+            src: default_book_src(),
+        }
+    }
+}
+
+fn default_book_src() -> PathBuf {
+    PathBuf::from("src")
+}
+
 #[derive(Default, Deserialize)]
 struct Preprocessors {
     #[serde(default)]
@@ -134,21 +286,87 @@ fn admonish_config_string(config: &Config) -> Result<String> {
     )?)
 }
 
-fn handle_generate_custom(proj_dir: PathBuf, output: PathBuf) -> Result<()> {
-    let config = proj_dir.join("book.toml");
+/// Read a book configuration file.
+fn read_config(config: &Path) -> Result<Config> {
     log::info!("Reading configuration file '{}'", config.display());
-    let data = fs::read_to_string(&config)
+    let data = fs::read_to_string(config)
         .with_context(|| format!("can't read configuration file '{}'", config.display()))?;
-    let config: Config = toml::from_str(&data).context("Invalid configuration file")?;
+    toml::from_str(&data).context("Invalid configuration file")
+}
 
-    let css =
-        mdbook_admonish::custom::css_from_config(&proj_dir, &admonish_config_string(&config)?)?;
+/// The configuration file to read: `config` if given, else `book.toml` in the
+/// book directory `dir`, which defaults to the current directory.
+fn config_path(dir: Option<&Path>, config: Option<PathBuf>) -> PathBuf {
+    config.unwrap_or_else(|| dir.unwrap_or(Path::new(".")).join("book.toml"))
+}
 
-    log::info!("Writing custom CSS file '{}'", output.display());
+/// The directory paths in the configuration file `config_path` are relative to.
+fn book_dir(config_path: &Path) -> &Path {
+    match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Read the book configuration file, and return the plugin specific config.
+fn read_admonish_config_string(config_path: &Path) -> Result<String> {
+    admonish_config_string(&read_config(config_path)?)
+}
+
+fn handle_generate_custom(
+    config_path: PathBuf,
+    output: PathBuf,
+    format: CustomFormat,
+    include_builtin: bool,
+) -> Result<()> {
+    let config = read_admonish_config_string(&config_path)?;
+    let css = mdbook_admonish::custom::custom_from_config(
+        book_dir(&config_path),
+        &config,
+        format,
+        include_builtin,
+    )?;
+
+    log::info!("Writing custom {format:?} file '{}'", output.display());
     fs::write(output, css)?;
     Ok(())
 }
 
+fn handle_generate_legend(config_path: PathBuf, output: PathBuf) -> Result<()> {
+    let config = read_admonish_config_string(&config_path)?;
+    let markdown = mdbook_admonish::legend::legend_from_config(book_dir(&config_path), &config)?;
+
+    log::info!("Writing legend file '{}'", output.display());
+    fs::write(output, markdown)?;
+    Ok(())
+}
+
+fn handle_render(config: Option<PathBuf>) -> Result<()> {
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .context("can't read markdown from stdin")?;
+
+    let html = match config {
+        Some(config) => {
+            let proj_dir = book_dir(&config);
+            let config = read_config(&config)?;
+            mdbook_admonish::render_markdown_from_config(
+                proj_dir,
+                Some(&admonish_config_string(&config)?),
+                Some(&proj_dir.join(&config.book.src)),
+                &content,
+            )?
+        }
+        None => mdbook_admonish::render_markdown_from_config(Path::new("."), None, None, &content)?,
+    };
+
+    io::stdout()
+        .write_all(html.as_bytes())
+        .context("can't write to stdout")?;
+    Ok(())
+}
+
 #[cfg(feature = "cli-install")]
 mod install {
     use anyhow::{Context, Result};
@@ -190,8 +408,7 @@ mod install {
             .context("UNIX style path normalization error")
     }
 
-    pub fn handle_install(proj_dir: PathBuf, css_dir: PathBuf) -> Result<()> {
-        let config = proj_dir.join("book.toml");
+    pub fn handle_install(proj_dir: PathBuf, css_dir: PathBuf, config: PathBuf) -> Result<()> {
         log::info!("Reading configuration file '{}'", config.display());
         let toml = fs::read_to_string(&config)
             .with_context(|| format!("can't read configuration file '{}'", config.display()))?;
@@ -317,3 +534,88 @@ A beautifully styled message.
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn log_level_from_flags() {
+        for (args, expected) in [
+            (vec![], log::LevelFilter::Info),
+            (vec!["-v"], log::LevelFilter::Debug),
+            (vec!["-vvv"], log::LevelFilter::Trace),
+            (vec!["--quiet"], log::LevelFilter::Warn),
+            (vec!["-qqq"], log::LevelFilter::Error),
+        ] {
+            let cli = Cli::try_parse_from(std::iter::once("mdbook-admonish").chain(args)).unwrap();
+            assert_eq!(cli.log_level(), expected);
+        }
+        assert!(Cli::try_parse_from(["mdbook-admonish", "-v", "-q"]).is_err());
+        // Subcommand flags are unaffected
+        assert!(Cli::try_parse_from(["mdbook-admonish", "supports", "--verbose", "html"]).is_ok());
+    }
+
+    #[test]
+    fn mdbook_version_mismatch_warned() {
+        assert_eq!(mdbook_version_warning(mdbook::MDBOOK_VERSION), None);
+        assert_eq!(
+            mdbook_version_warning("0.1.0"),
+            Some(format!(
+                "The mdbook-admonish preprocessor was built against version {} \
+                 of mdbook, but we're being called from version 0.1.0",
+                mdbook::MDBOOK_VERSION
+            ))
+        );
+    }
+
+    #[test]
+    fn config_path_and_book_dir() {
+        assert_eq!(config_path(None, None), Path::new("./book.toml"));
+        assert_eq!(
+            config_path(Some(Path::new("docs")), None),
+            Path::new("docs/book.toml")
+        );
+        assert_eq!(
+            config_path(
+                Some(Path::new("docs")),
+                Some(PathBuf::from("conf/admonish.toml"))
+            ),
+            Path::new("conf/admonish.toml")
+        );
+        assert_eq!(book_dir(Path::new("conf/admonish.toml")), Path::new("conf"));
+        assert_eq!(book_dir(Path::new("admonish.toml")), Path::new("."));
+    }
+
+    #[test]
+    fn generate_custom_with_config_elsewhere() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("mdbook-admonish-config-{}", std::process::id()));
+        let config_dir = dir.join("conf");
+        fs::create_dir_all(&config_dir)?;
+        fs::write(
+            config_dir.join("frog.svg"),
+            "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'></svg>",
+        )?;
+        let config = config_dir.join("admonish.toml");
+        fs::write(
+            &config,
+            "[preprocessor.admonish.directive.custom.frog]\nicon = \"frog.svg\"\ncolor = \"green\"\n",
+        )?;
+        let output = dir.join("custom.css");
+
+        let cli = Cli::try_parse_from([
+            "mdbook-admonish".as_ref(),
+            "generate-custom".as_ref(),
+            "--config".as_ref(),
+            config.as_os_str(),
+            output.as_os_str(),
+        ])?;
+        run(cli)?;
+        let css = fs::read_to_string(&output)?;
+        assert!(css.contains("--md-admonition-icon--admonish-frog: url("));
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -1,10 +1,20 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use mdbook::preprocess::PreprocessorContext;
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{DeserializeOwned, IntoDeserializer},
+    Deserialize, Serialize,
+};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use crate::types::{AdmonitionDefaults, BuiltinDirective, BuiltinDirectiveConfig};
+use crate::color::{Color, DirectiveColor};
+use crate::custom::BUILTIN_ICON_PREFIX;
+use crate::types::{
+    AdmonitionDefaults, AdmonitionPreset, BuiltinDirective, BuiltinDirectiveConfig,
+};
 
 /// Loads the plugin configuration from mdbook internals.
 ///
@@ -17,17 +27,384 @@ pub(crate) fn admonish_config_from_context(ctx: &PreprocessorContext) -> Result<
             .context("No configuration for mdbook-admonish in book.toml")?,
     )
     .context("Could not serialize mdbook-admonish config. This is a bug in the toml library.")?;
-    admonish_config_from_str(&table)
+    let mut config = admonish_config_from_str(&table, &ctx.root)?;
+    apply_env_overrides(&mut config, &ctx.renderer, |key| std::env::var(key).ok())?;
+    Ok(config)
+}
+
+/// Overrides `on_failure`.
+const ENV_ON_FAILURE: &str = "MDBOOK_ADMONISH_ON_FAILURE";
+/// Overrides `render_mode` for the renderer being run.
+const ENV_RENDER_MODE: &str = "MDBOOK_ADMONISH_RENDER_MODE";
+/// Overrides `default.skip_assets_check`.
+const ENV_SKIP_ASSETS_CHECK: &str = "MDBOOK_ADMONISH_SKIP_ASSETS_CHECK";
+
+/// Apply overrides from environment variables on top of the file config, so
+/// CI can build the same book in different ways.
+///
+/// `env` looks up a variable by name.
+fn apply_env_overrides(
+    config: &mut Config,
+    renderer: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<()> {
+    if let Some(on_failure) = env_value(&env, ENV_ON_FAILURE, parse_enum)? {
+        config.on_failure = on_failure;
+    }
+    if let Some(render_mode) = env_value(&env, ENV_RENDER_MODE, parse_enum)? {
+        config
+            .renderer
+            .entry(renderer.to_owned())
+            .or_insert(RendererConfig {
+                render_mode: None,
+                strip_annotate: false,
+            })
+            .render_mode = Some(render_mode);
+    }
+    if let Some(skip_assets_check) = env_value(&env, ENV_SKIP_ASSETS_CHECK, parse_bool)? {
+        config.default.skip_assets_check = skip_assets_check;
+    }
+    Ok(())
+}
+
+/// Parse the environment variable `key` with `parse`, if it is set.
+fn env_value<T>(
+    env: impl Fn(&str) -> Option<String>,
+    key: &str,
+    parse: impl Fn(&str) -> Result<T>,
+) -> Result<Option<T>> {
+    env(key)
+        .map(|value| {
+            parse(value.trim())
+                .with_context(|| format!("Invalid value '{value}' for environment variable {key}"))
+        })
+        .transpose()
+}
+
+/// Parse a lowercase enum value, as it would be written in `book.toml`.
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Result<T> {
+    T::deserialize(value.into_deserializer())
+        .map_err(|error: serde::de::value::Error| anyhow!(error))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    Ok(value.parse()?)
 }
 
-pub(crate) fn admonish_config_from_str(data: &str) -> Result<Config> {
+/// Parse the plugin configuration.
+///
+/// `book_dir` is the book root, which other files referenced by the config are relative to.
+pub(crate) fn admonish_config_from_str(data: &str, book_dir: &Path) -> Result<Config> {
     let readonly: ConfigReadonly =
         toml::from_str(data).context("Invalid mdbook-admonish configuration in book.toml")?;
-    let config = readonly.into();
+    let mut config: Config = readonly.into();
+    if let Some(directive_import) = &config.directive_import {
+        let imported = directives_from_import(&book_dir.join(directive_import))?;
+        merge_imported_directives(&mut config.directive, imported);
+    }
+    resolve_directive_extends(&mut config.directive)?;
+    validate_disabled_directives(&config)?;
+    config.default.collapsible_directives = canonical_directives(
+        &config,
+        "collapsible_directives",
+        &config.default.collapsible_directives,
+    )?;
+    config.default.numbered_directives = canonical_directives(
+        &config,
+        "numbered_directives",
+        &config.default.numbered_directives,
+    )?;
+    validate_default_titles(&config)?;
+    validate_error_directive(&config)?;
+    validate_presets(&config)?;
+    validate_default_directive(&mut config);
     log::debug!("Loaded admonish config: {:?}", config);
     Ok(config)
 }
 
+/// Front matter of a chapter, which may define directives for that chapter.
+///
+/// Other keys are left for other tools.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+struct ChapterFrontMatter {
+    #[serde(default)]
+    admonish: Option<ChapterConfig>,
+}
+
+/// Configuration given in the front matter of a single chapter.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+pub(crate) struct ChapterConfig {
+    /// Custom directives only available in this chapter.
+    #[serde(default)]
+    pub custom: HashMap<String, CustomDirective>,
+}
+
+/// Parse the TOML front matter of a chapter.
+///
+/// Returns `None` if the front matter has no `admonish` table.
+pub(crate) fn chapter_config_from_str(front_matter: &str) -> Result<Option<ChapterConfig>> {
+    let front_matter: ChapterFrontMatter =
+        toml::from_str(front_matter).context("Invalid chapter front matter")?;
+    let Some(config) = front_matter.admonish else {
+        return Ok(None);
+    };
+    let mut directives = DirectiveConfig {
+        custom: config.custom,
+        builtin: HashMap::new(),
+    };
+    resolve_directive_extends(&mut directives)?;
+    Ok(Some(ChapterConfig {
+        custom: directives.custom,
+    }))
+}
+
+/// The contents of a `directive_import` file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+struct DirectiveImport {
+    #[serde(default)]
+    directive: DirectiveConfig,
+}
+
+fn directives_from_import(path: &Path) -> Result<DirectiveConfig> {
+    log::debug!("Reading directive import file '{}'", path.display());
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("can't read directive import file '{}'", path.display()))?;
+    let import: DirectiveImport = toml::from_str(&data)
+        .with_context(|| format!("Invalid directive import file '{}'", path.display()))?;
+    Ok(import.directive)
+}
+
+/// Merge imported directives into the local config. Local definitions take precedence.
+fn merge_imported_directives(local: &mut DirectiveConfig, imported: DirectiveConfig) {
+    for (directive, config) in imported.custom {
+        local.custom.entry(directive).or_insert(config);
+    }
+    for (directive, config) in imported.builtin {
+        local.builtin.entry(directive).or_insert(config);
+    }
+}
+
+/// Fill in the unset fields of custom directives from the directive they extend.
+///
+/// Also ensures every custom directive ends up with an icon and color.
+fn resolve_directive_extends(directives: &mut DirectiveConfig) -> Result<()> {
+    let mut names: Vec<String> = directives.custom.keys().cloned().collect();
+    names.sort();
+    for name in &names {
+        let custom = directives
+            .custom
+            .get_mut(name)
+            .expect("custom directive exists");
+        expand_style_shorthand(name, custom)?;
+    }
+    for name in &names {
+        resolve_custom_extends(directives, name, &mut Vec::new())?;
+    }
+    for name in &names {
+        let custom = &directives.custom[name];
+        if custom.icon.is_none() || custom.color.is_none() {
+            return Err(anyhow!(
+                "Custom directive '{name}' must set 'icon' and 'color', or 'extends' another directive"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Expand the `style` shorthand of a custom directive into `icon` and `color`.
+fn expand_style_shorthand(name: &str, custom: &mut CustomDirective) -> Result<()> {
+    let Some(style) = custom.style.take() else {
+        return Ok(());
+    };
+    if custom.icon.is_some() || custom.color.is_some() {
+        return Err(anyhow!(
+            "Custom directive '{name}' must set either 'style', or 'icon' and 'color', not both"
+        ));
+    }
+    let Some((icon, color)) = style.trim().split_once(char::is_whitespace) else {
+        return Err(anyhow!(
+            "Invalid style '{style}' for custom directive '{name}', expected an icon path and a color, e.g. \"frog.svg #9B4F96\""
+        ));
+    };
+    let color = Color::from_str(color)
+        .map_err(|error| anyhow!("Invalid style for custom directive '{name}': {error}"))?;
+    custom.icon = Some(PathBuf::from(icon));
+    custom.color = Some(DirectiveColor::from(color));
+    Ok(())
+}
+
+/// Resolve a single custom directive, after any custom directive it extends.
+///
+/// `chain` holds the directives currently being resolved, to detect cycles.
+fn resolve_custom_extends(
+    directives: &mut DirectiveConfig,
+    name: &str,
+    chain: &mut Vec<String>,
+) -> Result<()> {
+    let Some(parent) = directives.custom[name].extends.clone() else {
+        return Ok(());
+    };
+    if chain.iter().any(|link| link == name) {
+        chain.push(name.to_owned());
+        return Err(anyhow!(
+            "Custom directives extend each other in a cycle: {}",
+            chain.join(" -> ")
+        ));
+    }
+
+    let parent_custom = directives
+        .custom
+        .iter()
+        .find(|(directive, custom)| {
+            *directive == &parent || custom.aliases.iter().any(|alias| alias == &parent)
+        })
+        .map(|(directive, _)| directive.clone());
+    let (icon, color, collapsible) = if let Some(parent_custom) = parent_custom {
+        chain.push(name.to_owned());
+        resolve_custom_extends(directives, &parent_custom, chain)?;
+        chain.pop();
+        let parent = &directives.custom[&parent_custom];
+        (parent.icon.clone(), parent.color, parent.collapsible)
+    } else if let Ok(builtin) = BuiltinDirective::from_str(&parent) {
+        let color = Color::from_str(builtin.info().color).map_err(|error| anyhow!(error))?;
+        (
+            Some(PathBuf::from(format!("{BUILTIN_ICON_PREFIX}{builtin}"))),
+            Some(DirectiveColor::from(color)),
+            directives
+                .builtin
+                .get(&builtin)
+                .and_then(|config| config.collapsible),
+        )
+    } else {
+        return Err(anyhow!(
+            "Custom directive '{name}' extends unknown directive '{parent}'"
+        ));
+    };
+
+    let custom = directives
+        .custom
+        .get_mut(name)
+        .expect("custom directive exists");
+    custom.icon = custom.icon.take().or(icon);
+    custom.color = custom.color.or(color);
+    custom.collapsible = custom.collapsible.or(collapsible);
+    Ok(())
+}
+
+/// Ensure all disabled directives are builtin directives.
+fn validate_disabled_directives(config: &Config) -> Result<()> {
+    for directive in &config.default.disabled_directives {
+        if BuiltinDirective::from_str(directive).is_err() {
+            return Err(anyhow!(
+                "Invalid disabled directive '{directive}', only builtin directives can be disabled"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Ensure default titles are only given for builtin directives.
+fn validate_default_titles(config: &Config) -> Result<()> {
+    for directive in config.default.titles.keys() {
+        if BuiltinDirective::from_str(directive).is_err() {
+            return Err(anyhow!(
+                "Invalid default title for '{directive}', only builtin directives can be given default titles"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Ensure the configured error directive exists.
+fn validate_error_directive(config: &Config) -> Result<()> {
+    let Some(directive) = &config.default.error_directive else {
+        return Ok(());
+    };
+    if !is_known_directive(config, directive) || is_disabled_directive(config, directive) {
+        return Err(anyhow!(
+            "Invalid error directive '{directive}', must be an enabled builtin or custom directive"
+        ));
+    }
+    Ok(())
+}
+
+/// Ensure the directives of presets exist.
+fn validate_presets(config: &Config) -> Result<()> {
+    let mut names: Vec<&String> = config.presets.keys().collect();
+    names.sort();
+    for name in names {
+        if let Some(directive) = &config.presets[name].directive {
+            if !is_known_directive(config, directive) {
+                return Err(anyhow!(
+                    "Invalid directive '{directive}' for preset '{name}', must be a builtin or custom directive"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the directive is a builtin or custom directive, or an alias of one.
+fn is_known_directive(config: &Config, directive: &str) -> bool {
+    canonical_directive(config, directive).is_some()
+}
+
+/// The name of a builtin or custom directive, given it or one of its aliases.
+fn canonical_directive(config: &Config, directive: &str) -> Option<String> {
+    if let Ok(builtin) = BuiltinDirective::from_str(directive) {
+        return Some(builtin.to_string());
+    }
+    config
+        .directive
+        .custom
+        .iter()
+        .find(|(name, custom)| {
+            *name == directive || custom.aliases.iter().any(|alias| alias == directive)
+        })
+        .map(|(name, _)| name.clone())
+}
+
+/// Resolve the directives listed in `default.{key}` to their names, so
+/// blocks using an alias match too.
+fn canonical_directives(config: &Config, key: &str, directives: &[String]) -> Result<Vec<String>> {
+    directives
+        .iter()
+        .map(|directive| {
+            canonical_directive(config, directive).ok_or_else(|| {
+                anyhow!("Invalid directive '{directive}' in 'default.{key}', must be a builtin or custom directive")
+            })
+        })
+        .collect()
+}
+
+/// Ensure the configured default directive exists, otherwise warn and fall back to `note`.
+fn validate_default_directive(config: &mut Config) {
+    let Some(directive) = &config.default.directive else {
+        return;
+    };
+    let warning = if !is_known_directive(config, directive) {
+        format!("Unknown default directive '{directive}', falling back to 'note'")
+    } else if is_disabled_directive(config, directive) {
+        format!("Default directive '{directive}' is disabled, falling back to 'note'")
+    } else {
+        return;
+    };
+    config.warnings.push(warning);
+    config.default.directive = None;
+}
+
+/// Whether the directive is a builtin directive listed in `default.disabled_directives`.
+fn is_disabled_directive(config: &Config, directive: &str) -> bool {
+    let Ok(builtin) = BuiltinDirective::from_str(directive) else {
+        return false;
+    };
+    config
+        .default
+        .disabled_directives
+        .iter()
+        .any(|disabled| BuiltinDirective::from_str(disabled) == Ok(builtin))
+}
+
 /// All valid input states including back-compatibility fields.
 ///
 /// This struct deliberately does not implement Serialize as it never meant to
@@ -54,6 +431,12 @@ struct ConfigReadonly {
 
     #[serde(default)]
     pub directive: DirectiveConfig,
+
+    #[serde(default)]
+    pub directive_import: Option<PathBuf>,
+
+    #[serde(default)]
+    pub presets: HashMap<String, AdmonitionPreset>,
 }
 
 /// The canonical config format, without back-compatibility
@@ -73,6 +456,19 @@ pub(crate) struct Config {
 
     #[serde(default)]
     pub directive: DirectiveConfig,
+
+    /// Path to a TOML file of additional directives, relative to the book root.
+    #[serde(default)]
+    pub directive_import: Option<PathBuf>,
+
+    /// Reusable admonitions, by name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub presets: HashMap<String, AdmonitionPreset>,
+
+    /// Problems found while loading the config, which are logged as warnings
+    /// once processing starts, so they count towards `default.deny_warnings`.
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
 
 impl From<ConfigReadonly> for Config {
@@ -85,6 +481,8 @@ impl From<ConfigReadonly> for Config {
             custom,
             builtin,
             mut directive,
+            directive_import,
+            presets,
         } = other;
 
         // Merge deprecated config fields into main config object
@@ -101,6 +499,9 @@ impl From<ConfigReadonly> for Config {
             renderer,
             assets_version,
             directive,
+            directive_import,
+            presets,
+            warnings: Vec::new(),
         }
     }
 }
@@ -117,15 +518,39 @@ pub(crate) struct DirectiveConfig {
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub(crate) struct CustomDirective {
     /// Path to an SVG file, relative to the book root.
-    pub icon: PathBuf,
+    ///
+    /// Required, unless inherited with `extends`.
+    #[serde(default)]
+    pub icon: Option<PathBuf>,
 
     /// Primary color for this directive.
-    pub color: hex_color::HexColor,
+    ///
+    /// May be a hex color, CSS named color, or `rgb()`/`hsl()` value, or a
+    /// table with the color as `base` and the title background alpha as `tint`.
+    /// Required, unless inherited with `extends`.
+    #[serde(default)]
+    pub color: Option<DirectiveColor>,
+
+    /// Shorthand for `icon` and `color`, as the icon path and the color
+    /// separated by whitespace, e.g. `"frog.svg #9B4F96"`.
+    ///
+    /// Expanded into `icon` and `color` when the config is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+
+    /// Builtin or custom directive to inherit the icon, color and collapsible
+    /// value from, if not set.
+    #[serde(default)]
+    pub extends: Option<String>,
 
     /// Alternative directives the user can specify
     #[serde(default)]
     pub aliases: Vec<String>,
 
+    /// Former names of this directive, which still work but log a warning.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renamed_from: Vec<String>,
+
     /// Title to use, human readable.
     #[serde(default)]
     pub title: Option<String>,
@@ -133,6 +558,26 @@ pub(crate) struct CustomDirective {
     /// Default collapsible value.
     #[serde(default)]
     pub collapsible: Option<bool>,
+
+    /// Default collapsible value for specific aliases, overriding `collapsible`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub alias_collapsible: HashMap<String, bool>,
+
+    /// Markdown added before the body of every block.
+    #[serde(default)]
+    pub body_prefix: Option<String>,
+
+    /// Markdown added after the body of every block.
+    #[serde(default)]
+    pub body_suffix: Option<String>,
+
+    /// ARIA role of every block, instead of `note`.
+    #[serde(default)]
+    pub role: Option<String>,
+
+    /// Template for the `aria-label` of every block, as for builtin directives.
+    #[serde(default)]
+    pub aria_label: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -148,6 +593,10 @@ pub(crate) struct CustomDirectiveReadonly {
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub(crate) struct RendererConfig {
     pub render_mode: Option<RenderMode>,
+
+    /// In `strip` mode, keep block options in HTML comments around the content.
+    #[serde(default)]
+    pub strip_annotate: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
@@ -156,6 +605,21 @@ pub(crate) enum RenderMode {
     Preserve,
     Strip,
     Html,
+    Blockquote,
+    Text,
+}
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Preserve => "preserve",
+            Self::Strip => "strip",
+            Self::Html => "html",
+            Self::Blockquote => "blockquote",
+            Self::Text => "text",
+        };
+        f.write_str(value)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -165,6 +629,7 @@ pub(crate) enum OnFailure {
     Continue,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for OnFailure {
     fn default() -> Self {
         Self::Continue
@@ -176,11 +641,14 @@ mod test {
     use super::*;
     use pretty_assertions::assert_eq;
 
-    use crate::types::BuiltinDirective;
+    use crate::types::{
+        AdmonitionElement, BlockStyle, BodyTrim, BuiltinDirective, ClassStyle, CollapsibleImpl,
+        ColorMode, Density, SummaryMarker, TitleLayout, UntitledIdSource,
+    };
 
     #[test]
     fn empty_config_okay() -> Result<()> {
-        let actual = admonish_config_from_str("")?;
+        let actual = admonish_config_from_str("", Path::new("."))?;
         let expected = Config::default();
         assert_eq!(actual, expected);
         Ok(())
@@ -197,11 +665,13 @@ mod test {
         };
 
         // Snake case okay
-        let actual = admonish_config_from_str(r#"default = { css_id_prefix = "flam-" }"#)?;
+        let actual =
+            admonish_config_from_str(r#"default = { css_id_prefix = "flam-" }"#, Path::new("."))?;
         assert_eq!(actual, expected);
 
         // Kebab case back-compat okay
-        let actual = admonish_config_from_str(r#"default = { css-id-prefix = "flam-" }"#)?;
+        let actual =
+            admonish_config_from_str(r#"default = { css-id-prefix = "flam-" }"#, Path::new("."))?;
         assert_eq!(actual, expected);
         Ok(())
     }
@@ -229,21 +699,37 @@ title = "Blue"
                     (
                         "purple".to_owned(),
                         CustomDirective {
-                            icon: PathBuf::from("/tmp/test-directive.svg"),
-                            color: hex_color::HexColor::from((155, 79, 150)),
+                            icon: Some(PathBuf::from("/tmp/test-directive.svg")),
+                            color: Some(Color::from((155, 79, 150)).into()),
+                            style: None,
+                            extends: None,
                             aliases: vec!["test-directive-alias-0".to_owned()],
+                            renamed_from: Vec::new(),
                             title: Some("Purple".to_owned()),
                             collapsible: Some(true),
+                            alias_collapsible: HashMap::new(),
+                            body_prefix: None,
+                            body_suffix: None,
+                            role: None,
+                            aria_label: None,
                         },
                     ),
                     (
                         "blue".to_owned(),
                         CustomDirective {
-                            icon: PathBuf::from("/tmp/test-directive.svg"),
-                            color: hex_color::HexColor::from((0, 56, 168)),
+                            icon: Some(PathBuf::from("/tmp/test-directive.svg")),
+                            color: Some(Color::from((0, 56, 168)).into()),
+                            style: None,
+                            extends: None,
                             aliases: vec![],
+                            renamed_from: Vec::new(),
                             title: Some("Blue".to_owned()),
                             collapsible: None,
+                            alias_collapsible: HashMap::new(),
+                            body_prefix: None,
+                            body_suffix: None,
+                            role: None,
+                            aria_label: None,
                         },
                     ),
                 ]),
@@ -252,7 +738,7 @@ title = "Blue"
             ..Default::default()
         };
 
-        let actual = admonish_config_from_str(serialized)?;
+        let actual = admonish_config_from_str(serialized, Path::new("."))?;
         assert_eq!(actual, expected);
         Ok(())
     }
@@ -262,35 +748,100 @@ title = "Blue"
         let input = Config {
             default: AdmonitionDefaults {
                 css_id_prefix: Some("flam-".to_owned()),
-                collapsible: true,
+                untitled_id_source: UntitledIdSource::Index,
+                collapsible: Some(true),
+                error_collapsible: true,
+                error_directive: Some("danger".to_owned()),
+                collapsible_directives: vec!["warning".to_owned()],
+                disabled_directives: vec!["quote".to_owned()],
+                numbered: true,
+                numbered_directives: vec!["example".to_owned()],
                 title: Some("".to_owned()),
+                titles: HashMap::from([("note".to_owned(), "Anmerkung".to_owned())]),
+                element: AdmonitionElement::Aside,
+                collapsible_impl: CollapsibleImpl::Aria,
+                density: Density::Compact,
+                style: BlockStyle::Bar,
+                title_layout: TitleLayout::Stacked,
+                marker: SummaryMarker::Start,
+                title_anchor: Some(false),
+                anchor_glyph: "#".to_owned(),
+                absolute_anchor_links: true,
+                show_badge: true,
+                deny_warnings: true,
+                warn_unused_directives: true,
+                inline_icons: true,
+                collapse_controls: true,
+                avoid_page_break: true,
+                figure_wrap: true,
+                force_expand_non_html: Some(false),
+                max_nesting: Some(3),
+                auto_collapse_lines: Some(20),
+                a11y_state_labels: true,
+                color_mode: ColorMode::Variable,
+                class_style: ClassStyle::Bem,
+                print_expand_collapsed: true,
+                mkdocs_syntax: true,
+                skip_assets_check: true,
+                directive: Some("warning".to_owned()),
+                trim: BodyTrim::Both,
+                include: vec!["reference/**".to_owned()],
+                exclude: vec!["reference/drafts/*".to_owned()],
             },
             assets_version: Some("1.1.1".to_owned()),
             directive: DirectiveConfig {
                 custom: HashMap::from([(
                     "test-directive".to_owned(),
                     CustomDirective {
-                        icon: PathBuf::from("/tmp/test-directive.svg"),
-                        color: hex_color::HexColor::from((155, 79, 150)),
+                        icon: Some(PathBuf::from("/tmp/test-directive.svg")),
+                        color: Some(Color::from((155, 79, 150)).into()),
+                        style: None,
+                        extends: None,
                         aliases: vec!["test-directive-alias-0".to_owned()],
+                        renamed_from: vec!["test-directive-old".to_owned()],
                         title: Some("test-directive-title".to_owned()),
                         collapsible: Some(true),
+                        alias_collapsible: HashMap::from([(
+                            "test-directive-alias-0".to_owned(),
+                            false,
+                        )]),
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: None,
+                        aria_label: None,
                     },
                 )]),
                 builtin: HashMap::from([(
                     BuiltinDirective::Warning,
                     BuiltinDirectiveConfig {
                         collapsible: Some(true),
+                        body_prefix: None,
+                        body_suffix: Some("Report issues to **compliance**.".to_owned()),
+                        role: Some("alert".to_owned()),
+                        aria_label: None,
                     },
                 )]),
             },
+            // Not set, as loading would read the file
+            directive_import: None,
+            presets: HashMap::from([(
+                "legal".to_owned(),
+                AdmonitionPreset {
+                    directive: Some("warning".to_owned()),
+                    title: Some("Legal".to_owned()),
+                    collapsible: None,
+                    body: Some("Not legal advice.".to_owned()),
+                },
+            )]),
             on_failure: OnFailure::Bail,
             renderer: HashMap::from([(
                 "test-mode".to_owned(),
                 RendererConfig {
                     render_mode: Some(RenderMode::Strip),
+                    strip_annotate: true,
                 },
             )]),
+            warnings: Vec::new(),
         };
 
         let expected = r##"on_failure = "bail"
@@ -298,28 +849,487 @@ assets_version = "1.1.1"
 
 [default]
 title = ""
+directive = "warning"
 collapsible = true
+error_collapsible = true
+error_directive = "danger"
+collapsible_directives = ["warning"]
+disabled_directives = ["quote"]
+numbered = true
+numbered_directives = ["example"]
 css_id_prefix = "flam-"
+untitled_id_source = "index"
+element = "aside"
+collapsible_impl = "aria"
+density = "compact"
+style = "bar"
+title_layout = "stacked"
+marker = "start"
+title_anchor = false
+anchor_glyph = "#"
+absolute_anchor_links = true
+show_badge = true
+deny_warnings = true
+warn_unused_directives = true
+inline_icons = true
+collapse_controls = true
+avoid_page_break = true
+figure_wrap = true
+force_expand_non_html = false
+max_nesting = 3
+auto_collapse_lines = 20
+a11y_state_labels = true
+color_mode = "variable"
+class_style = "bem"
+print_expand_collapsed = true
+mkdocs_syntax = true
+skip_assets_check = true
+trim = "both"
+include = ["reference/**"]
+exclude = ["reference/drafts/*"]
+
+[default.titles]
+note = "Anmerkung"
 
 [renderer.test-mode]
 render_mode = "strip"
+strip_annotate = true
 
 [directive.custom.test-directive]
 icon = "/tmp/test-directive.svg"
 color = "#9B4F96"
 aliases = ["test-directive-alias-0"]
+renamed_from = ["test-directive-old"]
 title = "test-directive-title"
 collapsible = true
 
+[directive.custom.test-directive.alias_collapsible]
+test-directive-alias-0 = false
+
 [directive.builtin.warning]
 collapsible = true
+body_suffix = "Report issues to **compliance**."
+role = "alert"
+
+[presets.legal]
+directive = "warning"
+title = "Legal"
+body = "Not legal advice."
 "##;
 
         let serialized = toml::to_string(&input)?;
         assert_eq!(serialized, expected);
 
-        let actual = admonish_config_from_str(&serialized)?;
+        let actual = admonish_config_from_str(&serialized, Path::new("."))?;
         assert_eq!(actual, input);
         Ok(())
     }
+
+    #[test]
+    fn default_directive_validated() -> Result<()> {
+        // Builtin directives and aliases are okay
+        let actual =
+            admonish_config_from_str(r#"default = { directive = "caution" }"#, Path::new("."))?;
+        assert_eq!(actual.default.directive, Some("caution".to_owned()));
+
+        // Custom directives and aliases are okay
+        let actual = admonish_config_from_str(
+            r##"
+default = { directive = "toad" }
+directive.custom.frog = { icon = "./frog.svg", color = "#9004CC", aliases = ["toad"] }
+"##,
+            Path::new("."),
+        )?;
+        assert_eq!(actual.default.directive, Some("toad".to_owned()));
+
+        // Unknown directives are dropped, with a warning
+        let actual =
+            admonish_config_from_str(r#"default = { directive = "frog" }"#, Path::new("."))?;
+        assert_eq!(actual.default.directive, None);
+        assert_eq!(
+            actual.warnings,
+            vec!["Unknown default directive 'frog', falling back to 'note'".to_owned()]
+        );
+
+        // Disabled directives are dropped once, rather than warning for every block
+        let actual = admonish_config_from_str(
+            r#"default = { directive = "cite", disabled_directives = ["quote"] }"#,
+            Path::new("."),
+        )?;
+        assert_eq!(actual.default.directive, None);
+        assert_eq!(
+            actual.warnings,
+            vec!["Default directive 'cite' is disabled, falling back to 'note'".to_owned()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn directive_import_merged() -> Result<()> {
+        let book_dir = std::env::temp_dir().join(format!(
+            "mdbook-admonish-test-directive-import-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&book_dir)?;
+        fs::write(
+            book_dir.join("directives.toml"),
+            r##"
+[directive.custom.frog]
+icon = "./frog.svg"
+color = "#9004CC"
+title = "Imported"
+
+[directive.custom.newt]
+icon = "./newt.svg"
+color = "#0038A8"
+"##,
+        )?;
+
+        let actual = admonish_config_from_str(
+            r##"
+directive_import = "directives.toml"
+
+[directive.custom.frog]
+icon = "./frog.svg"
+color = "#9004CC"
+title = "Local"
+"##,
+            &book_dir,
+        )?;
+        // Local definitions take precedence
+        assert_eq!(
+            actual.directive.custom["frog"].title,
+            Some("Local".to_owned())
+        );
+        assert!(actual.directive.custom.contains_key("newt"));
+
+        // Missing files are an error
+        let error = admonish_config_from_str(r#"directive_import = "missing.toml""#, &book_dir)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("can't read directive import file"));
+
+        fs::remove_dir_all(book_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn custom_directive_named_color() -> Result<()> {
+        let actual = admonish_config_from_str(
+            r#"directive.custom.frog = { icon = "./frog.svg", color = "rebeccapurple" }"#,
+            Path::new("."),
+        )?;
+        assert_eq!(
+            actual.directive.custom["frog"].color,
+            Some(Color::from((102, 51, 153)).into())
+        );
+
+        let error = admonish_config_from_str(
+            r#"directive.custom.frog = { icon = "./frog.svg", color = "notacolor" }"#,
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert!(format!("{error:#}").contains("invalid color 'notacolor'"));
+        Ok(())
+    }
+
+    #[test]
+    fn error_directive_validated() -> Result<()> {
+        let actual = admonish_config_from_str(
+            r##"
+default = { error_directive = "toad" }
+directive.custom.frog = { icon = "./frog.svg", color = "#9004CC", aliases = ["toad"] }
+"##,
+            Path::new("."),
+        )?;
+        assert_eq!(actual.default.error_directive, Some("toad".to_owned()));
+
+        let error = admonish_config_from_str(r#"default.error_directive = "frog""#, Path::new("."))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid error directive 'frog', must be an enabled builtin or custom directive"
+        );
+
+        // Disabled directives would be replaced for every invalid block
+        let error = admonish_config_from_str(
+            r#"default = { error_directive = "bug", disabled_directives = ["bug"] }"#,
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid error directive 'bug', must be an enabled builtin or custom directive"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collapsible_directives_resolved() -> Result<()> {
+        let actual = admonish_config_from_str(
+            r##"
+default.collapsible_directives = ["caution", "toad", "note"]
+directive.custom.frog = { icon = "./frog.svg", color = "#9004CC", aliases = ["toad"] }
+"##,
+            Path::new("."),
+        )?;
+        assert_eq!(
+            actual.default.collapsible_directives,
+            vec!["warning".to_owned(), "frog".to_owned(), "note".to_owned()]
+        );
+
+        let error = admonish_config_from_str(
+            r#"default.collapsible_directives = ["frog"]"#,
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid directive 'frog' in 'default.collapsible_directives', must be a builtin or custom directive"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn numbered_directives_resolved() -> Result<()> {
+        let actual = admonish_config_from_str(
+            r#"default.numbered_directives = ["example", "hint"]"#,
+            Path::new("."),
+        )?;
+        assert_eq!(
+            actual.default.numbered_directives,
+            vec!["example".to_owned(), "tip".to_owned()]
+        );
+
+        let error = admonish_config_from_str(
+            r#"default.numbered_directives = ["exmaple"]"#,
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid directive 'exmaple' in 'default.numbered_directives', must be a builtin or custom directive"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_directives_must_be_builtin() -> Result<()> {
+        let actual =
+            admonish_config_from_str(r#"default.disabled_directives = ["cite"]"#, Path::new("."))?;
+        assert_eq!(actual.default.disabled_directives, vec!["cite".to_owned()]);
+
+        let error =
+            admonish_config_from_str(r#"default.disabled_directives = ["frog"]"#, Path::new("."))
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid disabled directive 'frog', only builtin directives can be disabled"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn custom_directive_extends() -> Result<()> {
+        let actual = admonish_config_from_str(
+            r##"
+directive.builtin.danger = { collapsible = true }
+directive.custom.critical = { extends = "danger", title = "Critical" }
+directive.custom.frog = { icon = "./frog.svg", color = "#9004CC", aliases = ["newt"] }
+directive.custom.toad = { extends = "newt", collapsible = false }
+directive.custom.eft = { extends = "toad", color = "red" }
+"##,
+            Path::new("."),
+        )?;
+        let custom = &actual.directive.custom;
+
+        // Inherited from a builtin directive, including its config
+        assert_eq!(
+            custom["critical"].icon,
+            Some(PathBuf::from("builtin:danger"))
+        );
+        assert_eq!(
+            custom["critical"].color,
+            Some(Color::from((255, 23, 68)).into())
+        );
+        assert_eq!(custom["critical"].collapsible, Some(true));
+        assert_eq!(custom["critical"].title, Some("Critical".to_owned()));
+
+        // Inherited through other custom directives, by name or alias
+        assert_eq!(custom["toad"].icon, Some(PathBuf::from("./frog.svg")));
+        assert_eq!(
+            custom["toad"].color,
+            Some(Color::from((144, 4, 204)).into())
+        );
+        assert_eq!(custom["eft"].icon, Some(PathBuf::from("./frog.svg")));
+        assert_eq!(custom["eft"].color, Some(Color::from((255, 0, 0)).into()));
+        assert_eq!(custom["eft"].collapsible, Some(false));
+        Ok(())
+    }
+
+    #[test]
+    fn custom_directive_extends_invalid() {
+        let error = |config: &str| {
+            admonish_config_from_str(config, Path::new("."))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(r#"directive.custom.frog = { extends = "newt" }"#),
+            "Custom directive 'frog' extends unknown directive 'newt'"
+        );
+        assert_eq!(
+            error(
+                r#"
+directive.custom.frog = { extends = "newt" }
+directive.custom.newt = { extends = "toad" }
+directive.custom.toad = { extends = "frog" }
+"#
+            ),
+            "Custom directives extend each other in a cycle: frog -> newt -> toad -> frog"
+        );
+        assert_eq!(
+            error(r#"directive.custom.frog = { icon = "./frog.svg" }"#),
+            "Custom directive 'frog' must set 'icon' and 'color', or 'extends' another directive"
+        );
+    }
+
+    #[test]
+    fn default_titles_must_be_builtin() -> Result<()> {
+        let actual = admonish_config_from_str(
+            r#"default.titles = { note = "Anmerkung", caution = "Vorsicht" }"#,
+            Path::new("."),
+        )?;
+        assert_eq!(actual.default.titles["note"], "Anmerkung");
+
+        let error =
+            admonish_config_from_str(r#"default.titles = { frog = "Frosch" }"#, Path::new("."))
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid default title for 'frog', only builtin directives can be given default titles"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn custom_style_shorthand() -> Result<()> {
+        let config = admonish_config_from_str(
+            r##"
+[directive.custom.frog]
+style = "frog.svg #9B4F96"
+
+[directive.custom.newt]
+style = "  builtin:tip   rgb(0, 128, 0) "
+
+[directive.custom.toad]
+icon = "toad.svg"
+color = "#9B4F96"
+"##,
+            Path::new("."),
+        )?;
+        let custom = &config.directive.custom;
+        assert_eq!(custom["frog"].icon, Some(PathBuf::from("frog.svg")));
+        assert_eq!(custom["frog"].color, custom["toad"].color);
+        assert_eq!(custom["frog"].style, None);
+        assert_eq!(custom["newt"].icon, Some(PathBuf::from("builtin:tip")));
+        assert_eq!(
+            custom["newt"].color,
+            Some(DirectiveColor::from(Color::from_str("green").unwrap()))
+        );
+
+        // Expanded before roundtripping
+        let roundtripped = admonish_config_from_str(&toml::to_string(&config)?, Path::new("."))?;
+        assert_eq!(roundtripped, config);
+
+        let error = |data: &str| {
+            format!(
+                "{:#}",
+                admonish_config_from_str(data, Path::new(".")).unwrap_err()
+            )
+        };
+        assert_eq!(
+            error(r#"directive.custom.frog = { style = "frog.svg" }"#),
+            r#"Invalid style 'frog.svg' for custom directive 'frog', expected an icon path and a color, e.g. "frog.svg #9B4F96""#
+        );
+        assert_eq!(
+            error(r#"directive.custom.frog = { style = "frog.svg notacolor" }"#),
+            "Invalid style for custom directive 'frog': invalid color 'notacolor', expected a hex color, CSS named color, rgb() or hsl() value"
+        );
+        assert_eq!(
+            error(r#"directive.custom.frog = { style = "frog.svg green", color = "red" }"#),
+            "Custom directive 'frog' must set either 'style', or 'icon' and 'color', not both"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn preset_directive_validated() {
+        let error = admonish_config_from_str(
+            r#"presets.legal = { directive = "frog", body = "Text" }"#,
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid directive 'frog' for preset 'legal', must be a builtin or custom directive"
+        );
+    }
+
+    #[test]
+    fn env_overrides() -> Result<()> {
+        let file_config = r#"
+on_failure = "continue"
+
+[renderer.html]
+strip_annotate = true
+"#;
+        let config_with_env = |vars: &[(&str, &str)]| -> Result<Config> {
+            let mut config = admonish_config_from_str(file_config, Path::new("."))?;
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            apply_env_overrides(&mut config, "html", |key| vars.get(key).cloned())?;
+            Ok(config)
+        };
+
+        // The file config is used as is
+        let base = config_with_env(&[])?;
+        assert_eq!(base, admonish_config_from_str(file_config, Path::new("."))?);
+
+        let config = config_with_env(&[
+            ("MDBOOK_ADMONISH_ON_FAILURE", "bail"),
+            ("MDBOOK_ADMONISH_RENDER_MODE", "strip"),
+            ("MDBOOK_ADMONISH_SKIP_ASSETS_CHECK", "true"),
+        ])?;
+        assert_eq!(
+            config,
+            Config {
+                on_failure: OnFailure::Bail,
+                default: AdmonitionDefaults {
+                    skip_assets_check: true,
+                    ..base.default.clone()
+                },
+                renderer: HashMap::from([(
+                    "html".to_owned(),
+                    RendererConfig {
+                        render_mode: Some(RenderMode::Strip),
+                        strip_annotate: true,
+                    }
+                )]),
+                ..base
+            }
+        );
+
+        assert_eq!(
+            format!(
+                "{:#}",
+                config_with_env(&[("MDBOOK_ADMONISH_ON_FAILURE", "stop")]).unwrap_err()
+            ),
+            "Invalid value 'stop' for environment variable MDBOOK_ADMONISH_ON_FAILURE: unknown variant `stop`, expected `bail` or `continue`"
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,20 @@
+// Expand or collapse all collapsible blocks on the page, from the controls
+// just before this script. This is inlined into the chapter, inside an HTML
+// block, so must not contain blank lines.
+(function () {
+  const controls = document.currentScript.previousElementSibling;
+  controls.addEventListener("click", (event) => {
+    const button = event.target.closest("button[data-admonish-expand]");
+    if (!button) {
+      return;
+    }
+    const open = button.dataset.admonishExpand === "true";
+    for (const details of document.querySelectorAll("details.admonition")) {
+      details.open = open;
+    }
+    for (const toggle of document.querySelectorAll("button.admonition-toggle")) {
+      toggle.setAttribute("aria-expanded", open);
+      document.getElementById(toggle.getAttribute("aria-controls")).hidden = !open;
+    }
+  });
+})();
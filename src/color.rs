@@ -0,0 +1,441 @@
+//! Parsing of colors given in configuration.
+
+use hex_color::HexColor;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A color given in configuration.
+///
+/// Accepts hex colors, CSS named colors and `rgb()`/`hsl()` functional notation.
+/// Always serialized back as a hex color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Color(HexColor);
+
+impl Color {
+    pub(crate) fn hex(self) -> HexColor {
+        self.0
+    }
+}
+
+impl From<HexColor> for Color {
+    fn from(hex: HexColor) -> Self {
+        Self(hex)
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from(rgb: (u8, u8, u8)) -> Self {
+        Self(HexColor::from(rgb))
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let value = input.trim().to_ascii_lowercase();
+        let color = if value.starts_with('#') {
+            HexColor::parse_rgb(&value).ok()
+        } else if let Some(args) = function_args(&value, "rgb") {
+            parse_rgb_function(args)
+        } else if let Some(args) = function_args(&value, "hsl") {
+            parse_hsl_function(args)
+        } else {
+            named_color(&value)
+        };
+        color
+            .map(Self)
+            .ok_or_else(|| format!("invalid color '{input}', expected a hex color, CSS named color, rgb() or hsl() value"))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display_rgb().fmt(f)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Alpha of the title bar background of a directive, unless configured.
+pub(crate) const DEFAULT_TINT: f64 = 0.1;
+
+/// The color of a custom directive.
+///
+/// Given either as a bare color, or as a table with an explicit `tint`, the
+/// alpha of the title bar background, e.g. `{ base = "#448aff", tint = 0.15 }`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DirectiveColor {
+    pub base: Color,
+    pub tint: f64,
+}
+
+impl DirectiveColor {
+    pub(crate) fn hex(self) -> HexColor {
+        self.base.hex()
+    }
+}
+
+// Tints are validated to be between 0 and 1, so are never NaN
+impl Eq for DirectiveColor {}
+
+impl From<Color> for DirectiveColor {
+    fn from(base: Color) -> Self {
+        Self {
+            base,
+            tint: DEFAULT_TINT,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TintedColor {
+    base: String,
+    tint: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DirectiveColorInput {
+    Bare(String),
+    Tinted(TintedColor),
+}
+
+impl Serialize for DirectiveColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        if self.tint == DEFAULT_TINT {
+            return self.base.serialize(serializer);
+        }
+        let mut state = serializer.serialize_struct("DirectiveColor", 2)?;
+        state.serialize_field("base", &self.base)?;
+        state.serialize_field("tint", &self.tint)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for DirectiveColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (base, tint) = match DirectiveColorInput::deserialize(deserializer)? {
+            DirectiveColorInput::Bare(base) => (base, None),
+            DirectiveColorInput::Tinted(TintedColor { base, tint }) => (base, tint),
+        };
+        let base = base.parse().map_err(de::Error::custom)?;
+        let tint = tint.unwrap_or(DEFAULT_TINT);
+        if !(0.0..=1.0).contains(&tint) {
+            return Err(de::Error::custom(format!(
+                "invalid tint '{tint}', expected a value between 0 and 1"
+            )));
+        }
+        Ok(Self { base, tint })
+    }
+}
+
+/// Returns the arguments of a functional notation, e.g. `rgb(1, 2, 3)` -> `["1", "2", "3"]`.
+///
+/// The legacy `rgba`/`hsla` names are accepted, and any alpha value is ignored.
+fn function_args<'a>(value: &'a str, name: &str) -> Option<Vec<&'a str>> {
+    let rest = value.strip_prefix(name)?;
+    let rest = rest.strip_prefix('a').unwrap_or(rest);
+    let inner = rest.trim().strip_prefix('(')?.strip_suffix(')')?;
+    // Drop any alpha component given after a slash
+    let inner = inner.split('/').next().unwrap_or_default();
+    let args: Vec<&str> = inner
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+    match args.len() {
+        3 | 4 => Some(args[..3].to_vec()),
+        _ => None,
+    }
+}
+
+/// Parse an rgb channel, given as `0-255` or a percentage.
+fn parse_channel(value: &str) -> Option<u8> {
+    let value = match value.strip_suffix('%') {
+        Some(percentage) => percentage.parse::<f64>().ok()? * 255.0 / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    (0.0..=255.0).contains(&value).then(|| value.round() as u8)
+}
+
+fn parse_percentage(value: &str) -> Option<f64> {
+    let value = value
+        .strip_suffix('%')
+        .unwrap_or(value)
+        .parse::<f64>()
+        .ok()?;
+    (0.0..=100.0).contains(&value).then_some(value / 100.0)
+}
+
+fn parse_rgb_function(args: Vec<&str>) -> Option<HexColor> {
+    Some(HexColor::rgb(
+        parse_channel(args[0])?,
+        parse_channel(args[1])?,
+        parse_channel(args[2])?,
+    ))
+}
+
+/// ref: https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+fn parse_hsl_function(args: Vec<&str>) -> Option<HexColor> {
+    let hue = args[0].strip_suffix("deg").unwrap_or(args[0]);
+    let hue = hue.parse::<f64>().ok()?.rem_euclid(360.0);
+    let saturation = parse_percentage(args[1])?;
+    let lightness = parse_percentage(args[2])?;
+
+    let channel = |n: f64| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        let value = lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+        (value * 255.0).round() as u8
+    };
+    Some(HexColor::rgb(channel(0.0), channel(8.0), channel(4.0)))
+}
+
+/// ref: https://www.w3.org/TR/css-color-4/#named-colors
+fn named_color(name: &str) -> Option<HexColor> {
+    let hex: u32 = match name {
+        "aliceblue" => 0xf0f8ff,
+        "antiquewhite" => 0xfaebd7,
+        "aqua" => 0x00ffff,
+        "aquamarine" => 0x7fffd4,
+        "azure" => 0xf0ffff,
+        "beige" => 0xf5f5dc,
+        "bisque" => 0xffe4c4,
+        "black" => 0x000000,
+        "blanchedalmond" => 0xffebcd,
+        "blue" => 0x0000ff,
+        "blueviolet" => 0x8a2be2,
+        "brown" => 0xa52a2a,
+        "burlywood" => 0xdeb887,
+        "cadetblue" => 0x5f9ea0,
+        "chartreuse" => 0x7fff00,
+        "chocolate" => 0xd2691e,
+        "coral" => 0xff7f50,
+        "cornflowerblue" => 0x6495ed,
+        "cornsilk" => 0xfff8dc,
+        "crimson" => 0xdc143c,
+        "cyan" => 0x00ffff,
+        "darkblue" => 0x00008b,
+        "darkcyan" => 0x008b8b,
+        "darkgoldenrod" => 0xb8860b,
+        "darkgray" | "darkgrey" => 0xa9a9a9,
+        "darkgreen" => 0x006400,
+        "darkkhaki" => 0xbdb76b,
+        "darkmagenta" => 0x8b008b,
+        "darkolivegreen" => 0x556b2f,
+        "darkorange" => 0xff8c00,
+        "darkorchid" => 0x9932cc,
+        "darkred" => 0x8b0000,
+        "darksalmon" => 0xe9967a,
+        "darkseagreen" => 0x8fbc8f,
+        "darkslateblue" => 0x483d8b,
+        "darkslategray" | "darkslategrey" => 0x2f4f4f,
+        "darkturquoise" => 0x00ced1,
+        "darkviolet" => 0x9400d3,
+        "deeppink" => 0xff1493,
+        "deepskyblue" => 0x00bfff,
+        "dimgray" | "dimgrey" => 0x696969,
+        "dodgerblue" => 0x1e90ff,
+        "firebrick" => 0xb22222,
+        "floralwhite" => 0xfffaf0,
+        "forestgreen" => 0x228b22,
+        "fuchsia" => 0xff00ff,
+        "gainsboro" => 0xdcdcdc,
+        "ghostwhite" => 0xf8f8ff,
+        "gold" => 0xffd700,
+        "goldenrod" => 0xdaa520,
+        "gray" | "grey" => 0x808080,
+        "green" => 0x008000,
+        "greenyellow" => 0xadff2f,
+        "honeydew" => 0xf0fff0,
+        "hotpink" => 0xff69b4,
+        "indianred" => 0xcd5c5c,
+        "indigo" => 0x4b0082,
+        "ivory" => 0xfffff0,
+        "khaki" => 0xf0e68c,
+        "lavender" => 0xe6e6fa,
+        "lavenderblush" => 0xfff0f5,
+        "lawngreen" => 0x7cfc00,
+        "lemonchiffon" => 0xfffacd,
+        "lightblue" => 0xadd8e6,
+        "lightcoral" => 0xf08080,
+        "lightcyan" => 0xe0ffff,
+        "lightgoldenrodyellow" => 0xfafad2,
+        "lightgray" | "lightgrey" => 0xd3d3d3,
+        "lightgreen" => 0x90ee90,
+        "lightpink" => 0xffb6c1,
+        "lightsalmon" => 0xffa07a,
+        "lightseagreen" => 0x20b2aa,
+        "lightskyblue" => 0x87cefa,
+        "lightslategray" | "lightslategrey" => 0x778899,
+        "lightsteelblue" => 0xb0c4de,
+        "lightyellow" => 0xffffe0,
+        "lime" => 0x00ff00,
+        "limegreen" => 0x32cd32,
+        "linen" => 0xfaf0e6,
+        "magenta" => 0xff00ff,
+        "maroon" => 0x800000,
+        "mediumaquamarine" => 0x66cdaa,
+        "mediumblue" => 0x0000cd,
+        "mediumorchid" => 0xba55d3,
+        "mediumpurple" => 0x9370db,
+        "mediumseagreen" => 0x3cb371,
+        "mediumslateblue" => 0x7b68ee,
+        "mediumspringgreen" => 0x00fa9a,
+        "mediumturquoise" => 0x48d1cc,
+        "mediumvioletred" => 0xc71585,
+        "midnightblue" => 0x191970,
+        "mintcream" => 0xf5fffa,
+        "mistyrose" => 0xffe4e1,
+        "moccasin" => 0xffe4b5,
+        "navajowhite" => 0xffdead,
+        "navy" => 0x000080,
+        "oldlace" => 0xfdf5e6,
+        "olive" => 0x808000,
+        "olivedrab" => 0x6b8e23,
+        "orange" => 0xffa500,
+        "orangered" => 0xff4500,
+        "orchid" => 0xda70d6,
+        "palegoldenrod" => 0xeee8aa,
+        "palegreen" => 0x98fb98,
+        "paleturquoise" => 0xafeeee,
+        "palevioletred" => 0xdb7093,
+        "papayawhip" => 0xffefd5,
+        "peachpuff" => 0xffdab9,
+        "peru" => 0xcd853f,
+        "pink" => 0xffc0cb,
+        "plum" => 0xdda0dd,
+        "powderblue" => 0xb0e0e6,
+        "purple" => 0x800080,
+        "rebeccapurple" => 0x663399,
+        "red" => 0xff0000,
+        "rosybrown" => 0xbc8f8f,
+        "royalblue" => 0x4169e1,
+        "saddlebrown" => 0x8b4513,
+        "salmon" => 0xfa8072,
+        "sandybrown" => 0xf4a460,
+        "seagreen" => 0x2e8b57,
+        "seashell" => 0xfff5ee,
+        "sienna" => 0xa0522d,
+        "silver" => 0xc0c0c0,
+        "skyblue" => 0x87ceeb,
+        "slateblue" => 0x6a5acd,
+        "slategray" | "slategrey" => 0x708090,
+        "snow" => 0xfffafa,
+        "springgreen" => 0x00ff7f,
+        "steelblue" => 0x4682b4,
+        "tan" => 0xd2b48c,
+        "teal" => 0x008080,
+        "thistle" => 0xd8bfd8,
+        "tomato" => 0xff6347,
+        "turquoise" => 0x40e0d0,
+        "violet" => 0xee82ee,
+        "wheat" => 0xf5deb3,
+        "white" => 0xffffff,
+        "whitesmoke" => 0xf5f5f5,
+        "yellow" => 0xffff00,
+        "yellowgreen" => 0x9acd32,
+        _ => return None,
+    };
+    let [_, r, g, b] = hex.to_be_bytes();
+    Some(HexColor::rgb(r, g, b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_colors() {
+        for (input, expected) in [
+            ("#9B4F96", (155, 79, 150)),
+            ("#fff", (255, 255, 255)),
+            ("rebeccapurple", (102, 51, 153)),
+            ("RebeccaPurple", (102, 51, 153)),
+            ("rgb(155, 79, 150)", (155, 79, 150)),
+            ("rgb(155 79 150 / 50%)", (155, 79, 150)),
+            ("rgba(100%, 0%, 0%, 0.5)", (255, 0, 0)),
+            ("hsl(0, 100%, 50%)", (255, 0, 0)),
+            ("hsl(120deg 100% 25%)", (0, 128, 0)),
+            ("hsl(270, 50%, 40%)", (102, 51, 153)),
+        ] {
+            assert_eq!(
+                input.parse::<Color>(),
+                Ok(Color::from(expected)),
+                "parsing '{input}'"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid_colors() {
+        for input in [
+            "",
+            "#12",
+            "notacolor",
+            "rgb(1, 2)",
+            "rgb(256, 0, 0)",
+            "hsl(0, 200%, 0%)",
+        ] {
+            assert_eq!(
+                input.parse::<Color>(),
+                Err(format!("invalid color '{input}', expected a hex color, CSS named color, rgb() or hsl() value")),
+            );
+        }
+    }
+
+    #[test]
+    fn serialize_as_hex() {
+        let color: Color = "rebeccapurple".parse().unwrap();
+        assert_eq!(serde_json::to_string(&color).unwrap(), r##""#663399""##);
+    }
+
+    #[test]
+    fn directive_color_tint() {
+        let color: DirectiveColor = serde_json::from_str(r#""rebeccapurple""#).unwrap();
+        assert_eq!(color, DirectiveColor::from(Color::from((102, 51, 153))));
+        assert_eq!(serde_json::to_string(&color).unwrap(), r##""#663399""##);
+
+        let color: DirectiveColor =
+            serde_json::from_str(r#"{ "base": "rebeccapurple", "tint": 0.25 }"#).unwrap();
+        assert_eq!(
+            color,
+            DirectiveColor {
+                base: Color::from((102, 51, 153)),
+                tint: 0.25,
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&color).unwrap(),
+            r##"{"base":"#663399","tint":0.25}"##
+        );
+
+        let error = serde_json::from_str::<DirectiveColor>(r#"{ "base": "red", "tint": 1.5 }"#)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("invalid tint '1.5', expected a value between 0 and 1"));
+        assert!(
+            serde_json::from_str::<DirectiveColor>(r#"{ "base": "notacolor" }"#)
+                .unwrap_err()
+                .to_string()
+                .starts_with("invalid color 'notacolor'")
+        );
+    }
+}
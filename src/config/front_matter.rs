@@ -0,0 +1,206 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::toml_wrangling::{format_toml_parsing_error, UserInput};
+use super::InstanceConfig;
+
+/// A `key: value` line in front matter.
+static RX_FRONT_MATTER_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*([A-Za-z_][A-Za-z0-9_-]*):\s*(.*?)\s*$"#).expect("front matter regex")
+});
+
+const FRONT_MATTER_SEPARATOR: &str = "---";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Wrapper<T> {
+    config: T,
+}
+
+/// Split leading front matter from the body of a block.
+///
+/// Front matter is one or more `key: value` lines, followed by a `---` line.
+/// If the body does not start with front matter, returns `None`, so a body
+/// that happens to contain a `---` line is left alone.
+pub(crate) fn split_front_matter(body: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        if line.trim() == FRONT_MATTER_SEPARATOR {
+            if offset == 0 {
+                return None;
+            }
+            return Some((&body[..offset], &body[offset + line.len()..]));
+        }
+        if !RX_FRONT_MATTER_LINE.is_match(line) {
+            return None;
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Parse a single front matter value.
+///
+/// Values that are valid TOML (such as `true` or `"quoted"`) are used as is,
+/// anything else is treated as a plain string.
+fn parse_value(value: &str) -> toml::Value {
+    match toml::from_str::<Wrapper<toml::Value>>(&format!("config = {value}")) {
+        Ok(wrapper) => wrapper.config,
+        Err(_) => toml::Value::String(value.to_owned()),
+    }
+}
+
+fn user_input_from_front_matter(front_matter: &str) -> Result<UserInput, String> {
+    let table = front_matter
+        .lines()
+        .filter_map(|line| RX_FRONT_MATTER_LINE.captures(line))
+        .map(|captures| (captures[1].to_owned(), parse_value(&captures[2])))
+        .collect::<toml::Table>();
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(format_toml_parsing_error)
+}
+
+impl InstanceConfig {
+    /// Apply options given in front matter, which take precedence over the
+    /// info string.
+    pub(crate) fn merge_front_matter(&mut self, front_matter: &str) -> Result<(), String> {
+        let config = user_input_from_front_matter(front_matter)?;
+        self.additional_classnames.extend(config.classnames()?);
+        self.attributes.extend(config.attributes()?);
+        if let Some(renderers) = config.renderers() {
+            self.renderers = Some(renderers);
+        }
+        if let Some(width) = config.width()? {
+            self.width = Some(width);
+        }
+        if let Some(lang) = config.lang()? {
+            self.lang = Some(lang);
+        }
+        if let Some(title_link) = config.title_link()? {
+            self.title_link = Some(title_link);
+        }
+        if let Some(directive) = config.r#type {
+            self.directive = directive;
+        }
+        if config.title.is_some() {
+            self.title = config.title;
+        }
+        if config.id.is_some() {
+            self.id = config.id;
+        }
+        if config.id_prefix.is_some() {
+            self.id_prefix = config.id_prefix;
+        }
+        if config.collapsible.is_some() {
+            self.collapsible = config.collapsible;
+        }
+        if config.src.is_some() {
+            self.src = config.src;
+        }
+        if config.badge.is_some() {
+            self.badge = config.badge;
+        }
+        if config.icon.is_some() {
+            self.icon = config.icon;
+        }
+        if config.caption.is_some() {
+            self.caption = config.caption;
+        }
+        if config.density.is_some() {
+            self.density = config.density;
+        }
+        if config.style.is_some() {
+            self.style = config.style;
+        }
+        if config.open.is_some() {
+            self.open = config.open;
+        }
+        if config.summary.is_some() {
+            self.summary = config.summary;
+        }
+        if config.align.is_some() {
+            self.align = config.align;
+        }
+        if config.content.is_some() {
+            self.body_format = config.content;
+        }
+        if config.inline.is_some() {
+            self.inline = config.inline;
+        }
+        if config.r#use.is_some() {
+            self.preset = config.r#use;
+        }
+        if config.role.is_some() {
+            self.role = config.role;
+        }
+        if config.aria_label.is_some() {
+            self.aria_label = config.aria_label;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_split_front_matter() {
+        assert_eq!(
+            split_front_matter("title: Foo\ncollapsible: true\n---\nBody\n---\nMore"),
+            Some(("title: Foo\ncollapsible: true\n", "Body\n---\nMore"))
+        );
+        // Indented blocks
+        assert_eq!(
+            split_front_matter("  title: Foo\n  ---\n  Body"),
+            Some(("  title: Foo\n", "  Body"))
+        );
+        // Not front matter
+        assert_eq!(split_front_matter("Body"), None);
+        assert_eq!(split_front_matter("---\nBody"), None);
+        assert_eq!(split_front_matter("Heading\n---\nBody"), None);
+        assert_eq!(split_front_matter("title: Foo\nBody"), None);
+    }
+
+    #[test]
+    fn test_merge_front_matter() {
+        let mut config = InstanceConfig {
+            directive: "note".to_owned(),
+            title: Some("Info string".to_owned()),
+            additional_classnames: vec!["a".to_owned()],
+            ..Default::default()
+        };
+        config
+            .merge_front_matter(
+                "title: Front matter\ncollapsible: true\nclass: b c\nid: \"my-id\"\n",
+            )
+            .unwrap();
+        assert_eq!(
+            config,
+            InstanceConfig {
+                directive: "note".to_owned(),
+                title: Some("Front matter".to_owned()),
+                id: Some("my-id".to_owned()),
+                additional_classnames: vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+                collapsible: Some(true),
+                ..Default::default()
+            }
+        );
+
+        let mut config = InstanceConfig::default();
+        config
+            .merge_front_matter("class: [\"a\", \"b\"]\n")
+            .unwrap();
+        assert_eq!(
+            config.additional_classnames,
+            vec!["a".to_owned(), "b".to_owned()]
+        );
+
+        assert!(InstanceConfig::default()
+            .merge_front_matter("collapsible: 3\n")
+            .unwrap_err()
+            .starts_with("TOML parsing error"));
+    }
+}
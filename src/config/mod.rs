@@ -1,7 +1,13 @@
+use crate::types::{Align, BlockStyle, BodyFormat, Density};
+
+mod front_matter;
 mod toml_wrangling;
 mod v1;
 mod v2;
 mod v3;
+mod v4;
+
+pub(crate) use front_matter::split_front_matter;
 
 /// Configuration as described by the instance of an admonition in markdown.
 ///
@@ -12,14 +18,54 @@ pub(crate) struct InstanceConfig {
     pub(crate) directive: String,
     pub(crate) title: Option<String>,
     pub(crate) id: Option<String>,
+    pub(crate) id_prefix: Option<String>,
     pub(crate) additional_classnames: Vec<String>,
+    /// Extra attributes for the outer element, as `(name, value)` pairs.
+    pub(crate) attributes: Vec<(String, String)>,
     pub(crate) collapsible: Option<bool>,
+    pub(crate) src: Option<String>,
+    pub(crate) badge: Option<bool>,
+    /// Whether to show the icon of the directive.
+    pub(crate) icon: Option<bool>,
+    pub(crate) caption: Option<String>,
+    pub(crate) density: Option<Density>,
+    pub(crate) style: Option<BlockStyle>,
+    /// Renderers to show this block for. If not set, shown for all renderers.
+    pub(crate) renderers: Option<Vec<String>>,
+    /// Whether a collapsible block starts expanded.
+    pub(crate) open: Option<bool>,
+    /// Text of the `<summary>` of a collapsible block, if different to the title.
+    pub(crate) summary: Option<String>,
+    /// Width of the block, as a CSS length.
+    pub(crate) width: Option<String>,
+    /// Side to float the block to.
+    pub(crate) align: Option<Align>,
+    /// Language of the content, for the `lang` attribute.
+    pub(crate) lang: Option<String>,
+    /// How the body is written.
+    pub(crate) body_format: Option<BodyFormat>,
+    /// Whether to render as an inline span, rather than a block.
+    pub(crate) inline: Option<bool>,
+    /// Name of a preset from the book config to fill in unset options from.
+    pub(crate) preset: Option<String>,
+    /// ARIA role of the block.
+    pub(crate) role: Option<String>,
+    /// Template for the `aria-label` of the block.
+    pub(crate) aria_label: Option<String>,
+    /// Link to wrap the title in.
+    pub(crate) title_link: Option<String>,
+}
+
+/// The first word of the info string of an admonition block.
+pub(crate) const ADMONISH_BLOCK_KEYWORD: &str = "admonish";
+
+/// Whether the info string is for an admonition block.
+pub(crate) fn is_admonition_info_string(info_string: &str) -> bool {
+    admonition_config_string(info_string).is_some()
 }
 
 /// Extract the remaining info string, if this is an admonition block.
 fn admonition_config_string(info_string: &str) -> Option<&str> {
-    const ADMONISH_BLOCK_KEYWORD: &str = "admonish";
-
     // Get the rest of the info string if this is an admonition
     if info_string == ADMONISH_BLOCK_KEYWORD {
         return Some("");
@@ -42,6 +88,11 @@ impl InstanceConfig {
 
     /// Parse an info string that is known to be for `admonish`.
     fn from_admonish_config_string(config_string: &str) -> Result<Self, String> {
+        // JSON can't be mistaken for any other format, so don't fall back
+        if v4::is_config_string(config_string) {
+            return v4::from_config_string(config_string);
+        }
+
         // If we succeed at parsing v3, return that. Otherwise hold onto the error
         let config_v3_error = match v3::from_config_string(config_string) {
             Ok(config) => return Ok(config),
@@ -84,6 +135,7 @@ mod test {
                 id: None,
                 additional_classnames: vec!["additional-classname".to_owned()],
                 collapsible: None,
+                ..Default::default()
             }
         );
         // v2 syntax is supported
@@ -99,6 +151,7 @@ mod test {
                 id: Some("my-id".to_owned()),
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             }
         );
         // v3 syntax is supported
@@ -114,7 +167,27 @@ mod test {
                 id: Some("my-id".to_owned()),
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
+            }
+        );
+        // v4 syntax is supported
+        assert_eq!(
+            InstanceConfig::from_info_string(
+                r#"admonish {"title": "Custom Title", "type": "question", "id": "my-id"}"#
+            )
+            .unwrap()
+            .unwrap(),
+            InstanceConfig {
+                directive: "question".to_owned(),
+                title: Some("Custom Title".to_owned()),
+                id: Some("my-id".to_owned()),
+                ..Default::default()
             }
         );
+        // Invalid JSON does not fall back to other formats
+        assert!(InstanceConfig::from_info_string(r#"admonish {"title": "#)
+            .unwrap()
+            .unwrap_err()
+            .starts_with("JSON parsing error"));
     }
 }
@@ -1,8 +1,12 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::Deserialize;
+use std::borrow::Cow;
 use std::fmt::Display;
 
+use super::InstanceConfig;
+use crate::types::{Align, BlockStyle, BodyFormat, Density};
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub(crate) struct UserInput {
     #[serde(default)]
@@ -12,28 +16,381 @@ pub(crate) struct UserInput {
     #[serde(default)]
     pub id: Option<String>,
     #[serde(default)]
-    pub class: Option<String>,
+    pub id_prefix: Option<String>,
+    #[serde(default)]
+    pub class: Option<ClassInput>,
     #[serde(default)]
     pub collapsible: Option<bool>,
+    #[serde(default)]
+    pub src: Option<String>,
+    #[serde(default)]
+    pub badge: Option<bool>,
+    #[serde(default)]
+    pub icon: Option<bool>,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub density: Option<Density>,
+    #[serde(default)]
+    pub style: Option<BlockStyle>,
+    #[serde(default)]
+    pub renderers: Option<String>,
+    #[serde(default)]
+    pub open: Option<bool>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub attrs: Option<String>,
+    #[serde(default)]
+    pub width: Option<String>,
+    #[serde(default)]
+    pub align: Option<Align>,
+    #[serde(default)]
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub content: Option<BodyFormat>,
+    #[serde(default)]
+    pub inline: Option<bool>,
+    #[serde(default)]
+    pub r#use: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub aria_label: Option<String>,
+    #[serde(default)]
+    pub title_link: Option<String>,
+}
+
+/// Classnames given by the user, either space separated or as a list.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ClassInput {
+    Spaced(String),
+    List(Vec<String>),
 }
 
 impl UserInput {
-    pub fn classnames(&self) -> Vec<String> {
-        self.class
-            .as_ref()
-            .map(|class| {
-                class
-                    .split(' ')
-                    .filter(|classname| !classname.is_empty())
-                    .map(|classname| classname.to_owned())
-                    .collect()
+    /// Comma separated renderers given by the user.
+    pub fn renderers(&self) -> Option<Vec<String>> {
+        self.renderers.as_ref().map(|renderers| {
+            renderers
+                .split(',')
+                .map(str::trim)
+                .filter(|renderer| !renderer.is_empty())
+                .map(|renderer| renderer.to_owned())
+                .collect()
+        })
+    }
+
+    /// Classnames given by the user, space separated or as a list.
+    ///
+    /// Returns an error if any classname is not a valid CSS classname, as it
+    /// would break the HTML `class` attribute.
+    pub fn classnames(&self) -> Result<Vec<String>, String> {
+        let classnames: Vec<&str> = match &self.class {
+            Some(ClassInput::Spaced(class)) => class
+                .split(' ')
+                .filter(|classname| !classname.is_empty())
+                .collect(),
+            Some(ClassInput::List(classnames)) => classnames.iter().map(String::as_str).collect(),
+            None => Vec::new(),
+        };
+        classnames
+            .into_iter()
+            .map(|classname| {
+                if RX_CLASSNAME.is_match(classname) {
+                    Ok(classname.to_owned())
+                } else {
+                    Err(format!("'{classname}' is not a valid CSS classname."))
+                }
             })
+            .collect()
+    }
+
+    /// Width given by the user.
+    ///
+    /// Returns an error if the width is not a CSS length, as it is used in an
+    /// inline style.
+    pub fn width(&self) -> Result<Option<String>, String> {
+        match self.width.as_deref() {
+            Some(width) if RX_CSS_LENGTH.is_match(width) => Ok(Some(width.to_owned())),
+            Some(width) => Err(format!(
+                "'{width}' is not a valid width, expected a CSS length such as '300px' or '40%'."
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Language of the content given by the user.
+    ///
+    /// Returns an error if the language is not a plausible language tag, such
+    /// as `en-GB`, or identifier, such as `rust`.
+    pub fn lang(&self) -> Result<Option<String>, String> {
+        match self.lang.as_deref() {
+            Some(lang) if RX_LANG.is_match(lang) => Ok(Some(lang.to_owned())),
+            Some(lang) => Err(format!(
+                "'{lang}' is not a valid lang, expected a language tag such as 'en-GB' or 'rust'."
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Link for the title given by the user.
+    ///
+    /// Returns an error unless the link is a web or mail URL, or a relative
+    /// path, so that users can't add `javascript:` links.
+    pub fn title_link(&self) -> Result<Option<String>, String> {
+        match self.title_link.as_deref() {
+            Some(link) if is_safe_link(link) => Ok(Some(link.to_owned())),
+            Some(link) => Err(format!(
+                "'{link}' is not a valid title link, expected a URL such as 'https://example.com' or a relative path."
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Space separated `name=value` attributes given by the user.
+    ///
+    /// Returns an error if any attribute is not allowed, so that users can't
+    /// add event handlers or override the attributes we set.
+    pub fn attributes(&self) -> Result<Vec<(String, String)>, String> {
+        self.attrs
+            .as_deref()
             .unwrap_or_default()
+            .split(' ')
+            .filter(|attribute| !attribute.is_empty())
+            .map(|attribute| {
+                let (name, value) = attribute.split_once('=').ok_or_else(|| {
+                    format!("'{attribute}' is not a valid attribute, expected 'name=value'.")
+                })?;
+                if !(RX_DATA_ATTRIBUTE.is_match(name) || SAFE_ATTRIBUTES.contains(&name)) {
+                    return Err(format!(
+                        "'{name}' is not an allowed attribute. Only 'data-*' attributes, and {} are allowed.",
+                        SAFE_ATTRIBUTES
+                            .iter()
+                            .map(|name| format!("'{name}'"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+                Ok((name.to_owned(), value.to_owned()))
+            })
+            .collect()
+    }
+}
+
+/// Validate options given by the user, shared by every format that parses to
+/// `UserInput`.
+impl TryFrom<UserInput> for InstanceConfig {
+    type Error = String;
+
+    fn try_from(config: UserInput) -> Result<Self, Self::Error> {
+        let additional_classnames = config.classnames()?;
+        let attributes = config.attributes()?;
+        let renderers = config.renderers();
+        let width = config.width()?;
+        let lang = config.lang()?;
+        let title_link = config.title_link()?;
+        Ok(Self {
+            directive: config.r#type.unwrap_or_default(),
+            title: config.title,
+            id: config.id,
+            id_prefix: config.id_prefix,
+            additional_classnames,
+            attributes,
+            collapsible: config.collapsible,
+            src: config.src,
+            badge: config.badge,
+            icon: config.icon,
+            caption: config.caption,
+            density: config.density,
+            style: config.style,
+            renderers,
+            open: config.open,
+            summary: config.summary,
+            width,
+            align: config.align,
+            lang,
+            body_format: config.content,
+            inline: config.inline,
+            preset: config.r#use,
+            role: config.role,
+            aria_label: config.aria_label,
+            title_link,
+        })
     }
 }
 
+/// Directives may contain unicode letters, numbers and combining marks, as well
+/// as `_` and `-`.
+///
+/// Directives are used in CSS classnames, so whitespace and punctuation are not allowed.
 pub(crate) static RX_DIRECTIVE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"^[A-Za-z0-9_-]+$"#).expect("directive regex"));
+    Lazy::new(|| Regex::new(r#"^[\p{Alphabetic}\p{Nd}\p{M}_-]+$"#).expect("directive regex"));
+
+/// Classnames may contain anything except whitespace, quotes, `<`, `>`, `&` and
+/// control characters, which would break out of the `class` attribute.
+static RX_CLASSNAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^[^\s"'<>&\p{Cc}]+$"#).expect("classname regex"));
+
+/// A positive CSS length or percentage, such as `300px` or `40%`.
+static RX_CSS_LENGTH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^(\d+(\.\d+)?|\.\d+)(px|em|rem|ch|ex|vw|vh|cm|mm|in|pt|pc|%)$"#)
+        .expect("CSS length regex")
+});
+
+/// A BCP 47 language tag, or an identifier such as a programming language.
+static RX_LANG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^[A-Za-z][A-Za-z0-9_+]{0,31}(-[A-Za-z0-9]{1,8})*$"#).expect("lang regex")
+});
+
+/// A URL scheme, such as `https:`, at the start of a link.
+static RX_URL_SCHEME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^([A-Za-z][A-Za-z0-9+.-]*):"#).expect("URL scheme regex"));
+
+/// Schemes that are safe for links given by users.
+const SAFE_URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Whether `link` is a URL with a safe scheme, or a relative path.
+fn is_safe_link(link: &str) -> bool {
+    if link.is_empty() || link.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
+    match RX_URL_SCHEME.captures(link) {
+        Some(captures) => SAFE_URL_SCHEMES
+            .iter()
+            .any(|scheme| scheme.eq_ignore_ascii_case(&captures[1])),
+        None => true,
+    }
+}
+
+/// `data-*` attributes, which have no effect on their own.
+static RX_DATA_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^data-[A-Za-z0-9_.-]+$"#).expect("data attribute regex"));
+
+/// Other attributes that are safe for users to set.
+const SAFE_ATTRIBUTES: [&str; 4] = ["dir", "lang", "title", "translate"];
+
+/// A quoted TOML string, to skip over, or a `key=value` pair with a bare value.
+static RX_BARE_VALUE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#""(?:[^"\\]|\\.)*"|'[^']*'|(?P<before>^|[\s,])(?P<key>[A-Za-z_][A-Za-z0-9_]*)(?P<equals>\s*=\s*)(?P<value>[^\s,"'{}\[\]=#\\]+)"#,
+    )
+    .expect("bare value regex")
+});
+
+/// Keys whose values are strings, so may be given without quotes.
+const STRING_KEYS: [&str; 20] = [
+    "type",
+    "title",
+    "id",
+    "id_prefix",
+    "class",
+    "src",
+    "caption",
+    "density",
+    "style",
+    "renderers",
+    "summary",
+    "attrs",
+    "width",
+    "align",
+    "lang",
+    "content",
+    "use",
+    "role",
+    "aria_label",
+    "title_link",
+];
+
+/// Quote bare values of string keys, such as `id=my-id`, so they parse as
+/// TOML strings.
+///
+/// Values of other keys, such as `collapsible=true`, are already valid TOML,
+/// and are left as is.
+pub(crate) fn quote_bare_values(pairs: &str) -> Cow<'_, str> {
+    RX_BARE_VALUE.replace_all(pairs, |captures: &regex::Captures| {
+        match (captures.name("key"), captures.name("value")) {
+            (Some(key), Some(value)) if STRING_KEYS.contains(&key.as_str()) => format!(
+                r#"{}{}{}"{}""#,
+                &captures["before"],
+                key.as_str(),
+                &captures["equals"],
+                value.as_str()
+            ),
+            _ => captures[0].to_owned(),
+        }
+    })
+}
+
+/// A `key: value` pair, with at least one space after the colon.
+static RX_COLON_PAIR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^\s*(?P<key>[A-Za-z_][A-Za-z0-9_]*):\s+(?P<value>.*?)\s*$"#)
+        .expect("colon pair regex")
+});
+
+/// Convert YAML style `key: value` pairs, such as `title: Foo, collapsible: true`,
+/// to `key=value` pairs.
+///
+/// Pairs are separated by commas outside of quotes and brackets, so values
+/// may contain colons, such as URLs. Unquoted values of string keys are
+/// quoted, and may contain spaces. Other pairs are left as is.
+pub(crate) fn colon_pairs_to_equals(pairs: &str) -> Cow<'_, str> {
+    let segments = split_top_level_commas(pairs);
+    if !segments
+        .iter()
+        .any(|segment| RX_COLON_PAIR.is_match(segment))
+    {
+        return Cow::Borrowed(pairs);
+    }
+    Cow::Owned(
+        segments
+            .into_iter()
+            .map(|segment| match RX_COLON_PAIR.captures(segment) {
+                Some(captures) => {
+                    let (key, value) = (&captures["key"], &captures["value"]);
+                    let is_literal = value.starts_with(['"', '\'', '[', '{']);
+                    if STRING_KEYS.contains(&key) && !is_literal {
+                        format!("{key}={}", toml::Value::String(value.to_owned()))
+                    } else {
+                        format!("{key}={value}")
+                    }
+                }
+                None => segment.trim().to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+/// Split on commas that are not inside quotes or brackets.
+fn split_top_level_commas(pairs: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in pairs.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    segments.push(&pairs[start..index]);
+                    start = index + 1;
+                }
+                _ => {}
+            },
+        }
+    }
+    segments.push(&pairs[start..]);
+    segments
+}
 
 pub(crate) fn format_toml_parsing_error(error: impl Display) -> String {
     format!("TOML parsing error: {error}")
@@ -42,3 +399,240 @@ pub(crate) fn format_toml_parsing_error(error: impl Display) -> String {
 pub(crate) fn format_invalid_directive(directive: &str, original_error: impl Display) -> String {
     format!("'{directive}' is not a valid directive or TOML key-value pair.\n\n{original_error}")
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn user_input() -> UserInput {
+        UserInput {
+            r#type: None,
+            title: None,
+            id: None,
+            id_prefix: None,
+            class: None,
+            collapsible: None,
+            src: None,
+            badge: None,
+            icon: None,
+            caption: None,
+            density: None,
+            style: None,
+            renderers: None,
+            open: None,
+            summary: None,
+            attrs: None,
+            width: None,
+            align: None,
+            lang: None,
+            content: None,
+            inline: None,
+            r#use: None,
+            role: None,
+            aria_label: None,
+            title_link: None,
+        }
+    }
+
+    fn classnames(class: &str) -> Result<Vec<String>, String> {
+        UserInput {
+            class: Some(ClassInput::Spaced(class.to_owned())),
+            ..user_input()
+        }
+        .classnames()
+    }
+
+    #[test]
+    fn test_classnames() {
+        assert_eq!(
+            classnames("  a  b-c _d 注意 -e1 "),
+            Ok(vec![
+                "a".to_owned(),
+                "b-c".to_owned(),
+                "_d".to_owned(),
+                "注意".to_owned(),
+                "-e1".to_owned()
+            ])
+        );
+        // Utility classes, such as from Tailwind
+        assert_eq!(
+            classnames("md:flex w-1/2 2xl"),
+            Ok(vec![
+                "md:flex".to_owned(),
+                "w-1/2".to_owned(),
+                "2xl".to_owned()
+            ])
+        );
+        assert_eq!(classnames(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let attributes = |attrs: &str| {
+            UserInput {
+                attrs: Some(attrs.to_owned()),
+                ..user_input()
+            }
+            .attributes()
+        };
+        assert_eq!(
+            attributes(" data-foo=bar  lang=en data-empty="),
+            Ok(vec![
+                ("data-foo".to_owned(), "bar".to_owned()),
+                ("lang".to_owned(), "en".to_owned()),
+                ("data-empty".to_owned(), "".to_owned()),
+            ])
+        );
+        assert_eq!(
+            attributes("onclick=alert(1)"),
+            Err("'onclick' is not an allowed attribute. Only 'data-*' attributes, and 'dir', 'lang', 'title', 'translate' are allowed.".to_owned())
+        );
+        assert!(attributes("style=color:red").is_err());
+        assert!(attributes("data-=x").is_err());
+        assert_eq!(
+            attributes("data-foo"),
+            Err("'data-foo' is not a valid attribute, expected 'name=value'.".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_width() {
+        let width = |width: &str| {
+            UserInput {
+                width: Some(width.to_owned()),
+                ..user_input()
+            }
+            .width()
+        };
+        assert_eq!(width("300px"), Ok(Some("300px".to_owned())));
+        assert_eq!(width("40%"), Ok(Some("40%".to_owned())));
+        assert_eq!(width("12.5em"), Ok(Some("12.5em".to_owned())));
+        assert_eq!(user_input().width(), Ok(None));
+        for invalid in ["300", "-1px", "300px; color: red", "auto", ""] {
+            assert!(width(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_lang() {
+        let lang = |lang: &str| {
+            UserInput {
+                lang: Some(lang.to_owned()),
+                ..user_input()
+            }
+            .lang()
+        };
+        for valid in ["en", "en-GB", "zh-Hant-TW", "rust", "c++", "objective_c"] {
+            assert_eq!(lang(valid), Ok(Some(valid.to_owned())));
+        }
+        assert_eq!(user_input().lang(), Ok(None));
+        for invalid in [
+            "",
+            "en GB",
+            "\"><script>",
+            "-en",
+            "en-",
+            "toolongsubtag-abcdefghi",
+        ] {
+            assert!(lang(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_classnames_invalid() {
+        for (class, invalid) in [
+            (r#"a" onclick="alert(1)"#, r#"a""#),
+            ("a <script>", "<script>"),
+            ("a>b", "a>b"),
+            ("a 'b'", "'b'"),
+            ("a&amp;", "a&amp;"),
+            ("a\tb", "a\tb"),
+        ] {
+            assert_eq!(
+                classnames(class),
+                Err(format!("'{invalid}' is not a valid CSS classname."))
+            );
+        }
+    }
+
+    #[test]
+    fn test_quote_bare_values() {
+        for (pairs, expected) in [
+            ("id=my-id", r#"id="my-id""#),
+            (
+                "collapsible=true, id = my-id,title=Note",
+                r#"collapsible=true, id = "my-id",title="Note""#,
+            ),
+            ("width=40%, open=false", r#"width="40%", open=false"#),
+            // Numbers are strings for string keys only
+            ("id=123, collapsible=1", r#"id="123", collapsible=1"#),
+            // Quoted values are left alone, even if they look like pairs
+            (
+                r#"title="id=x", class='a b' id=y"#,
+                r#"title="id=x", class='a b' id="y""#,
+            ),
+            (r#"title="Say \"id=x\"""#, r#"title="Say \"id=x\"""#),
+            // Values that aren't barewords are left for TOML to report
+            ("title=a b", r#"title="a" b"#),
+            ("data-id=x", "data-id=x"),
+        ] {
+            assert_eq!(quote_bare_values(pairs), expected, "pairs: {pairs}");
+        }
+    }
+
+    #[test]
+    fn test_is_safe_link() {
+        for link in [
+            "https://example.com/a?b=c#d",
+            "HTTP://example.com",
+            "mailto:me@example.com",
+            "other.md",
+            "../other.md#section",
+            "/absolute/path",
+            "#fragment",
+        ] {
+            assert!(is_safe_link(link), "{link}");
+        }
+        for link in [
+            "",
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "data:text/html,hi",
+            "https://example.com/with space",
+        ] {
+            assert!(!is_safe_link(link), "{link}");
+        }
+    }
+
+    #[test]
+    fn test_colon_pairs_to_equals() {
+        for (pairs, expected) in [
+            ("title: Foo", r#"title="Foo""#),
+            (
+                "title: Note: read this, collapsible: true",
+                r#"title="Note: read this", collapsible=true"#,
+            ),
+            (
+                r#"title: "Quoted, with comma", class: ["a", "b"]"#,
+                r#"title="Quoted, with comma", class=["a", "b"]"#,
+            ),
+            (
+                "title_link: https://example.com/a?b=c",
+                r#"title_link="https://example.com/a?b=c""#,
+            ),
+            (r#"title: Say "hi""#, r#"title='Say "hi"'"#),
+            // Mixed with equals pairs
+            (r#"id=my-id, title: Foo"#, r#"id=my-id, title="Foo""#),
+            // Left alone
+            (r#"title="a: b", id="x""#, r#"title="a: b", id="x""#),
+            (
+                "title_link=https://example.com",
+                "title_link=https://example.com",
+            ),
+            ("note title:Foo", "note title:Foo"),
+        ] {
+            assert_eq!(colon_pairs_to_equals(pairs), expected, "{pairs}");
+        }
+    }
+}
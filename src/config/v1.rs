@@ -54,7 +54,27 @@ pub(crate) fn from_config_string(config_string: &str) -> Result<InstanceConfig,
         title,
         id: None,
         additional_classnames,
+        attributes: Vec::new(),
         collapsible: None,
+        src: None,
+        id_prefix: None,
+        badge: None,
+        icon: None,
+        caption: None,
+        density: None,
+        style: None,
+        renderers: None,
+        open: None,
+        summary: None,
+        width: None,
+        align: None,
+        lang: None,
+        body_format: None,
+        inline: None,
+        preset: None,
+        role: None,
+        aria_label: None,
+        title_link: None,
     })
 }
 
@@ -73,6 +93,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -83,6 +104,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -93,6 +115,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -103,6 +126,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -113,6 +137,7 @@ mod test {
                 id: None,
                 additional_classnames: vec!["additional-classname".to_owned()],
                 collapsible: None,
+                ..Default::default()
             }
         );
     }
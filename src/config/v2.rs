@@ -65,23 +65,7 @@ pub(crate) fn from_config_string(config_string: &str) -> Result<InstanceConfig,
             config
         }
     };
-    let additional_classnames = config
-        .class
-        .map(|class| {
-            class
-                .split(' ')
-                .filter(|classname| !classname.is_empty())
-                .map(|classname| classname.to_owned())
-                .collect()
-        })
-        .unwrap_or_default();
-    Ok(InstanceConfig {
-        directive: config.r#type.unwrap_or_default(),
-        title: config.title,
-        id: config.id,
-        additional_classnames,
-        collapsible: config.collapsible,
-    })
+    config.try_into()
 }
 
 #[cfg(test)]
@@ -108,6 +92,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         check(
@@ -118,6 +103,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         check(
@@ -128,6 +114,7 @@ mod test {
                 id: None,
                 additional_classnames: vec!["additional".to_owned(), "classname".to_owned()],
                 collapsible: Some(true),
+                ..Default::default()
             },
         )?;
         // Specifying unknown keys is okay, as long as they're valid
@@ -139,6 +126,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         // Just directive is fine
@@ -150,6 +138,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         // Directive plus toml config
@@ -161,6 +150,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: Some(false),
+                ..Default::default()
             },
         )?;
         // Test custom id
@@ -172,6 +162,7 @@ mod test {
                 id: Some("my-info-custom-id".to_owned()),
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         // Directive after toml config is an error
@@ -1,5 +1,6 @@
 use super::toml_wrangling::{
-    format_invalid_directive, format_toml_parsing_error, UserInput, RX_DIRECTIVE,
+    colon_pairs_to_equals, format_invalid_directive, format_toml_parsing_error, quote_bare_values,
+    UserInput, RX_DIRECTIVE,
 };
 use super::InstanceConfig;
 use serde::Deserialize;
@@ -11,7 +12,10 @@ struct Wrapper<T> {
 
 /// Transform our config string into valid toml
 fn bare_inline_table_to_toml(pairs: &str) -> String {
-    format!("config = {{ {pairs} }}")
+    format!(
+        "config = {{ {} }}",
+        quote_bare_values(&colon_pairs_to_equals(pairs))
+    )
 }
 
 fn user_input_from_config_string(config_string: &str) -> Result<UserInput, String> {
@@ -52,19 +56,13 @@ pub(crate) fn from_config_string(config_string: &str) -> Result<InstanceConfig,
         }
     };
 
-    let additional_classnames = config.classnames();
-    Ok(InstanceConfig {
-        directive: config.r#type.unwrap_or_default(),
-        title: config.title,
-        id: config.id,
-        additional_classnames,
-        collapsible: config.collapsible,
-    })
+    config.try_into()
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::types::Density;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -88,6 +86,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         check(
@@ -98,6 +97,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         check(
@@ -108,6 +108,7 @@ mod test {
                 id: None,
                 additional_classnames: vec!["additional".to_owned(), "classname".to_owned()],
                 collapsible: Some(true),
+                ..Default::default()
             },
         )?;
         // Specifying unknown keys is okay, as long as they're valid
@@ -119,6 +120,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         // Just directive is fine
@@ -130,6 +132,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         // Directive plus toml config
@@ -141,6 +144,7 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: Some(false),
+                ..Default::default()
             },
         )?;
         // Test custom id
@@ -152,10 +156,28 @@ mod test {
                 id: Some("my-info-custom-id".to_owned()),
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
         // Directive after toml config is an error
         assert!(from_config_string(r#"title="Information" info"#).is_err());
+        // Unicode directives
+        check(
+            r#"注意 title="Attention""#,
+            InstanceConfig {
+                directive: "注意".to_owned(),
+                title: Some("Attention".to_owned()),
+                ..Default::default()
+            },
+        )?;
+        // Combining marks (e followed by a combining acute accent)
+        check(
+            "cafe\u{301}",
+            InstanceConfig {
+                directive: "cafe\u{301}".to_owned(),
+                ..Default::default()
+            },
+        )?;
         // HTML with quotes inside content
         // Note that we use toml literal (single quoted) strings here
         check(
@@ -166,12 +188,54 @@ mod test {
                 id: None,
                 additional_classnames: Vec::new(),
                 collapsible: None,
+                ..Default::default()
             },
         )?;
 
         Ok(())
     }
 
+    #[test]
+    fn test_from_config_string_renderers() {
+        assert_eq!(
+            from_config_string(r#"note renderers="html, pdf""#).unwrap(),
+            InstanceConfig {
+                directive: "note".to_owned(),
+                renderers: Some(vec!["html".to_owned(), "pdf".to_owned()]),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_config_string_bare_values() {
+        assert_eq!(
+            from_config_string(
+                r#"note id=my-id, title="Quoted title", collapsible=true, density=compact"#
+            )
+            .unwrap(),
+            InstanceConfig {
+                directive: "note".to_owned(),
+                title: Some("Quoted title".to_owned()),
+                id: Some("my-id".to_owned()),
+                collapsible: Some(true),
+                density: Some(Density::Compact),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            from_config_string("type=warning, title=Careful, class=wide").unwrap(),
+            InstanceConfig {
+                directive: "warning".to_owned(),
+                title: Some("Careful".to_owned()),
+                additional_classnames: vec!["wide".to_owned()],
+                ..Default::default()
+            }
+        );
+        // Bare values can't contain spaces
+        assert!(from_config_string("note title=Two words").is_err());
+    }
+
     #[test]
     fn test_from_config_string_invalid_directive() {
         assert_eq!(
@@ -187,6 +251,71 @@ expected `.`, `=`
         );
     }
 
+    #[test]
+    fn test_from_config_string_invalid_unicode_directive() {
+        // Punctuation and quotes would produce invalid CSS classnames
+        for config_string in ["注意!", "注意\"", "注意。"] {
+            assert!(
+                from_config_string(config_string).is_err(),
+                "expected '{config_string}' to be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_config_string_colon_pairs() {
+        let expected = InstanceConfig {
+            directive: "note".to_owned(),
+            title: Some("Read this: carefully".to_owned()),
+            collapsible: Some(true),
+            ..Default::default()
+        };
+        for config_string in [
+            r#"type: note, title: Read this: carefully, collapsible: true"#,
+            r#"note title: Read this: carefully, collapsible: true"#,
+            r#"note title="Read this: carefully", collapsible=true"#,
+        ] {
+            assert_eq!(
+                from_config_string(config_string).unwrap(),
+                expected,
+                "{config_string}"
+            );
+        }
+        // Invalid values are still reported
+        assert!(from_config_string("note collapsible: yes")
+            .unwrap_err()
+            .starts_with("TOML parsing error"));
+    }
+
+    #[test]
+    fn test_from_config_string_classname_list() {
+        assert_eq!(
+            from_config_string(r#"note class=["a", "b-c"], title="List""#).unwrap(),
+            InstanceConfig {
+                directive: "note".to_owned(),
+                title: Some("List".to_owned()),
+                additional_classnames: vec!["a".to_owned(), "b-c".to_owned()],
+                ..Default::default()
+            }
+        );
+        // Each item is a single classname
+        assert_eq!(
+            from_config_string(r#"note class=["a b"]"#).unwrap_err(),
+            "'a b' is not a valid CSS classname."
+        );
+        assert!(from_config_string("note class=[1]")
+            .unwrap_err()
+            .starts_with("TOML parsing error"));
+    }
+
+    #[test]
+    fn test_from_config_string_invalid_classname() {
+        assert_eq!(
+            from_config_string(r#"note class='a"b'"#).unwrap_err(),
+            r#"'a"b' is not a valid CSS classname."#
+        );
+    }
+
     #[test]
     fn test_from_config_string_invalid_toml_value() {
         assert_eq!(
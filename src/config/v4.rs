@@ -0,0 +1,71 @@
+use super::toml_wrangling::UserInput;
+use super::InstanceConfig;
+
+/// Whether the config string is in v4 format, a JSON object.
+pub(crate) fn is_config_string(config_string: &str) -> bool {
+    config_string.trim_start().starts_with('{')
+}
+
+fn format_json_parsing_error(error: serde_json::Error) -> String {
+    format!("JSON parsing error: {error}")
+}
+
+/// Parse and return the config assuming v4 format.
+///
+/// This is a JSON object with the same keys as v3, for books generated by
+/// other tools, which would otherwise have to get the TOML quoting right.
+pub(crate) fn from_config_string(config_string: &str) -> Result<InstanceConfig, String> {
+    serde_json::from_str::<UserInput>(config_string.trim())
+        .map_err(format_json_parsing_error)?
+        .try_into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Density;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_is_config_string() {
+        assert!(is_config_string("{}"));
+        assert!(is_config_string(r#" {"type": "note"}"#));
+        assert!(!is_config_string(""));
+        assert!(!is_config_string(r#"note title="{}""#));
+    }
+
+    #[test]
+    fn test_from_config_string_v4() {
+        assert_eq!(from_config_string("{}").unwrap(), InstanceConfig::default());
+        assert_eq!(
+            from_config_string(
+                r#"{"type": "note", "title": "Say \"hi\", it's = {fine}", "class": "a b", "collapsible": true, "density": "compact"}"#
+            )
+            .unwrap(),
+            InstanceConfig {
+                directive: "note".to_owned(),
+                title: Some(r#"Say "hi", it's = {fine}"#.to_owned()),
+                additional_classnames: vec!["a".to_owned(), "b".to_owned()],
+                collapsible: Some(true),
+                density: Some(Density::Compact),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_from_config_string_invalid() {
+        assert_eq!(
+            from_config_string(r#"{"type": "note""#).unwrap_err(),
+            "JSON parsing error: EOF while parsing an object at line 1 column 15"
+        );
+        assert_eq!(
+            from_config_string(r#"{"collapsible": "yes"}"#).unwrap_err(),
+            r#"JSON parsing error: invalid type: string "yes", expected a boolean at line 1 column 21"#
+        );
+        assert_eq!(
+            from_config_string(r#"{"class": "a\"b"}"#).unwrap_err(),
+            r#"'a"b' is not a valid CSS classname."#
+        );
+    }
+}
@@ -2,15 +2,82 @@
 //!
 //! It has unit tests to ensure the output matches that of the compile_assets CSS.
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, Result};
 use hex_color::{Case, HexColor};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::color::{Color, DirectiveColor};
+use crate::types::{BuiltinDirective, ClassStyle, ColorMode};
 
 static RX_COLLAPSE_NEWLINES: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"[\r\n]+\s*").expect("invalid whitespace regex"));
+static RX_SVG_VIEWBOX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"viewBox=["']\s*[-\d.]+[\s,]+[-\d.]+[\s,]+([\d.]+)[\s,]+([\d.]+)\s*["']"#)
+        .expect("invalid viewBox regex")
+});
+static RX_SVG_WIDTH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<svg[^>]*\swidth=["']([\d.]+)(?:px)?["']"#).expect("invalid width regex")
+});
+static RX_SVG_HEIGHT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"<svg[^>]*\sheight=["']([\d.]+)(?:px)?["']"#).expect("invalid height regex")
+});
+
+/// Errors generating styles for custom directives.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CustomCssError {
+    /// The config does not define any custom directives.
+    #[error("No custom directives provided")]
+    NoDirectives,
+    /// The icon file of a custom directive could not be read.
+    #[error("can't read icon file '{}'", path.display())]
+    IconRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Any other error, such as an invalid config.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Icons with a larger aspect ratio than this will look distorted.
+const ICON_MAX_ASPECT_RATIO: f64 = 1.25;
+/// Icons are inlined into the CSS, so large files bloat every page.
+const ICON_MAX_BYTES: usize = 16 * 1024;
+
+/// Read the width and height of an SVG, from the `viewBox` or `width`/`height` attributes.
+fn svg_dimensions(svg: &str) -> Option<(f64, f64)> {
+    let (width, height) = match RX_SVG_VIEWBOX.captures(svg) {
+        Some(captures) => (captures[1].parse().ok()?, captures[2].parse().ok()?),
+        None => (
+            RX_SVG_WIDTH.captures(svg)?[1].parse().ok()?,
+            RX_SVG_HEIGHT.captures(svg)?[1].parse().ok()?,
+        ),
+    };
+    Some((width, height))
+}
+
+/// Warn about icons that are unlikely to render well.
+fn check_icon(name: &str, svg: &str) {
+    if let Some((width, height)) = svg_dimensions(svg) {
+        let ratio = width.max(height) / width.min(height);
+        if ratio > ICON_MAX_ASPECT_RATIO {
+            log::warn!("Icon for directive '{name}' is not square ({width}x{height}), it will render distorted.");
+        }
+    }
+    if svg.len() > ICON_MAX_BYTES {
+        log::warn!(
+            "Icon for directive '{name}' is large ({} bytes), consider simplifying it.",
+            svg.len()
+        );
+    }
+}
 
 // Do some simple things to make the svg input probably a valid data url
 // Based on this gist: https://gist.github.com/jennyknuth/222825e315d45a738ed9d6e04c7a88d0
@@ -31,26 +98,279 @@ fn svg_to_data_url(svg: &str) -> String {
     format!("url(\"data:image/svg+xml;charset=utf-8,{}\")", svg)
 }
 
+/// Icons with this prefix reuse the icon of a builtin directive, e.g. `builtin:warning`.
+pub(crate) const BUILTIN_ICON_PREFIX: &str = "builtin:";
+
+/// The builtin directive whose icon is reused, if `icon` has the builtin prefix.
+fn builtin_icon(icon: &Path) -> Option<Result<BuiltinDirective>> {
+    let name = icon.to_str()?.strip_prefix(BUILTIN_ICON_PREFIX)?;
+    Some(
+        BuiltinDirective::from_str(name)
+            .map_err(|()| anyhow!("'{name}' is not a builtin directive, can't use its icon")),
+    )
+}
+
+/// Read the SVG of a custom icon file, relative to `book_dir`.
+fn read_icon(name: &str, book_dir: &Path, icon: &Path) -> Result<String, CustomCssError> {
+    let svg =
+        fs::read_to_string(book_dir.join(icon)).map_err(|source| CustomCssError::IconRead {
+            path: icon.to_owned(),
+            source,
+        })?;
+    check_icon(name, &svg);
+    Ok(svg)
+}
+
+/// Resolve the icon of a custom directive to a CSS image value.
+fn icon_css(name: &str, book_dir: &Path, icon: &Path) -> Result<String, CustomCssError> {
+    if let Some(builtin) = builtin_icon(icon) {
+        // Builtin icons are already defined by the main stylesheet
+        return Ok(format!("var(--md-admonition-icon--admonish-{})", builtin?));
+    }
+    Ok(svg_to_data_url(&read_icon(name, book_dir, icon)?))
+}
+
+/// Resolve the icon of a custom directive to SVG markup, for inline icons.
+///
+/// Newlines are removed, so the markup can't end the surrounding HTML block.
+fn icon_svg(name: &str, book_dir: &Path, icon: &Path) -> Result<String> {
+    if let Some(builtin) = builtin_icon(icon) {
+        return Ok(builtin_icon_svg(builtin?).to_owned());
+    }
+    let svg = read_icon(name, book_dir, icon)?;
+    Ok(RX_COLLAPSE_NEWLINES
+        .replace_all(svg.trim(), " ")
+        .into_owned())
+}
+
+/// SVG markup of the icon of a builtin directive.
+pub(crate) fn builtin_icon_svg(builtin: BuiltinDirective) -> &'static str {
+    BUILTIN_ICONS[builtin as usize]
+}
+
+/// Convert custom directives from the config to those used while rendering.
+///
+/// If `icon_dir` is given, the SVG of each icon is loaded from there, for
+/// `default.inline_icons`.
+pub(crate) fn runtime_directives(
+    directives: HashMap<String, crate::book_config::CustomDirective>,
+    icon_dir: Option<&Path>,
+) -> Result<Vec<crate::types::CustomDirective>> {
+    directives
+        .into_iter()
+        .map(|(name, config)| {
+            let icon_svg = match (icon_dir, &config.icon) {
+                (Some(icon_dir), Some(icon)) => Some(icon_svg(&name, icon_dir, icon)?),
+                _ => None,
+            };
+            Ok(crate::types::CustomDirective {
+                icon_svg,
+                ..(name, config).into()
+            })
+        })
+        .collect()
+}
+
+/// Problems with the icons of custom directives, in directive name order.
+///
+/// Only checks that each icon file exists relative to `book_dir`, so a
+/// misconfigured icon is reported by every build, not just by
+/// `generate-custom`.
+pub(crate) fn icon_problems(
+    directives: &HashMap<String, crate::book_config::CustomDirective>,
+    book_dir: &Path,
+) -> Vec<String> {
+    let mut names: Vec<&String> = directives.keys().collect();
+    names.sort_unstable();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let icon = directives[name].icon.as_deref()?;
+            match builtin_icon(icon) {
+                Some(Ok(_)) => None,
+                Some(Err(error)) => Some(format!("custom directive '{name}': {error}")),
+                None if book_dir.join(icon).is_file() => None,
+                None => Some(format!(
+                    "custom directive '{name}': icon file '{}' does not exist",
+                    icon.display()
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Icons of the builtin directives, in the order of [`BuiltinDirective::ALL`].
+///
+/// These must match `compile_assets/scss/admonition.scss`.
+const BUILTIN_ICONS: [&str; 12] = [
+    // pencil
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M20.71 7.04c.39-.39.39-1.04 0-1.41l-2.34-2.34c-.37-.39-1.02-.39-1.41 0l-1.84 1.83 3.75 3.75M3 17.25V21h3.75L17.81 9.93l-3.75-3.75L3 17.25z'/></svg>",
+    // clipboard-text
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M17 9H7V7h10m0 6H7v-2h10m-3 6H7v-2h7M12 3a1 1 0 0 1 1 1 1 1 0 0 1-1 1 1 1 0 0 1-1-1 1 1 0 0 1 1-1m7 0h-4.18C14.4 1.84 13.3 1 12 1c-1.3 0-2.4.84-2.82 2H5a2 2 0 0 0-2 2v14a2 2 0 0 0 2 2h14a2 2 0 0 0 2-2V5a2 2 0 0 0-2-2z'/></svg>",
+    // information
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M13 9h-2V7h2m0 10h-2v-6h2m-1-9A10 10 0 0 0 2 12a10 10 0 0 0 10 10 10 10 0 0 0 10-10A10 10 0 0 0 12 2z'/></svg>",
+    // fire
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M17.66 11.2c-.23-.3-.51-.56-.77-.82-.67-.6-1.43-1.03-2.07-1.66C13.33 7.26 13 4.85 13.95 3c-.95.23-1.78.75-2.49 1.32-2.59 2.08-3.61 5.75-2.39 8.9.04.1.08.2.08.33 0 .22-.15.42-.35.5-.23.1-.47.04-.66-.12a.58.58 0 0 1-.14-.17c-1.13-1.43-1.31-3.48-.55-5.12C5.78 10 4.87 12.3 5 14.47c.06.5.12 1 .29 1.5.14.6.41 1.2.71 1.73 1.08 1.73 2.95 2.97 4.96 3.22 2.14.27 4.43-.12 6.07-1.6 1.83-1.66 2.47-4.32 1.53-6.6l-.13-.26c-.21-.46-.77-1.26-.77-1.26m-3.16 6.3c-.28.24-.74.5-1.1.6-1.12.4-2.24-.16-2.9-.82 1.19-.28 1.9-1.16 2.11-2.05.17-.8-.15-1.46-.28-2.23-.12-.74-.1-1.37.17-2.06.19.38.39.76.63 1.06.77 1 1.98 1.44 2.24 2.8.04.14.06.28.06.43.03.82-.33 1.72-.93 2.27z'/></svg>",
+    // check-bold
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='m9 20.42-6.21-6.21 2.83-2.83L9 14.77l9.88-9.89 2.83 2.83L9 20.42z'/></svg>",
+    // help-circle
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='m15.07 11.25-.9.92C13.45 12.89 13 13.5 13 15h-2v-.5c0-1.11.45-2.11 1.17-2.83l1.24-1.26c.37-.36.59-.86.59-1.41a2 2 0 0 0-2-2 2 2 0 0 0-2 2H8a4 4 0 0 1 4-4 4 4 0 0 1 4 4 3.2 3.2 0 0 1-.93 2.25M13 19h-2v-2h2M12 2A10 10 0 0 0 2 12a10 10 0 0 0 10 10 10 10 0 0 0 10-10c0-5.53-4.5-10-10-10z'/></svg>",
+    // alert
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M13 14h-2V9h2m0 9h-2v-2h2M1 21h22L12 2 1 21z'/></svg>",
+    // close-thick
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M20 6.91 17.09 4 12 9.09 6.91 4 4 6.91 9.09 12 4 17.09 6.91 20 12 14.91 17.09 20 20 17.09 14.91 12 20 6.91z'/></svg>",
+    // lighting-bold
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M11 15H6l7-14v8h5l-7 14v-8z'/></svg>",
+    // bug
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M14 12h-4v-2h4m0 6h-4v-2h4m6-6h-2.81a5.985 5.985 0 0 0-1.82-1.96L17 4.41 15.59 3l-2.17 2.17a6.002 6.002 0 0 0-2.83 0L8.41 3 7 4.41l1.62 1.63C7.88 6.55 7.26 7.22 6.81 8H4v2h2.09c-.05.33-.09.66-.09 1v1H4v2h2v1c0 .34.04.67.09 1H4v2h2.81c1.04 1.79 2.97 3 5.19 3s4.15-1.21 5.19-3H20v-2h-2.09c.05-.33.09-.66.09-1v-1h2v-2h-2v-1c0-.34-.04-.67-.09-1H20V8z'/></svg>",
+    // format-list-numbered
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M7 13v-2h14v2H7m0 6v-2h14v2H7M7 7V5h14v2H7M3 8V5H2V4h2v4H3m-1 9v-1h3v4H2v-1h2v-.5H3v-1h1V17H2m2.25-7a.75.75 0 0 1 .75.75c0 .2-.08.39-.21.52L3.12 13H5v1H2v-.92L4 11H2v-1h2.25z'/></svg>",
+    // format-quote-close
+    "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24'><path d='M14 17h3l2-4V7h-6v6h3M6 17h3l2-4V7H5v6h3l-2 4z'/></svg>",
+];
+
+/// Format of the output of `generate-custom`.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CustomFormat {
+    /// Finished CSS, ready to include in the book.
+    #[default]
+    Css,
+    /// SCSS variables for each directive's colors and icon.
+    Scss,
+    /// JSON design tokens for each directive's colors and icon.
+    Json,
+}
+
+impl FromStr for CustomFormat {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, String> {
+        match string {
+            "css" => Ok(Self::Css),
+            "scss" => Ok(Self::Scss),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "'{string}' is not a valid format, expected one of 'css', 'scss' or 'json'"
+            )),
+        }
+    }
+}
+
+/// The values needed to style a single custom directive.
+struct DirectiveStyle<'a> {
+    name: &'a str,
+    icon: String,
+    tint: DirectiveColor,
+}
+
+impl DirectiveStyle<'_> {
+    fn color(&self) -> String {
+        self.tint
+            .hex()
+            .display_rgb()
+            .with_case(Case::Lower)
+            .to_string()
+    }
+
+    fn color_faint(&self) -> String {
+        let HexColor { r, g, b, .. } = self.tint.hex();
+        format!("rgba({r}, {g}, {b}, {})", self.tint.tint)
+    }
+}
+
+fn directive_scss(style: &DirectiveStyle) -> String {
+    format!(
+        "$admonish-{name}-color: {color};
+$admonish-{name}-color-faint: {color_faint};
+$admonish-{name}-icon: {icon};
+",
+        name = style.name,
+        color = style.color(),
+        color_faint = style.color_faint(),
+        icon = style.icon,
+    )
+}
+
+fn directives_json(styles: &[DirectiveStyle]) -> Result<String> {
+    let tokens = styles
+        .iter()
+        .map(|style| {
+            (
+                style.name.to_owned(),
+                serde_json::json!({
+                    "color": style.color(),
+                    "color-faint": style.color_faint(),
+                    "icon": style.icon,
+                }),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>();
+    let mut json = serde_json::to_string_pretty(&tokens)?;
+    json.push('\n');
+    Ok(json)
+}
+
 /// Given a valid set of inputs, generate the relevant CSS.
 ///
 /// It is up to the caller to validate inputs.
-fn directive_css(name: &str, svg_data: &str, tint: HexColor) -> String {
-    let data_url = svg_to_data_url(svg_data);
-    let tint_faint = format!("rgba({}, {}, {}, {})", tint.r, tint.g, tint.b, 0.1);
-    let tint = tint.display_rgb().with_case(Case::Lower);
+fn directive_css(
+    name: &str,
+    icon: &str,
+    tint: DirectiveColor,
+    color_mode: ColorMode,
+    class_style: ClassStyle,
+) -> String {
     format!(
         ":root {{
-  --md-admonition-icon--admonish-{name}: {data_url};
-}}
+{icon}}}
 
-:is(.admonition):is(.admonish-{name}) {{
+{rules}",
+        icon = directive_icon_css(name, icon),
+        rules = directive_rules_css(name, &[], tint, color_mode, class_style),
+    )
+}
+
+/// The CSS variable holding the icon of a directive.
+fn directive_icon_css(name: &str, icon: &str) -> String {
+    format!("  --md-admonition-icon--admonish-{name}: {icon};\n")
+}
+
+/// The rules styling a directive, and any aliases with their own classnames.
+fn directive_rules_css(
+    name: &str,
+    aliases: &[&str],
+    tint: DirectiveColor,
+    color_mode: ColorMode,
+    class_style: ClassStyle,
+) -> String {
+    let style = DirectiveStyle {
+        name,
+        icon: String::new(),
+        tint,
+    };
+    let flavours = std::iter::once(&name)
+        .chain(aliases)
+        .map(|name| format!(".{}", class_style.directive_class(name)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (tint, tint_faint) = (style.color(), style.color_faint());
+    let (tint, tint_faint) = match color_mode {
+        ColorMode::Fixed => (tint, tint_faint),
+        ColorMode::Variable => (
+            format!("var(--admonish-{name}-color, {tint})"),
+            format!("var(--admonish-{name}-color-faint, {tint_faint})"),
+        ),
+    };
+    format!(
+        ":is(.admonition):is({flavours}) {{
   border-color: {tint};
 }}
 
-:is(.admonish-{name}) > :is(.admonition-title, summary.admonition-title) {{
+:is({flavours}) > :is(.admonition-title, summary.admonition-title) {{
   background-color: {tint_faint};
 }}
-:is(.admonish-{name}) > :is(.admonition-title, summary.admonition-title)::before {{
+:is({flavours}) > :is(.admonition-title, summary.admonition-title)::before {{
   background-color: {tint};
   mask-image: var(--md-admonition-icon--admonish-{name});
   -webkit-mask-image: var(--md-admonition-icon--admonish-{name});
@@ -60,30 +380,176 @@ fn directive_css(name: &str, svg_data: &str, tint: HexColor) -> String {
   -webkit-mask-repeat: no-repeat;
 }}
 ",
-        name = name,
-        data_url = data_url,
-        tint = tint,
-        tint_faint = tint_faint
     )
 }
 
+/// Styles for all builtin directives, matching the bundled stylesheet.
+fn builtin_styles() -> Vec<DirectiveStyle<'static>> {
+    BuiltinDirective::ALL
+        .into_iter()
+        .zip(BUILTIN_ICONS)
+        .map(|(builtin, icon)| {
+            let info = builtin.info();
+            DirectiveStyle {
+                name: info.directive,
+                icon: svg_to_data_url(icon),
+                tint: Color::from(
+                    HexColor::parse_rgb(info.color).expect("builtin colors are valid"),
+                )
+                .into(),
+            }
+        })
+        .collect()
+}
+
+/// CSS for all builtin directives, laid out as in the bundled stylesheet.
+fn builtin_css(color_mode: ColorMode, class_style: ClassStyle) -> String {
+    let styles = builtin_styles();
+    let icons: String = styles
+        .iter()
+        .map(|style| directive_icon_css(style.name, &style.icon))
+        .collect();
+    let rules = BuiltinDirective::ALL
+        .into_iter()
+        .zip(&styles)
+        .map(|(builtin, style)| {
+            directive_rules_css(
+                style.name,
+                builtin.info().aliases,
+                style.tint,
+                color_mode,
+                class_style,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        ":root {{
+{icons}}}
+
+{rules}"
+    )
+}
+
+/// Show the content of collapsed blocks when printing.
+///
+/// This is not specific to any directive, so should only be included once.
+const PRINT_EXPAND_COLLAPSED_CSS: &str = "@media print {
+  details.admonition > :not(summary) {
+    display: block !important;
+  }
+  details.admonition::details-content {
+    content-visibility: visible;
+    display: block;
+  }
+}
+";
+
+/// CSS for custom directives defined in the front matter of a chapter.
+///
+/// Icon paths are relative to `src_dir`, the book source directory.
+pub(crate) fn chapter_css(
+    src_dir: &Path,
+    directives: &HashMap<String, crate::book_config::CustomDirective>,
+    color_mode: ColorMode,
+    class_style: ClassStyle,
+) -> Result<String> {
+    let mut names: Vec<&String> = directives.keys().collect();
+    names.sort();
+    let mut css = String::new();
+    for name in names {
+        let directive = &directives[name];
+        // Both are checked when loading the config
+        let (Some(icon), Some(color)) = (&directive.icon, directive.color) else {
+            return Err(anyhow!("Custom directive '{name}' has no icon or color"));
+        };
+        css.push_str(&directive_css(
+            name,
+            &icon_css(name, src_dir, icon)?,
+            color,
+            color_mode,
+            class_style,
+        ));
+    }
+    Ok(css)
+}
+
+#[doc(hidden)]
+pub fn css_from_config(book_dir: &Path, config: &str) -> Result<String, CustomCssError> {
+    custom_from_config(book_dir, config, CustomFormat::Css, false)
+}
+
+/// Generate styles for custom directives in the given format.
+///
+/// If `include_builtin` is set, styles for the builtin directives are
+/// generated first, so the result does not depend on the bundled stylesheet.
 #[doc(hidden)]
-pub fn css_from_config(book_dir: &Path, config: &str) -> Result<String> {
-    let config = crate::book_config::admonish_config_from_str(config)?;
+pub fn custom_from_config(
+    book_dir: &Path,
+    config: &str,
+    format: CustomFormat,
+    include_builtin: bool,
+) -> Result<String, CustomCssError> {
+    let config = crate::book_config::admonish_config_from_str(config, book_dir)?;
     let custom_directives = config.directive.custom;
 
-    if custom_directives.is_empty() {
-        return Err(anyhow!("No custom directives provided"));
+    if custom_directives.is_empty() && !include_builtin {
+        return Err(CustomCssError::NoDirectives);
     }
 
     log::info!("Loaded {} custom directives", custom_directives.len());
-    let mut css = String::new();
-    for (directive_name, directive) in custom_directives.iter() {
-        let svg = fs::read_to_string(book_dir.join(&directive.icon))
-            .with_context(|| format!("can't read icon file '{}'", directive.icon.display()))?;
-        css.push_str(&directive_css(directive_name, &svg, directive.color));
+    let styles = custom_directives
+        .iter()
+        .map(|(directive_name, directive)| {
+            // Both are checked when loading the config
+            let (Some(icon), Some(color)) = (&directive.icon, directive.color) else {
+                return Err(CustomCssError::Other(anyhow!(
+                    "Custom directive '{directive_name}' has no icon or color"
+                )));
+            };
+            Ok(DirectiveStyle {
+                name: directive_name,
+                icon: icon_css(directive_name, book_dir, icon)?,
+                tint: color,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match format {
+        CustomFormat::Css => {
+            let mut css = String::new();
+            if include_builtin {
+                css.push_str(&builtin_css(
+                    config.default.color_mode,
+                    config.default.class_style,
+                ));
+            }
+            for style in styles.iter() {
+                css.push_str(&directive_css(
+                    style.name,
+                    &style.icon,
+                    style.tint,
+                    config.default.color_mode,
+                    config.default.class_style,
+                ));
+            }
+            if config.default.print_expand_collapsed {
+                css.push_str(PRINT_EXPAND_COLLAPSED_CSS);
+            }
+            Ok(css)
+        }
+        CustomFormat::Scss | CustomFormat::Json => {
+            let styles = if include_builtin {
+                builtin_styles().into_iter().chain(styles).collect()
+            } else {
+                styles
+            };
+            match format {
+                CustomFormat::Scss => Ok(styles.iter().map(directive_scss).collect()),
+                _ => Ok(directives_json(&styles)?),
+            }
+        }
     }
-    Ok(css)
 }
 
 #[cfg(test)]
@@ -102,10 +568,297 @@ mod test {
     // The ensures that any new custom CSS will be in line with official styles.
     #[test]
     fn verify_against_generated_css() {
-        let actual = directive_css("note", NOTE_SVG, HexColor::parse("#448aff").unwrap());
+        let actual = directive_css(
+            "note",
+            &svg_to_data_url(NOTE_SVG),
+            Color::from(HexColor::parse("#448aff").unwrap()).into(),
+            ColorMode::Fixed,
+            ClassStyle::Admonish,
+        );
         assert_eq!(
             GENERATED_CSS, actual,
             "Rust generated CSS is out of step with SCSS generated CSS"
         )
     }
+
+    // Verify the generated builtin CSS against the bundled stylesheet.
+    #[test]
+    fn verify_builtin_against_bundled_css() {
+        const BUNDLED_CSS: &str = include_str!("./bin/assets/mdbook-admonish.css");
+        let actual = builtin_css(ColorMode::Fixed, ClassStyle::Admonish);
+        assert!(
+            BUNDLED_CSS.contains(&actual),
+            "Rust generated builtin CSS is out of step with the bundled stylesheet:\n{actual}"
+        );
+    }
+
+    #[test]
+    fn include_builtin() -> Result<()> {
+        let book_dir = Path::new(".");
+        // Builtin styles are enough on their own
+        let css = custom_from_config(book_dir, "", CustomFormat::Css, true)?;
+        assert_eq!(css, builtin_css(ColorMode::Fixed, ClassStyle::Admonish));
+
+        let config = r##"
+[directive.custom.frog]
+icon = "builtin:tip"
+color = "#9004CC"
+"##;
+        let css = custom_from_config(book_dir, config, CustomFormat::Css, true)?;
+        assert!(css.starts_with(&builtin_css(ColorMode::Fixed, ClassStyle::Admonish)));
+        assert!(css.ends_with(&css_from_config(book_dir, config)?));
+
+        let scss = custom_from_config(book_dir, config, CustomFormat::Scss, true)?;
+        assert!(scss.starts_with("$admonish-note-color: #448aff;\n"));
+        assert!(scss.contains("$admonish-quote-color: #9e9e9e;\n"));
+        assert!(scss.contains("$admonish-frog-color: #9004cc;\n"));
+
+        let error = custom_from_config(book_dir, "", CustomFormat::Css, false).unwrap_err();
+        assert!(matches!(error, CustomCssError::NoDirectives));
+        assert_eq!(error.to_string(), "No custom directives provided");
+        Ok(())
+    }
+
+    #[test]
+    fn variable_color_mode() {
+        let actual = directive_css(
+            "note",
+            &svg_to_data_url(NOTE_SVG),
+            Color::from(HexColor::parse("#448aff").unwrap()).into(),
+            ColorMode::Variable,
+            ClassStyle::Admonish,
+        );
+        let expected = GENERATED_CSS
+            .replace(
+                "border-color: #448aff;",
+                "border-color: var(--admonish-note-color, #448aff);",
+            )
+            .replace(
+                "background-color: #448aff;",
+                "background-color: var(--admonish-note-color, #448aff);",
+            )
+            .replace(
+                "background-color: rgba(68, 138, 255, 0.1);",
+                "background-color: var(--admonish-note-color-faint, rgba(68, 138, 255, 0.1));",
+            );
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn print_expand_collapsed_included_once() -> Result<()> {
+        let book_dir = std::env::temp_dir().join(format!(
+            "mdbook-admonish-test-print-expand-collapsed-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&book_dir)?;
+        fs::write(book_dir.join("note.svg"), NOTE_SVG)?;
+
+        let config = r##"
+[directive.custom.frog]
+icon = "./note.svg"
+color = "#9004CC"
+
+[directive.custom.newt]
+icon = "./note.svg"
+color = "#0038A8"
+"##;
+        let actual = css_from_config(&book_dir, config)?;
+        assert!(!actual.contains("@media print"));
+
+        let config = format!("default.print_expand_collapsed = true\n{config}");
+        let actual = css_from_config(&book_dir, &config)?;
+        assert_eq!(actual.matches("@media print").count(), 1);
+
+        fs::remove_dir_all(book_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn scss_and_json_formats() -> Result<()> {
+        let book_dir = Path::new(".");
+        let config = r##"
+[directive.custom.frog]
+icon = "builtin:tip"
+color = "#9004CC"
+"##;
+        assert_eq!(
+            custom_from_config(book_dir, config, CustomFormat::Scss, false)?,
+            "$admonish-frog-color: #9004cc;
+$admonish-frog-color-faint: rgba(144, 4, 204, 0.1);
+$admonish-frog-icon: var(--md-admonition-icon--admonish-tip);
+"
+        );
+        assert_eq!(
+            custom_from_config(book_dir, config, CustomFormat::Json, false)?,
+            r##"{
+  "frog": {
+    "color": "#9004cc",
+    "color-faint": "rgba(144, 4, 204, 0.1)",
+    "icon": "var(--md-admonition-icon--admonish-tip)"
+  }
+}
+"##
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_svg_dimensions() {
+        assert_eq!(svg_dimensions(NOTE_SVG), Some((24.0, 24.0)));
+        assert_eq!(
+            svg_dimensions(r#"<svg viewBox="0,0,48.5,12"></svg>"#),
+            Some((48.5, 12.0))
+        );
+        assert_eq!(
+            svg_dimensions(r#"<svg width="32px" height='16'></svg>"#),
+            Some((32.0, 16.0))
+        );
+        assert_eq!(svg_dimensions("<svg></svg>"), None);
+    }
+
+    #[test]
+    fn builtin_icon() -> Result<()> {
+        let book_dir = Path::new(".");
+        assert_eq!(
+            icon_css("frog", book_dir, Path::new("builtin:warning"))?,
+            "var(--md-admonition-icon--admonish-warning)"
+        );
+        // Aliases resolve to the builtin directive
+        assert_eq!(
+            icon_css("frog", book_dir, Path::new("builtin:cite"))?,
+            "var(--md-admonition-icon--admonish-quote)"
+        );
+        assert_eq!(
+            icon_css("frog", book_dir, Path::new("builtin:frog"))
+                .unwrap_err()
+                .to_string(),
+            "'frog' is not a builtin directive, can't use its icon"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extended_directives() -> Result<()> {
+        let book_dir = Path::new(".");
+        let config = r##"
+[directive.custom.critical]
+extends = "danger"
+title = "Critical"
+
+[directive.custom.frog]
+icon = "builtin:tip"
+color = "#9004CC"
+
+[directive.custom.toad]
+extends = "frog"
+color = "#00ff00"
+"##;
+        let scss = custom_from_config(book_dir, config, CustomFormat::Scss, false)?;
+        // From a builtin directive
+        assert!(scss.contains(
+            "$admonish-critical-color: #ff1744;
+$admonish-critical-color-faint: rgba(255, 23, 68, 0.1);
+$admonish-critical-icon: var(--md-admonition-icon--admonish-danger);
+"
+        ));
+        // From a custom directive, overriding the color
+        assert!(scss.contains(
+            "$admonish-toad-color: #00ff00;
+$admonish-toad-color-faint: rgba(0, 255, 0, 0.1);
+$admonish-toad-icon: var(--md-admonition-icon--admonish-tip);
+"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn custom_tint() -> Result<()> {
+        let book_dir = Path::new(".");
+        let css = css_from_config(
+            book_dir,
+            r##"
+[directive.custom.frog]
+icon = "builtin:tip"
+color = { base = "#9004CC", tint = 0.25 }
+"##,
+        )?;
+        assert!(css.contains("background-color: rgba(144, 4, 204, 0.25);"));
+        assert!(css.contains("border-color: #9004cc;"));
+
+        let scss = custom_from_config(
+            book_dir,
+            r#"directive.custom.frog = { icon = "builtin:tip", color = { base = "red", tint = 0 } }"#,
+            CustomFormat::Scss,
+            false,
+        )?;
+        assert!(scss.contains("$admonish-frog-color-faint: rgba(255, 0, 0, 0);\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_icon_problems() {
+        let directive = |icon: &str| crate::book_config::CustomDirective {
+            icon: Some(icon.into()),
+            color: None,
+            style: None,
+            extends: None,
+            aliases: Vec::new(),
+            renamed_from: Vec::new(),
+            title: None,
+            collapsible: None,
+            alias_collapsible: HashMap::new(),
+            body_prefix: None,
+            body_suffix: None,
+            role: None,
+            aria_label: None,
+        };
+        let directives = HashMap::from([
+            ("money".to_owned(), directive("book/money-bag.svg")),
+            ("frog".to_owned(), directive("builtin:frog")),
+            ("toad".to_owned(), directive("builtin:tip")),
+            ("newt".to_owned(), directive("missing.svg")),
+        ]);
+        assert_eq!(
+            icon_problems(&directives, Path::new(".")),
+            vec![
+                "custom directive 'frog': 'frog' is not a builtin directive, can't use its icon",
+                "custom directive 'newt': icon file 'missing.svg' does not exist",
+            ]
+        );
+    }
+
+    #[test]
+    fn bem_class_style() {
+        let actual = directive_css(
+            "note",
+            &svg_to_data_url(NOTE_SVG),
+            Color::from(HexColor::parse("#448aff").unwrap()).into(),
+            ColorMode::Fixed,
+            ClassStyle::Bem,
+        );
+        assert_eq!(
+            actual,
+            GENERATED_CSS.replace(".admonish-note", ".admonition--note")
+        );
+        // Icon variables are unchanged
+        assert!(actual.contains("--md-admonition-icon--admonish-note"));
+    }
+
+    #[test]
+    fn icon_read_error() {
+        let config = r##"
+[directive.custom.frog]
+icon = "missing-frog.svg"
+color = "#9004CC"
+"##;
+        let error = css_from_config(Path::new("."), config).unwrap_err();
+        match &error {
+            CustomCssError::IconRead { path, source } => {
+                assert_eq!(path, Path::new("missing-frog.svg"));
+                assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        assert_eq!(error.to_string(), "can't read icon file 'missing-frog.svg'");
+    }
 }
@@ -0,0 +1,19 @@
+// Toggle collapsible blocks rendered as disclosure widgets, with
+// `collapsible_impl = "aria"`. This is inlined into each chapter, inside an
+// HTML block, so must not contain blank lines.
+(function () {
+  // Pages such as print.html include several chapters
+  if ("admonishDisclosure" in document.documentElement.dataset) {
+    return;
+  }
+  document.documentElement.dataset.admonishDisclosure = "";
+  document.addEventListener("click", (event) => {
+    const button = event.target.closest("button.admonition-toggle");
+    if (!button) {
+      return;
+    }
+    const open = button.getAttribute("aria-expanded") !== "true";
+    button.setAttribute("aria-expanded", open);
+    document.getElementById(button.getAttribute("aria-controls")).hidden = !open;
+  });
+})();
@@ -0,0 +1,135 @@
+//! This module is responsible for generating a reference page of all available directives.
+
+use anyhow::Result;
+use hex_color::Case;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::resolve::uppercase_first;
+use crate::types::BuiltinDirective;
+
+struct LegendEntry {
+    directive: String,
+    aliases: Vec<String>,
+    title: String,
+    color: Option<String>,
+}
+
+#[doc(hidden)]
+pub fn legend_from_config(book_dir: &Path, config: &str) -> Result<String> {
+    let config = crate::book_config::admonish_config_from_str(config, book_dir)?;
+
+    let mut entries: Vec<LegendEntry> = BuiltinDirective::ALL
+        .into_iter()
+        .map(|builtin| {
+            let info = builtin.info();
+            LegendEntry {
+                directive: info.directive.to_owned(),
+                aliases: info.aliases.iter().map(|alias| alias.to_string()).collect(),
+                title: info.title.to_owned(),
+                color: Some(info.color.to_owned()),
+            }
+        })
+        .collect();
+
+    let mut custom_directives: Vec<_> = config.directive.custom.into_iter().collect();
+    custom_directives.sort_by(|(a, _), (b, _)| a.cmp(b));
+    log::info!("Loaded {} custom directives", custom_directives.len());
+    entries.extend(custom_directives.into_iter().map(|(directive, custom)| {
+        LegendEntry {
+            title: custom.title.unwrap_or_else(|| uppercase_first(&directive)),
+            aliases: custom.aliases,
+            color: custom
+                .color
+                .map(|color| color.hex().display_rgb().with_case(Case::Lower).to_string()),
+            directive,
+        }
+    }));
+
+    Ok(legend_markdown(&entries))
+}
+
+fn legend_markdown(entries: &[LegendEntry]) -> String {
+    let mut markdown = String::from(
+        "# Directives
+
+| Directive | Aliases | Title | Color |
+| --- | --- | --- | --- |
+",
+    );
+    for entry in entries {
+        let aliases = entry
+            .aliases
+            .iter()
+            .map(|alias| format!("`{alias}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let color = entry
+            .color
+            .as_ref()
+            .map(|color| format!(r#"<span style="color: {color}">■</span> `{color}`"#))
+            .unwrap_or_default();
+        let _ = writeln!(
+            markdown,
+            "| `{directive}` | {aliases} | {title} | {color} |",
+            directive = entry.directive,
+            title = entry.title,
+        );
+    }
+    for entry in entries {
+        let _ = write!(
+            markdown,
+            "
+## `{directive}`
+
+```admonish {directive}
+A sample `{directive}` block.
+```
+",
+            directive = entry.directive,
+        );
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn legend_includes_builtin_and_custom_directives() {
+        let actual = legend_from_config(
+            Path::new("."),
+            r##"
+[directive.custom.frog]
+icon = "./frog.svg"
+color = "#9004CC"
+aliases = ["toad"]
+"##,
+        )
+        .unwrap();
+
+        assert!(actual.starts_with(
+            r##"# Directives
+
+| Directive | Aliases | Title | Color |
+| --- | --- | --- | --- |
+| `note` |  | Note | <span style="color: #448aff">■</span> `#448aff` |
+| `abstract` | `summary`, `tldr` | Abstract | <span style="color: #00b0ff">■</span> `#00b0ff` |
+"##
+        ));
+        assert!(actual.contains(
+            r#"| `frog` | `toad` | Frog | <span style="color: #9004cc">■</span> `#9004cc` |"#
+        ));
+        assert_eq!(
+            actual.split_once("## `frog`").unwrap().1,
+            "
+
+```admonish frog
+A sample `frog` block.
+```
+"
+        );
+    }
+}
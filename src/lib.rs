@@ -3,14 +3,22 @@
 //! Documentation is hosted externally, as docs.rs does not currently support plugins.
 
 mod book_config;
+mod color;
 mod config;
 #[doc(hidden)]
 pub mod custom;
+#[doc(hidden)]
+pub mod legend;
 mod markdown;
+mod mkdocs;
 mod parse;
 mod preprocessor;
 mod render;
 mod resolve;
 mod types;
 
-pub use crate::preprocessor::Admonish;
+pub use crate::preprocessor::{list_admonitions, Admonish};
+#[doc(hidden)]
+pub use crate::preprocessor::{render_markdown_from_config, render_mode_from_config};
+pub use crate::render::{AdmonitionRenderHook, RenderedAdmonition};
+pub use crate::types::{builtin_directives, AdmonitionPosition, DirectiveInfo};
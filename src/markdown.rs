@@ -1,57 +1,248 @@
+use anyhow::anyhow;
 use mdbook::errors::Result as MdbookResult;
-use pulldown_cmark::{CodeBlockKind::*, Event, Options, Parser, Tag};
+use pulldown_cmark::{CodeBlockKind::*, CowStr, Event, Options, Parser, Tag};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
 
 use crate::{
-    book_config::OnFailure,
-    parse::parse_admonition,
-    types::{Overrides, RenderTextMode},
+    book_config::{chapter_config_from_str, OnFailure},
+    config::{is_admonition_info_string, ADMONISH_BLOCK_KEYWORD},
+    custom::{chapter_css, runtime_directives},
+    mkdocs::{convert_mkdocs_syntax, may_contain_mkdocs_blocks},
+    parse::{admonition_meta, parse_admonition, Location},
+    types::{AdmonitionPosition, CollapsibleImpl, CssId, Overrides, RenderTextMode},
 };
 
-pub(crate) fn preprocess(
-    content: &str,
-    on_failure: OnFailure,
-    overrides: &Overrides,
-    render_text_mode: RenderTextMode,
-) -> MdbookResult<String> {
-    let mut id_counter = Default::default();
+/// A fenced code block, which may be an admonition.
+struct FencedBlock<'a> {
+    info_string: CowStr<'a>,
+    span: Range<usize>,
+    /// One-indexed line number of the opening fence.
+    line: usize,
+}
+
+/// The fenced code blocks in `content`, in document order.
+fn fenced_blocks(content: &str) -> impl Iterator<Item = FencedBlock<'_>> {
+    // Track line numbers incrementally, as events are emitted in document order
+    let mut line = 1;
+    let mut line_scanned_to = 0;
+
+    Parser::new_ext(content, parser_options())
+        .into_offset_iter()
+        .filter_map(move |(event, span)| {
+            let Event::Start(Tag::CodeBlock(Fenced(info_string))) = event else {
+                return None;
+            };
+            line += content[line_scanned_to..span.start].matches('\n').count();
+            line_scanned_to = span.start;
+            Some(FencedBlock {
+                info_string,
+                span,
+                line,
+            })
+        })
+}
+
+/// The markdown extensions enabled when scanning for blocks.
+fn parser_options() -> Options {
     let mut opts = Options::empty();
     opts.insert(Options::ENABLE_TABLES);
     opts.insert(Options::ENABLE_FOOTNOTES);
     opts.insert(Options::ENABLE_STRIKETHROUGH);
     opts.insert(Options::ENABLE_TASKLISTS);
+    opts
+}
 
-    let mut admonish_blocks = vec![];
+/// Whether admonitions in `content` are nested more than `max_depth` deep.
+///
+/// Only scans as deep as needed, so pathological nesting is cheap to reject.
+pub(crate) fn admonitions_nested_deeper_than(content: &str, max_depth: usize) -> bool {
+    let mut events = Parser::new_ext(content, parser_options());
+    while let Some(event) = events.next() {
+        let Event::Start(Tag::CodeBlock(Fenced(info_string))) = event else {
+            continue;
+        };
+        if !is_admonition_info_string(&info_string) {
+            continue;
+        }
+        if max_depth == 0 {
+            return true;
+        }
+        // The text of a code block is its body, with any indent removed
+        let mut body = String::new();
+        for event in events.by_ref() {
+            match event {
+                Event::Text(text) => body.push_str(&text),
+                _ => break,
+            }
+        }
+        if admonitions_nested_deeper_than(&body, max_depth - 1) {
+            return true;
+        }
+    }
+    false
+}
 
-    let events = Parser::new_ext(content, opts);
+pub(crate) fn preprocess(
+    content: &str,
+    path: Option<&Path>,
+    src_dir: Option<&Path>,
+    on_failure: OnFailure,
+    overrides: &Overrides,
+    render_text_mode: RenderTextMode,
+    renderer: Option<&str>,
+) -> MdbookResult<String> {
+    if let Some((front_matter, body)) = split_chapter_front_matter(content) {
+        match chapter_overrides(front_matter, src_dir, overrides, render_text_mode) {
+            Ok(Some((chapter_overrides, style))) => {
+                // Keep line numbers in diagnostics the same as in the source
+                let lines_removed = content[..content.len() - body.len()].matches('\n').count();
+                let content = format!("{}{body}", "\n".repeat(lines_removed));
+                let result = preprocess(
+                    &content,
+                    path,
+                    src_dir,
+                    on_failure,
+                    &chapter_overrides,
+                    render_text_mode,
+                    renderer,
+                );
+                overrides
+                    .used_directives
+                    .borrow_mut()
+                    .extend(chapter_overrides.used_directives.take());
+                overrides
+                    .failed_blocks
+                    .set(overrides.failed_blocks.get() + chapter_overrides.failed_blocks.get());
+                overrides
+                    .found_blocks
+                    .set(overrides.found_blocks.get() + chapter_overrides.found_blocks.get());
+                overrides
+                    .warnings
+                    .set(overrides.warnings.get() + chapter_overrides.warnings.get());
+                return result.map(|content| format!("{style}{content}"));
+            }
+            Ok(None) => {}
+            Err(error) => {
+                let message = format!("{error:#}");
+                match on_failure {
+                    OnFailure::Continue => overrides.warn(&message),
+                    OnFailure::Bail => return Err(anyhow!(message)),
+                }
+            }
+        }
+    }
 
-    for (event, span) in events.into_offset_iter() {
-        if let Event::Start(Tag::CodeBlock(Fenced(info_string))) = event.clone() {
-            let span_content = &content[span.start..span.end];
-            const INDENT_SCAN_MAX: usize = 1024;
-            let indent = indent_of(content, span.start, INDENT_SCAN_MAX);
+    let content = if overrides.book.mkdocs_syntax {
+        Cow::Owned(convert_mkdocs_syntax(content))
+    } else {
+        Cow::Borrowed(content)
+    };
+    let content = content.as_ref();
+    let anchor_page = path
+        .filter(|_| overrides.book.absolute_anchor_links)
+        .and_then(html_page_name);
 
-            let admonition = match parse_admonition(
-                info_string.as_ref(),
-                overrides,
-                span_content,
-                on_failure,
-                indent,
-            ) {
-                Some(admonition) => admonition,
-                None => continue,
-            };
+    let mut id_counter = Default::default();
+    // Numbers of numbered blocks, by directive
+    let mut number_counter = Default::default();
+    // Verbatim ids given by the user, and where they were first seen
+    let mut verbatim_ids: HashMap<String, Location<'_>> = HashMap::new();
 
-            let admonition = admonition?;
+    let mut admonish_blocks = vec![];
+    let mut has_collapsible = false;
+    let mut has_disclosure = false;
+    let mut has_state_labels = false;
+    let mut found_blocks = 0;
+
+    for FencedBlock {
+        info_string,
+        span,
+        line,
+    } in fenced_blocks(content)
+    {
+        let span_content = &content[span.start..span.end];
+        const INDENT_SCAN_MAX: usize = 1024;
+        let indent = indent_of(content, span.start, INDENT_SCAN_MAX);
+
+        let location = Location { path, line };
+
+        let admonition = match parse_admonition(
+            info_string.as_ref(),
+            overrides,
+            span_content,
+            on_failure,
+            indent,
+            location,
+            src_dir,
+        ) {
+            Some(admonition) => admonition,
+            None => continue,
+        };
+
+        let mut admonition = admonition?;
+        found_blocks += 1;
+        overrides
+            .used_directives
+            .borrow_mut()
+            .insert(admonition.directive.clone());
+
+        // Drop blocks targeted at other renderers, keeping line numbering
+        if !admonition.is_for_renderer(renderer) {
+            admonish_blocks.push((span, "\n".repeat(span_content.matches('\n').count())));
+            continue;
+        }
 
-            // Once we've identitified admonition blocks, handle them differently
-            // depending on our render mode
-            let new_content = match render_text_mode {
-                RenderTextMode::Html => admonition.html(&mut id_counter),
-                RenderTextMode::Strip => admonition.strip(),
-            };
+        // Only the html renderer can toggle blocks. Other renderers given html,
+        // such as epub with `render_mode = "html"`, show them expanded
+        if render_text_mode == RenderTextMode::Html
+            && renderer.is_some_and(|renderer| renderer != "html")
+            && overrides.book.force_expand_non_html != Some(false)
+        {
+            admonition.collapsible = false;
+        }
 
-            admonish_blocks.push((span, new_content));
+        if render_text_mode == RenderTextMode::Html {
+            if let CssId::Verbatim(id) = &admonition.css_id {
+                if let Some(first) = verbatim_ids.get(id) {
+                    let message = format!(
+                        "Duplicate admonition id '{id}' at {location}, first used at {first}"
+                    );
+                    match on_failure {
+                        OnFailure::Continue => overrides.warn(&message),
+                        OnFailure::Bail => return Err(anyhow!(message)),
+                    }
+                } else {
+                    verbatim_ids.insert(id.clone(), location);
+                }
+            }
         }
+
+        // Once we've identitified admonition blocks, handle them differently
+        // depending on our render mode
+        let new_content = match render_text_mode {
+            RenderTextMode::Html => {
+                has_collapsible |= admonition.collapsible;
+                has_disclosure |=
+                    admonition.collapsible && admonition.collapsible_impl == CollapsibleImpl::Aria;
+                has_state_labels |= admonition.state_labels
+                    && admonition.collapsible
+                    && admonition.collapsible_impl == CollapsibleImpl::Native;
+                admonition.html(
+                    &mut id_counter,
+                    &mut number_counter,
+                    anchor_page.as_deref(),
+                    overrides.render_hook.as_ref(),
+                )
+            }
+            RenderTextMode::Strip { annotate } => admonition.strip(annotate),
+            RenderTextMode::Blockquote => admonition.blockquote(&mut number_counter),
+            RenderTextMode::Text => admonition.to_text(&mut number_counter),
+        };
+
+        admonish_blocks.push((span, new_content));
     }
 
     let mut content = content.to_string();
@@ -61,9 +252,178 @@ pub(crate) fn preprocess(
         content = format!("{}{}{}", pre_content, block, post_content);
     }
 
+    if has_collapsible && overrides.book.collapse_controls {
+        content = format!("{COLLAPSE_CONTROLS_HTML}\n{content}");
+    }
+    if has_disclosure {
+        content = format!("{content}\n\n{DISCLOSURE_SCRIPT_HTML}");
+    }
+    if has_state_labels {
+        content = format!("{content}\n\n{STATE_LABELS_SCRIPT_HTML}");
+    }
+
+    // Chapters that mention admonitions but have none may have a syntax error
+    match path {
+        Some(path) => log::debug!("Found {found_blocks} admonitions in {}", path.display()),
+        None => log::debug!("Found {found_blocks} admonitions in chapter"),
+    }
+    overrides
+        .found_blocks
+        .set(overrides.found_blocks.get() + found_blocks);
+
     Ok(content)
 }
 
+/// The admonitions in `content`, without rendering them.
+///
+/// Blocks with invalid options, and mkdocs-material style blocks, are skipped.
+pub(crate) fn collect_admonitions(
+    content: &str,
+    overrides: &Overrides,
+) -> anyhow::Result<Vec<AdmonitionPosition>> {
+    if let Some((front_matter, body)) = split_chapter_front_matter(content) {
+        // Styles are only generated in html mode, and aren't needed here
+        let render_text_mode = RenderTextMode::Strip { annotate: false };
+        if let Some((chapter_overrides, _)) =
+            chapter_overrides(front_matter, None, overrides, render_text_mode)?
+        {
+            // Positions are relative to the whole chapter, including front matter
+            let offset = content.len() - body.len();
+            let lines_removed = content[..offset].matches('\n').count();
+            let mut positions = collect_admonitions(body, &chapter_overrides)?;
+            for position in &mut positions {
+                position.byte_range =
+                    position.byte_range.start + offset..position.byte_range.end + offset;
+                position.line += lines_removed;
+            }
+            return Ok(positions);
+        }
+    }
+
+    Ok(fenced_blocks(content)
+        .filter_map(|block| {
+            let span_content = &content[block.span.clone()];
+            let meta = admonition_meta(&block.info_string, overrides, span_content)?.ok()?;
+            Some(AdmonitionPosition {
+                directive: meta.directive,
+                title: meta.title,
+                byte_range: block.span,
+                line: block.line,
+            })
+        })
+        .collect())
+}
+
+/// Buttons to expand or collapse all blocks in a chapter, and the script
+/// handling them.
+const COLLAPSE_CONTROLS_HTML: &str = concat!(
+    r#"<div class="admonish-collapse-controls">
+<button type="button" data-admonish-expand="true">Expand all</button>
+<button type="button" data-admonish-expand="false">Collapse all</button>
+</div>
+<script>
+"#,
+    include_str!("./collapse_controls.js"),
+    "</script>\n"
+);
+
+/// The script toggling collapsible blocks rendered as disclosure widgets.
+const DISCLOSURE_SCRIPT_HTML: &str =
+    concat!("<script>\n", include_str!("./disclosure.js"), "</script>\n");
+
+/// The script updating the state labels of collapsible blocks.
+const STATE_LABELS_SCRIPT_HTML: &str = concat!(
+    "<script>\n",
+    include_str!("./state_labels.js"),
+    "</script>\n"
+);
+
+/// Delimits TOML front matter at the start of a chapter.
+const CHAPTER_FRONT_MATTER_DELIMITER: &str = "+++";
+
+/// Split TOML front matter, delimited by `+++` lines, from the start of a chapter.
+///
+/// Returns the front matter, and the rest of the chapter after the closing delimiter.
+fn split_chapter_front_matter(content: &str) -> Option<(&str, &str)> {
+    let mut lines = content.split_inclusive('\n');
+    if lines.next()?.trim_end() != CHAPTER_FRONT_MATTER_DELIMITER {
+        return None;
+    }
+    let start = content.find('\n')? + 1;
+    let mut offset = start;
+    for line in lines {
+        if line.trim_end() == CHAPTER_FRONT_MATTER_DELIMITER {
+            return Some((&content[start..offset], &content[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Overrides for a chapter defining its own directives in front matter, and
+/// the styles for those directives.
+///
+/// Returns `None` if the front matter does not configure `admonish`.
+fn chapter_overrides(
+    front_matter: &str,
+    src_dir: Option<&Path>,
+    overrides: &Overrides,
+    render_text_mode: RenderTextMode,
+) -> anyhow::Result<Option<(Overrides, String)>> {
+    let Some(config) = chapter_config_from_str(front_matter)? else {
+        return Ok(None);
+    };
+    let icon_dir = src_dir.unwrap_or(Path::new("."));
+    let style = if render_text_mode == RenderTextMode::Html && !config.custom.is_empty() {
+        let css = chapter_css(
+            icon_dir,
+            &config.custom,
+            overrides.book.color_mode,
+            overrides.book.class_style,
+        )?;
+        format!("<style>\n{css}</style>\n")
+    } else {
+        String::new()
+    };
+    let chapter_overrides = Overrides {
+        book: overrides.book.clone(),
+        builtin: overrides.builtin.clone(),
+        custom: overrides.custom.extended_with(runtime_directives(
+            config.custom,
+            overrides.book.inline_icons.then_some(icon_dir),
+        )?),
+        presets: overrides.presets.clone(),
+        render_hook: overrides.render_hook.clone(),
+        used_directives: Default::default(),
+        failed_blocks: Default::default(),
+        found_blocks: Default::default(),
+        warnings: Default::default(),
+    };
+    Ok(Some((chapter_overrides, style)))
+}
+
+/// The file name of the HTML page rendered from the chapter at `path`.
+///
+/// Mirrors mdbook, which renders `README.md` chapters as `index.html`. Only the
+/// file name is used, so links stay relative to the current page.
+fn html_page_name(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    if stem.eq_ignore_ascii_case("readme") {
+        Some("index.html".to_owned())
+    } else {
+        Some(format!("{stem}.html"))
+    }
+}
+
+/// Whether `content` may contain admonitions, as a cheap check before parsing.
+///
+/// This may return `true` for content without admonitions, such as when the
+/// keyword is only used in text, in which case the full parser is still run.
+pub(crate) fn may_contain_admonitions(content: &str, overrides: &Overrides) -> bool {
+    content.contains(ADMONISH_BLOCK_KEYWORD)
+        || (overrides.book.mkdocs_syntax && may_contain_mkdocs_blocks(content))
+}
+
 /// Returns the indent of the given position.
 ///
 /// Defined as the number of characters between the given `position` (where
@@ -91,8 +451,14 @@ fn indent_of(content: &str, position: usize, max: usize) -> usize {
 #[cfg(test)]
 mod test {
     use pretty_assertions::assert_eq;
+    use std::sync::Arc;
 
-    use crate::types::AdmonitionDefaults;
+    use crate::render::{AdmonitionRenderHook, RenderHook, RenderedAdmonition};
+    use crate::types::{
+        AdmonitionDefaults, AdmonitionElement, AdmonitionPreset, BlockStyle, BodyTrim,
+        BuiltinDirective, BuiltinDirectiveConfig, ClassStyle, CustomDirective, Density,
+        SummaryMarker, TitleLayout, UntitledIdSource,
+    };
 
     use super::*;
 
@@ -137,9 +503,12 @@ mod test {
     fn prep(content: &str) -> String {
         preprocess(
             content,
+            None,
+            None,
             OnFailure::Continue,
             &Overrides::default(),
             RenderTextMode::Html,
+            None,
         )
         .unwrap()
     }
@@ -155,7 +524,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-note" class="admonition admonish-note" role="note" aria-labelledby="admonition-note-title">
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
 <div class="admonition-title">
 <div id="admonition-note-title">
 
@@ -189,7 +558,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-note" class="admonition admonish-note" role="note" aria-labelledby="admonition-note-title">
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
 <div class="admonition-title">
 <div id="admonition-note-title">
 
@@ -223,7 +592,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-warning" class="admonition admonish-warning" role="note" aria-labelledby="admonition-warning-title">
+<div id="admonition-warning" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-warning-title">
 <div class="admonition-title">
 <div id="admonition-warning-title">
 
@@ -255,7 +624,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-caution" class="admonition admonish-warning" role="note" aria-labelledby="admonition-caution-title">
+<div id="admonition-caution" class="admonition admonish-warning" data-admonish-directive="caution" role="note" aria-labelledby="admonition-caution-title">
 <div class="admonition-title">
 <div id="admonition-caution-title">
 
@@ -287,7 +656,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-read-this" class="admonition admonish-warning" role="note" aria-labelledby="admonition-read-this-title">
+<div id="admonition-read-this" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-read-this-title">
 <div class="admonition-title">
 <div id="admonition-read-this-title">
 
@@ -383,7 +752,7 @@ hello
 
         let expected = r##"
 
-<div id="admonition-and-in-the-title" class="admonition admonish-note" role="note" aria-labelledby="admonition-and-in-the-title-title">
+<div id="admonition-and-in-the-title" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-and-in-the-title-title">
 <div class="admonition-title">
 <div id="admonition-and-in-the-title-title">
 
@@ -415,7 +784,7 @@ hello
 
         let expected = r##"
 
-<div id="admonition-trademark" class="admonition admonish-warning" role="note" aria-labelledby="admonition-trademark-title">
+<div id="admonition-trademark" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-trademark-title">
 <div class="admonition-title">
 <div id="admonition-trademark-title">
 
@@ -446,7 +815,7 @@ Will have bonus classnames
 
         let expected = r##"
 
-<div id="admonition-tip" class="admonition admonish-tip my-style other-style" role="note" aria-labelledby="admonition-tip-title">
+<div id="admonition-tip" class="admonition admonish-tip my-style other-style" data-admonish-directive="tip" role="note" aria-labelledby="admonition-tip-title">
 <div class="admonition-title">
 <div id="admonition-tip-title">
 
@@ -476,7 +845,7 @@ Will have bonus classnames
 
         let expected = r##"
 
-<div id="admonition-developers-dont-want-you-to-know-this-one-weird-tip" class="admonition admonish-tip my-style other-style" role="note" aria-labelledby="admonition-developers-dont-want-you-to-know-this-one-weird-tip-title">
+<div id="admonition-developers-dont-want-you-to-know-this-one-weird-tip" class="admonition admonish-tip my-style other-style" data-admonish-directive="tip" role="note" aria-labelledby="admonition-developers-dont-want-you-to-know-this-one-weird-tip-title">
 <div class="admonition-title">
 <div id="admonition-developers-dont-want-you-to-know-this-one-weird-tip-title">
 
@@ -505,7 +874,7 @@ Will have bonus classnames
 
         let expected = r#"
 
-<div id="admonition-default" class="admonition admonish-note" role="note">
+<div id="admonition-default" class="admonition admonish-note" data-admonish-directive="note" role="note">
 <div>
 
 
@@ -531,7 +900,7 @@ Content one.
 
         let expected = r##"
 
-<div id="admonition-my-note" class="admonition admonish-note" role="note" aria-labelledby="admonition-my-note-title">
+<div id="admonition-my-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-my-note-title">
 <div class="admonition-title">
 <div id="admonition-my-note-title">
 
@@ -548,7 +917,7 @@ Content zero.
 </div>
 
 
-<div id="admonition-my-note-1" class="admonition admonish-note" role="note" aria-labelledby="admonition-my-note-1-title">
+<div id="admonition-my-note-1" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-my-note-1-title">
 <div class="admonition-title">
 <div id="admonition-my-note-1-title">
 
@@ -578,7 +947,7 @@ Bonus content!
 
         let expected = r##"
 
-<div id="admonition-article-heading" class="admonition admonish-tip my other-style" role="note" aria-labelledby="admonition-article-heading-title">
+<div id="admonition-article-heading" class="admonition admonish-tip my other-style" data-admonish-directive="tip" role="note" aria-labelledby="admonition-article-heading-title">
 <div class="admonition-title">
 <div id="admonition-article-heading-title">
 
@@ -608,7 +977,7 @@ Bonus content!
 
         let expected = r##"
 
-<div id="admonition-error-rendering-admonishment" class="admonition admonish-bug" role="note" aria-labelledby="admonition-error-rendering-admonishment-title">
+<div id="admonition-error-rendering-admonishment" class="admonition admonish-bug" data-admonish-directive="bug" role="note" aria-labelledby="admonition-error-rendering-admonishment-title">
 <div class="admonition-title">
 <div id="admonition-error-rendering-admonishment-title">
 
@@ -658,9 +1027,12 @@ Bonus content!
         assert_eq!(
             preprocess(
                 content,
+                None,
+                None,
                 OnFailure::Bail,
                 &Overrides::default(),
-                RenderTextMode::Html
+                RenderTextMode::Html,
+                None,
             )
             .unwrap_err()
             .to_string(),
@@ -685,9 +1057,12 @@ x = 20;
         assert_eq!(
             preprocess(
                 content,
+                None,
+                None,
                 OnFailure::Bail,
                 &Overrides::default(),
-                RenderTextMode::Strip
+                RenderTextMode::Strip { annotate: false },
+                None,
             )
             .unwrap(),
             r#"
@@ -712,7 +1087,7 @@ Hidden
 
         let expected = r##"
 
-<details id="admonition-note" class="admonition admonish-note" role="note" aria-labelledby="admonition-note-title">
+<details id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
 <summary class="admonition-title">
 <div id="admonition-note-title">
 
@@ -743,7 +1118,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-admonish" class="admonition admonish-note" role="note" aria-labelledby="admonition-admonish-title">
+<div id="admonition-admonish" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-admonish-title">
 <div class="admonition-title">
 <div id="admonition-admonish-title">
 
@@ -763,16 +1138,20 @@ Text
 
         let preprocess_result = preprocess(
             content,
+            None,
+            None,
             OnFailure::Continue,
             &Overrides {
                 book: AdmonitionDefaults {
                     title: Some("Admonish".to_owned()),
                     css_id_prefix: None,
-                    collapsible: false,
+                    collapsible: Some(false),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
             RenderTextMode::Html,
+            None,
         )
         .unwrap();
         assert_eq!(expected, preprocess_result);
@@ -789,7 +1168,7 @@ Text
 
         let expected = r#"# Chapter
 
-<div id="admonition-default" class="admonition admonish-note" role="note">
+<div id="admonition-default" class="admonition admonish-note" data-admonish-directive="note" role="note">
 <div>
 
 A simple admonition.
@@ -801,16 +1180,20 @@ Text
 
         let preprocess_result = preprocess(
             content,
+            None,
+            None,
             OnFailure::Continue,
             &Overrides {
                 book: AdmonitionDefaults {
                     title: Some("Admonish".to_owned()),
                     css_id_prefix: None,
-                    collapsible: false,
+                    collapsible: Some(false),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
             RenderTextMode::Html,
+            None,
         )
         .unwrap();
         assert_eq!(expected, preprocess_result);
@@ -827,7 +1210,7 @@ Text
 
         let expected = r#"# Chapter
 
-<div id="admonition-default" class="admonition admonish-note" role="note">
+<div id="admonition-default" class="admonition admonish-note" data-admonish-directive="note" role="note">
 <div>
 
 A simple admonition.
@@ -851,7 +1234,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="yay-custom-id" class="admonition admonish-success" role="note" aria-labelledby="yay-custom-id-title">
+<div id="yay-custom-id" class="admonition admonish-success" data-admonish-directive="check" role="note" aria-labelledby="yay-custom-id-title">
 <div class="admonition-title">
 <div id="yay-custom-id-title">
 
@@ -883,7 +1266,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-check" class="admonition admonish-success" role="note" aria-labelledby="admonition-check-title">
+<div id="admonition-check" class="admonition admonish-success" data-admonish-directive="check" role="note" aria-labelledby="admonition-check-title">
 <div class="admonition-title">
 <div id="admonition-check-title">
 
@@ -915,7 +1298,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-check-mark" class="admonition admonish-success" role="note" aria-labelledby="admonition-check-mark-title">
+<div id="admonition-check-mark" class="admonition admonish-success" data-admonish-directive="check" role="note" aria-labelledby="admonition-check-mark-title">
 <div class="admonition-title">
 <div id="admonition-check-mark-title">
 
@@ -950,7 +1333,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="admonition-check-mark" class="admonition admonish-success" role="note" aria-labelledby="admonition-check-mark-title">
+<div id="admonition-check-mark" class="admonition admonish-success" data-admonish-directive="success" role="note" aria-labelledby="admonition-check-mark-title">
 <div class="admonition-title">
 <div id="admonition-check-mark-title">
 
@@ -982,7 +1365,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="info" class="admonition admonish-info" role="note" aria-labelledby="info-title">
+<div id="info" class="admonition admonish-info" data-admonish-directive="info" role="note" aria-labelledby="info-title">
 <div class="admonition-title">
 <div id="info-title">
 
@@ -1002,16 +1385,20 @@ Text
 
         let preprocess_result = preprocess(
             content,
+            None,
+            None,
             OnFailure::Continue,
             &Overrides {
                 book: AdmonitionDefaults {
                     title: Some("Info".to_owned()),
                     css_id_prefix: Some("".to_owned()),
-                    collapsible: false,
+                    collapsible: Some(false),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
             RenderTextMode::Html,
+            None,
         )
         .unwrap();
         assert_eq!(expected, preprocess_result);
@@ -1028,7 +1415,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="prefix-my-title" class="admonition admonish-info" role="note" aria-labelledby="prefix-my-title-title">
+<div id="prefix-my-title" class="admonition admonish-info" data-admonish-directive="info" role="note" aria-labelledby="prefix-my-title-title">
 <div class="admonition-title">
 <div id="prefix-my-title-title">
 
@@ -1048,16 +1435,20 @@ Text
 
         let preprocess_result = preprocess(
             content,
+            None,
+            None,
             OnFailure::Continue,
             &Overrides {
                 book: AdmonitionDefaults {
                     title: Some("Info".to_owned()),
                     css_id_prefix: Some("prefix-".to_owned()),
-                    collapsible: false,
+                    collapsible: Some(false),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
             RenderTextMode::Html,
+            None,
         )
         .unwrap();
         assert_eq!(expected, preprocess_result);
@@ -1074,7 +1465,7 @@ Text
 
         let expected = r##"# Chapter
 
-<div id="my-section-id" class="admonition admonish-info" role="note" aria-labelledby="my-section-id-title">
+<div id="my-section-id" class="admonition admonish-info" data-admonish-directive="info" role="note" aria-labelledby="my-section-id-title">
 <div class="admonition-title">
 <div id="my-section-id-title">
 
@@ -1094,16 +1485,20 @@ Text
 
         let preprocess_result = preprocess(
             content,
+            None,
+            None,
             OnFailure::Continue,
             &Overrides {
                 book: AdmonitionDefaults {
                     title: Some("Info".to_owned()),
                     css_id_prefix: Some("ignored-prefix-".to_owned()),
-                    collapsible: false,
+                    collapsible: Some(false),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
             RenderTextMode::Html,
+            None,
         )
         .unwrap();
         assert_eq!(expected, preprocess_result);
@@ -1143,7 +1538,7 @@ Text
 1. Thing two
 
    
-   <div id="admonition-note" class="admonition admonish-note" role="note" aria-labelledby="admonition-note-title">
+   <div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
    <div class="admonition-title">
    <div id="admonition-note-title">
    
@@ -1168,4 +1563,2822 @@ Text
 
         assert_eq!(expected, prep(content));
     }
+
+    #[test]
+    fn element_aside() {
+        let content = r#"# Chapter
+```admonish
+A simple admonition.
+```
+```admonish collapsible=true
+A collapsible admonition.
+```
+Text
+"#;
+
+        let expected = r##"# Chapter
+
+<aside id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+A simple admonition.
+
+</div>
+</aside>
+
+<details id="admonition-note-1" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-1-title">
+<summary class="admonition-title">
+<div id="admonition-note-1-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note-1"></a>
+</summary>
+<div>
+
+A collapsible admonition.
+
+</div>
+</details>
+Text
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    element: AdmonitionElement::Aside,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn title_anchor_combinations() {
+        fn prep_title_anchor(content: &str, title_anchor: bool) -> String {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &Overrides {
+                    book: AdmonitionDefaults {
+                        title_anchor: Some(title_anchor),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        }
+
+        let with_title = r#"
+```admonish title="Title"
+Content.
+```
+"#;
+        let without_title = r#"
+```admonish title=""
+Content.
+```
+"#;
+
+        // Title present, anchor on
+        assert_eq!(
+            prep_title_anchor(with_title, true),
+            r##"
+
+<div id="admonition-title" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-title-title">
+<div class="admonition-title">
+<div id="admonition-title-title">
+
+Title
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-title"></a>
+</div>
+<div>
+
+Content.
+
+</div>
+</div>
+"##
+        );
+
+        // Title present, anchor off
+        assert_eq!(
+            prep_title_anchor(with_title, false),
+            r##"
+
+<div id="admonition-title" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-title-title">
+<div class="admonition-title">
+<div id="admonition-title-title">
+
+Title
+
+</div>
+</div>
+<div>
+
+Content.
+
+</div>
+</div>
+"##
+        );
+
+        // Title absent, anchor on or off
+        let expected_without_title = r##"
+
+<div id="admonition-default" class="admonition admonish-note" data-admonish-directive="note" role="note">
+<div>
+
+Content.
+
+</div>
+</div>
+"##;
+        assert_eq!(
+            prep_title_anchor(without_title, true),
+            expected_without_title
+        );
+        assert_eq!(
+            prep_title_anchor(without_title, false),
+            expected_without_title
+        );
+    }
+
+    #[test]
+    fn bail_on_duplicate_verbatim_id() {
+        let content = r#"
+```admonish id="my-id"
+First.
+```
+
+```admonish id="other-id"
+Other.
+```
+
+```admonish id="my-id"
+Second.
+```
+"#;
+        assert_eq!(
+            preprocess(
+                content,
+                Some(Path::new("chapter_1.md")),
+                None,
+                OnFailure::Bail,
+                &Overrides::default(),
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap_err()
+            .to_string(),
+            "Duplicate admonition id 'my-id' at chapter_1.md:10, first used at chapter_1.md:2"
+        );
+
+        // Ids are not rendered when stripping
+        assert!(preprocess(
+            content,
+            Some(Path::new("chapter_1.md")),
+            None,
+            OnFailure::Bail,
+            &Overrides::default(),
+            RenderTextMode::Strip { annotate: false },
+            None,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn continue_on_error_output_collapsible() {
+        let content = r#"
+```admonish title="
+Bonus content!
+```
+"#;
+
+        let expected = r##"
+
+<details id="admonition-error-rendering-admonishment" class="admonition admonish-bug" data-admonish-directive="bug" role="note" aria-labelledby="admonition-error-rendering-admonishment-title">
+<summary class="admonition-title">
+<div id="admonition-error-rendering-admonishment-title">
+
+Error rendering admonishment
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-error-rendering-admonishment"></a>
+</summary>
+<div>
+
+Failed with:
+
+```log
+'title="' is not a valid directive or TOML key-value pair.
+
+TOML parsing error: TOML parse error at line 1, column 21
+  |
+1 | config = { title=" }
+  |                     ^
+invalid basic string
+
+```
+
+Original markdown input:
+
+````markdown
+```admonish title="
+Bonus content!
+```
+````
+
+
+</div>
+</details>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    error_collapsible: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn show_badge() {
+        let content = r#"
+```admonish warning
+Default title.
+```
+
+```admonish warning title="Custom title"
+Custom title.
+```
+
+```admonish warning badge=false
+No badge.
+```
+
+```admonish warning title=""
+No title.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-warning" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-warning-title">
+<div class="admonition-title">
+<div id="admonition-warning-title">
+
+Warning
+
+</div>
+<span class="admonition-badge">warning</span>
+<a class="admonition-anchor-link" href="#admonition-warning"></a>
+</div>
+<div>
+
+Default title.
+
+</div>
+</div>
+
+
+<div id="admonition-custom-title" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-custom-title-title">
+<div class="admonition-title">
+<div id="admonition-custom-title-title">
+
+Custom title
+
+</div>
+<span class="admonition-badge">warning</span>
+<a class="admonition-anchor-link" href="#admonition-custom-title"></a>
+</div>
+<div>
+
+Custom title.
+
+</div>
+</div>
+
+
+<div id="admonition-warning-1" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-warning-1-title">
+<div class="admonition-title">
+<div id="admonition-warning-1-title">
+
+Warning
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-warning-1"></a>
+</div>
+<div>
+
+No badge.
+
+</div>
+</div>
+
+
+<div id="admonition-default" class="admonition admonish-warning" data-admonish-directive="warning" role="note">
+<span class="admonition-badge">warning</span>
+<div>
+
+No title.
+
+</div>
+</div>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    show_badge: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn avoid_page_break() {
+        let content = r#"
+```admonish
+Content.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title" style="break-inside: avoid">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+Content.
+
+</div>
+</div>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    avoid_page_break: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn caption() {
+        let content = r#"
+```admonish caption='Figure <1> & "more"'
+Content.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+Content.
+
+</div>
+<div class="admonition-caption">Figure &lt;1&gt; &amp; &quot;more&quot;</div>
+</div>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides::default(),
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn untitled_id_source() {
+        let content = r#"
+```admonish warning title=""
+One.
+```
+```admonish warning title=""
+Two.
+```
+"#;
+
+        let expected_ids = [
+            (
+                UntitledIdSource::Default,
+                ["admonition-default", "admonition-default-1"],
+            ),
+            (
+                UntitledIdSource::Directive,
+                ["admonition-warning", "admonition-warning-1"],
+            ),
+            (UntitledIdSource::Index, ["admonition-1", "admonition-2"]),
+        ];
+        for (untitled_id_source, [first, second]) in expected_ids {
+            let expected = format!(
+                r#"
+
+<div id="{first}" class="admonition admonish-warning" data-admonish-directive="warning" role="note">
+<div>
+
+One.
+
+</div>
+</div>
+
+<div id="{second}" class="admonition admonish-warning" data-admonish-directive="warning" role="note">
+<div>
+
+Two.
+
+</div>
+</div>
+"#
+            );
+
+            let preprocess_result = preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &Overrides {
+                    book: AdmonitionDefaults {
+                        untitled_id_source,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap();
+            assert_eq!(expected, preprocess_result);
+        }
+    }
+
+    #[test]
+    fn front_matter() {
+        let content = r#"
+```admonish
+title: Front matter
+collapsible: true
+---
+Content.
+```
+"#;
+
+        let expected = r##"
+
+<details id="admonition-front-matter" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-front-matter-title">
+<summary class="admonition-title">
+<div id="admonition-front-matter-title">
+
+Front matter
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-front-matter"></a>
+</summary>
+<div>
+
+Content.
+
+</div>
+</details>
+"##;
+
+        assert_eq!(expected, prep(content));
+    }
+
+    #[test]
+    fn instance_id_prefix() {
+        let content = r#"
+```admonish title="Term", id_prefix="glossary-"
+Definition.
+```
+"#;
+
+        let expected = r##"
+
+<div id="glossary-term" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="glossary-term-title">
+<div class="admonition-title">
+<div id="glossary-term-title">
+
+Term
+
+</div>
+<a class="admonition-anchor-link" href="#glossary-term"></a>
+</div>
+<div>
+
+Definition.
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+    }
+
+    #[test]
+    fn numbered_directives() {
+        let content = r#"
+```admonish example
+One.
+```
+```admonish example title="Custom"
+Two.
+```
+```admonish note
+Not numbered.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-example" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-example-title">
+<div class="admonition-title">
+<div id="admonition-example-title">
+
+Example 1
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-example"></a>
+</div>
+<div>
+
+One.
+
+</div>
+</div>
+
+<div id="admonition-custom" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-custom-title">
+<div class="admonition-title">
+<div id="admonition-custom-title">
+
+Example 2: Custom
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-custom"></a>
+</div>
+<div>
+
+Two.
+
+</div>
+</div>
+
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+Not numbered.
+
+</div>
+</div>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    numbered_directives: vec!["example".to_owned()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn density_compact() {
+        let content = r#"
+```admonish
+Default.
+```
+```admonish density="compact"
+Compact.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+Default.
+
+</div>
+</div>
+
+<div id="admonition-note-1" class="admonition admonish-note admonition-compact" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-1-title">
+<div class="admonition-title">
+<div id="admonition-note-1-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note-1"></a>
+</div>
+<div>
+
+Compact.
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+
+        // Per-block values override the book default
+        let content = r#"
+```admonish density="normal"
+Normal.
+```
+"#;
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    density: Density::Compact,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(!preprocess_result.contains("admonition-compact"));
+    }
+
+    #[test]
+    fn test_renderer_strip_annotate() {
+        let content = r#"
+```admonish warning title="Careful -->", id="careful", class="a b"
+Content.
+```
+"#;
+        assert_eq!(
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Bail,
+                &Overrides::default(),
+                RenderTextMode::Strip { annotate: true },
+                None,
+            )
+            .unwrap(),
+            r#"
+<!-- admonish: {"class":["a","b"],"collapsible":false,"directive":"warning","id":"careful","title":"Careful --\u003e"} -->
+Content.
+<!-- /admonish -->
+"#
+        )
+    }
+
+    #[test]
+    fn continue_on_error_output_error_directive() {
+        let content = r#"
+```admonish title="
+Bonus content!
+```
+"#;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    error_directive: Some("error".to_owned()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(preprocess_result.contains(
+            r#"<div id="admonition-error-rendering-admonishment" class="admonition admonish-danger" data-admonish-directive="error" role="note""#
+        ));
+    }
+
+    #[test]
+    fn renderers_filter() {
+        let content = r#"Before
+```admonish renderers="html, pdf"
+HTML and PDF only.
+```
+```admonish renderers="epub"
+EPUB only.
+```
+After"#;
+
+        let render = |renderer| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &Overrides::default(),
+                RenderTextMode::Strip { annotate: false },
+                renderer,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            render(Some("html")),
+            "Before\n\nHTML and PDF only.\n\n\n\n\nAfter"
+        );
+        assert_eq!(render(Some("epub")), "Before\n\n\n\n\nEPUB only.\n\nAfter");
+        // If the renderer is not known, all blocks are shown
+        assert_eq!(
+            render(None),
+            "Before\n\nHTML and PDF only.\n\n\nEPUB only.\n\nAfter"
+        );
+    }
+
+    #[test]
+    fn title_layout_stacked() {
+        let content = r#"
+```admonish warning
+Stacked.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-warning" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-warning-title">
+<div class="admonition-title admonition-title-stacked">
+<div id="admonition-warning-title">
+
+Warning
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-warning"></a>
+</div>
+<div>
+
+Stacked.
+
+</div>
+</div>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    title_layout: TitleLayout::Stacked,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+
+        // The inline layout is unchanged
+        assert!(prep(content).contains(r#"<div class="admonition-title">"#));
+    }
+
+    #[test]
+    fn mkdocs_syntax() {
+        let content = r#"!!! note
+    Fenced blocks still work alongside.
+
+???+ tip "Expanded"
+    Body.
+
+```admonish warning
+Fenced.
+```
+"#;
+
+        let expected = r##"
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+Fenced blocks still work alongside.
+
+</div>
+</div>
+
+
+<details id="admonition-expanded" class="admonition admonish-tip" data-admonish-directive="tip" role="note" aria-labelledby="admonition-expanded-title" open="">
+<summary class="admonition-title">
+<div id="admonition-expanded-title">
+
+Expanded
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-expanded"></a>
+</summary>
+<div>
+
+Body.
+
+</div>
+</details>
+
+
+<div id="admonition-warning" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-warning-title">
+<div class="admonition-title">
+<div id="admonition-warning-title">
+
+Warning
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-warning"></a>
+</div>
+<div>
+
+Fenced.
+
+</div>
+</div>
+"##;
+
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                mkdocs_syntax: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &overrides,
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+
+        // Not converted unless enabled
+        assert!(prep(content).starts_with("!!! note\n"));
+    }
+
+    #[test]
+    fn collapsible_summary() {
+        let content = r#"
+```admonish example title="Full build log", summary="Show details", collapsible=true
+Build succeeded.
+```
+
+```admonish example summary="Ignored"
+Not collapsible.
+```
+"#;
+
+        let expected = r##"
+
+<details id="admonition-full-build-log" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-full-build-log-title">
+<summary class="admonition-title">
+<div id="admonition-full-build-log-title">
+
+Show details
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-full-build-log"></a>
+</summary>
+<div>
+<div class="admonition-expanded-title">
+
+Full build log
+
+</div>
+
+Build succeeded.
+
+</div>
+</details>
+
+
+<div id="admonition-example" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-example-title">
+<div class="admonition-title">
+<div id="admonition-example-title">
+
+Example
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-example"></a>
+</div>
+<div>
+
+Not collapsible.
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+    }
+
+    #[test]
+    fn may_contain_admonitions_check() {
+        let overrides = Overrides::default();
+        assert!(!may_contain_admonitions(
+            "# Chapter\n\nNo blocks.\n",
+            &overrides
+        ));
+        assert!(may_contain_admonitions(
+            "```admonish\nText\n```",
+            &overrides
+        ));
+        // Mentions of the keyword still run the full parser
+        assert!(may_contain_admonitions("Use admonish blocks.", &overrides));
+
+        let content = "!!! note\n    Text\n";
+        assert!(!may_contain_admonitions(content, &overrides));
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                mkdocs_syntax: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(may_contain_admonitions(content, &overrides));
+    }
+
+    #[test]
+    fn custom_attributes() {
+        let content = r#"
+```admonish note attrs='data-tooltip=a"b<c lang=en'
+Text.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title" data-tooltip="a&quot;b&lt;c" lang="en">
+<div class="admonition-title">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div>
+
+Text.
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+    }
+
+    #[test]
+    fn reference_links_resolve_in_body() {
+        for content in [
+            // Defined after the block
+            "```admonish\nSee [the docs][docs].\n```\n\n[docs]: https://example.com\n",
+            // Defined before the block
+            "[docs]: https://example.com\n\n```admonish\nSee [the docs][docs].\n```\n",
+            // Block indented in a list item
+            "- Item\n\n  ```admonish\n  See [the docs][docs].\n  ```\n\n[docs]: https://example.com\n",
+            // Used in the title
+            "```admonish title=\"[the docs][docs]\"\nText.\n```\n\n[docs]: https://example.com\n",
+        ] {
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, Parser::new(&prep(content)));
+            assert!(
+                html.contains(r#"<a href="https://example.com">the docs</a>"#),
+                "reference not resolved for:\n{content}\n\n{html}"
+            );
+        }
+    }
+
+    #[test]
+    fn blockquote_render_mode() {
+        let content = r#"
+```admonish warning title="Careful", collapsible=true
+Titled.
+```
+
+```admonish note title=""
+Untitled.
+```
+"#;
+
+        let expected = r#"
+
+<blockquote>
+<p><strong>Careful</strong></p>
+
+Titled.
+
+</blockquote>
+
+
+<blockquote>
+
+Untitled.
+
+</blockquote>
+"#;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides::default(),
+            RenderTextMode::Blockquote,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn width_and_align() {
+        let content = r#"
+```admonish info align="right", width="300px"
+Side note.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-info" class="admonition admonish-info admonition-float-right" data-admonish-directive="info" role="note" aria-labelledby="admonition-info-title" style="width: 300px">
+<div class="admonition-title">
+<div id="admonition-info-title">
+
+Info
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-info"></a>
+</div>
+<div>
+
+Side note.
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+
+        // Width is combined with other inline styles
+        let preprocess_result = preprocess(
+            "```admonish align=\"left\" width=\"40%\"\nSide note.\n```\n",
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    avoid_page_break: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(preprocess_result.contains("admonition-float-left"));
+        assert!(preprocess_result.contains(r#"style="break-inside: avoid; width: 40%""#));
+
+        // Invalid values are errors
+        for content in [
+            "```admonish width=\"300px; color: red\"\nText\n```\n",
+            "```admonish align=\"center\"\nText\n```\n",
+        ] {
+            assert!(prep(content).contains("Error rendering admonishment"));
+        }
+    }
+
+    #[test]
+    fn body_prefix_and_suffix() {
+        let overrides = Overrides {
+            builtin: HashMap::from([(
+                BuiltinDirective::Warning,
+                BuiltinDirectiveConfig {
+                    collapsible: None,
+                    body_prefix: None,
+                    body_suffix: Some("Report issues to **compliance**.".to_owned()),
+                    role: None,
+                    aria_label: None,
+                },
+            )]),
+            custom: [CustomDirective {
+                directive: "frog".to_owned(),
+                aliases: Vec::new(),
+                renamed_from: Vec::new(),
+                title: None,
+                collapsible: None,
+                alias_collapsible: Default::default(),
+                body_prefix: Some("First line.\nSecond line.".to_owned()),
+                body_suffix: Some("- A\n- B".to_owned()),
+                role: None,
+                aria_label: None,
+                icon_svg: None,
+            }]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        let content = r#"
+```admonish warning
+Be careful.
+```
+"#;
+        let expected = r##"
+
+<div id="admonition-warning" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-warning-title">
+<div class="admonition-title">
+<div id="admonition-warning-title">
+
+Warning
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-warning"></a>
+</div>
+<div>
+
+Be careful.
+
+Report issues to **compliance**.
+
+</div>
+</div>
+"##;
+        assert_eq!(expected, render(content));
+
+        // Indented blocks keep every line inside the block
+        let content = r#"
+- List item
+
+  ```admonish frog
+  Body.
+  ```
+"#;
+        let expected = r##"
+- List item
+
+  
+  <div id="admonition-frog" class="admonition admonish-frog" data-admonish-directive="frog" role="note" aria-labelledby="admonition-frog-title">
+  <div class="admonition-title">
+  <div id="admonition-frog-title">
+  
+  Frog
+  
+  </div>
+  <a class="admonition-anchor-link" href="#admonition-frog"></a>
+  </div>
+  <div>
+  
+  First line.
+  Second line.
+  
+    Body.
+  
+  - A
+  - B
+  
+  </div>
+  </div>
+"##;
+        assert_eq!(expected, render(content));
+
+        // Other directives are unchanged
+        assert!(!render("```admonish note\nText\n```\n").contains("compliance"));
+    }
+
+    #[test]
+    fn absolute_anchor_links() {
+        let render = |path: &str, absolute_anchor_links: bool| {
+            preprocess(
+                "```admonish\nText\n```\n",
+                Some(Path::new(path)),
+                None,
+                OnFailure::Continue,
+                &Overrides {
+                    book: AdmonitionDefaults {
+                        absolute_anchor_links,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        // Relative by default
+        assert!(render("chapter_1/intro.md", false)
+            .contains(r##"<a class="admonition-anchor-link" href="#admonition-note"></a>"##));
+        assert!(render("chapter_1/intro.md", true).contains(
+            r##"<a class="admonition-anchor-link" href="intro.html#admonition-note"></a>"##
+        ));
+        assert!(render("chapter_1/README.md", true).contains(
+            r##"<a class="admonition-anchor-link" href="index.html#admonition-note"></a>"##
+        ));
+
+        // Without a chapter path, links stay relative
+        let preprocess_result = preprocess(
+            "```admonish\nText\n```\n",
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    absolute_anchor_links: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(preprocess_result.contains(r##"href="#admonition-note""##));
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let lf = "```admonish title=\"Title\"\nLine one\nline two.\n\nParagraph.\n```";
+        let crlf = lf.replace('\n', "\r\n");
+        let rendered = prep(&crlf);
+        assert!(!rendered.contains('\r'));
+        assert_eq!(rendered, prep(lf));
+    }
+
+    #[test]
+    fn duplicate_classes() {
+        for (content, expected) in [
+            (
+                "```admonish note class=\"b a b\"\nText\n```",
+                r#"class="admonition admonish-note b a""#,
+            ),
+            // Duplicates of generated classes
+            (
+                "```admonish note class=\"admonish-note admonition x\"\nText\n```",
+                r#"class="admonition admonish-note x""#,
+            ),
+        ] {
+            assert!(prep(content).contains(expected), "{content}");
+        }
+    }
+
+    #[test]
+    fn icon_false() {
+        let content = r#"
+```admonish tip icon=false
+Plain.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-tip" class="admonition admonish-tip admonition-no-icon" data-admonish-directive="tip" role="note" aria-labelledby="admonition-tip-title">
+<div class="admonition-title">
+<div id="admonition-tip-title">
+
+Tip
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-tip"></a>
+</div>
+<div>
+
+Plain.
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+        assert!(!prep("```admonish tip icon=true\nText\n```").contains("admonition-no-icon"));
+    }
+
+    #[test]
+    fn lang_attribute() {
+        let content = r#"
+```admonish example lang="rust"
+Text
+```
+"#;
+        assert!(prep(content).contains(
+            r#"<div id="admonition-example" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-example-title" lang="rust">"#
+        ));
+
+        // Only present when set
+        assert!(!prep("```admonish example\nText\n```").contains("lang="));
+
+        // Takes precedence over `attrs`
+        let rendered =
+            prep("```admonish example lang=\"fr\", attrs=\"lang=en data-x=1\"\nText\n```");
+        assert!(rendered.contains(r#" lang="fr" data-x="1">"#));
+        assert!(!rendered.contains(r#"lang="en""#));
+
+        // Values that could break out of the attribute are rejected
+        assert!(prep("```admonish example lang='\"onclick=\"x'\nText\n```")
+            .contains("Error rendering admonishment"));
+    }
+
+    #[test]
+    fn mismatched_fence_characters() {
+        let content = r#"
+```admonish
+Text
+~~~
+
+After.
+"#;
+        let rendered = prep(content);
+        assert!(rendered.contains("Error rendering admonishment"));
+        assert!(rendered.contains("Block opened with '```' looks like it was closed with '~~~'."));
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Bail,
+            &Overrides::default(),
+            RenderTextMode::Html,
+            None,
+        );
+        assert!(preprocess_result.is_err());
+    }
+
+    #[test]
+    fn attribute_values_escaped() {
+        let content = r#"
+```admonish note id='a"b&c', title="<em>Raw</em> title"
+Text
+```
+"#;
+
+        let expected = r##"
+
+<div id="a&quot;b&amp;c" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="a&quot;b&amp;c-title">
+<div class="admonition-title">
+<div id="a&quot;b&amp;c-title">
+
+<em>Raw</em> title
+
+</div>
+<a class="admonition-anchor-link" href="#a&quot;b&amp;c"></a>
+</div>
+<div>
+
+Text
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, prep(content));
+
+        // Prefixes of generated ids are also escaped
+        assert!(prep("```admonish note id_prefix='x\"&'\nText\n```")
+            .contains(r#"id="x&quot;&amp;note""#));
+
+        // Classes can't contain quotes or ampersands at all
+        assert!(prep("```admonish note class='a\"b c&d'\nText\n```")
+            .contains("Error rendering admonishment"));
+    }
+
+    #[test]
+    fn render_hook() {
+        struct Wrap;
+
+        impl AdmonitionRenderHook for Wrap {
+            fn render(&self, admonition: &RenderedAdmonition<'_>, html: String) -> String {
+                format!(
+                    "<section data-id=\"{}\" data-title=\"{}\">{html}\n</section>",
+                    admonition.id, admonition.title
+                )
+            }
+        }
+
+        let content = r#"
+```admonish tip title="Custom"
+Body.
+```
+"#;
+        let expected = r##"
+<section data-id="admonition-custom" data-title="Custom">
+<div id="admonition-custom" class="admonition admonish-tip" data-admonish-directive="tip" role="note" aria-labelledby="admonition-custom-title">
+<div class="admonition-title">
+<div id="admonition-custom-title">
+
+Custom
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-custom"></a>
+</div>
+<div>
+
+Body.
+
+</div>
+</div>
+</section>
+"##;
+
+        let overrides = Overrides {
+            render_hook: Some(RenderHook(Arc::new(Wrap))),
+            ..Default::default()
+        };
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &overrides,
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+
+        // Not called for other render modes
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &overrides,
+            RenderTextMode::Strip { annotate: false },
+            None,
+        )
+        .unwrap();
+        assert!(!preprocess_result.contains("<section"));
+    }
+
+    #[test]
+    fn html_content() {
+        let markdown = prep(
+            r#"
+```admonish note title="Table"
+<table><tr><td>Cell</td></tr></table>
+```
+"#,
+        );
+        let html = prep(
+            r#"
+```admonish note title="Table", content="html"
+<table><tr><td>Cell</td></tr></table>
+```
+"#,
+        );
+        let expected = r##"
+
+<div id="admonition-table" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-table-title">
+<div class="admonition-title">
+<div id="admonition-table-title">
+
+Table
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-table"></a>
+</div>
+<div>
+<table><tr><td>Cell</td></tr></table>
+</div>
+</div>
+"##;
+        assert_eq!(expected, html);
+        // Markdown bodies are surrounded by blank lines
+        assert_eq!(
+            markdown,
+            html.replace(
+                "<div>\n<table><tr><td>Cell</td></tr></table>\n</div>",
+                "<div>\n\n<table><tr><td>Cell</td></tr></table>\n\n</div>"
+            )
+        );
+
+        // Other values are rejected
+        assert!(prep("```admonish note content=\"rst\"\nText\n```")
+            .contains("Error rendering admonishment"));
+    }
+
+    #[test]
+    fn inline() {
+        let content = r#"
+```admonish tip inline=true
+Use `cargo fmt` before *committing*.
+```
+"#;
+        let expected = r#"
+
+<span id="admonition-tip" class="admonition-inline admonish-tip" data-admonish-directive="tip" role="note"><span class="admonition-title" aria-hidden="true"></span>Use `cargo fmt` before *committing*.</span>
+"#;
+        assert_eq!(expected, prep(content));
+
+        // Without the icon, and with additional classes
+        assert!(prep("```admonish tip inline=true, icon=false, class=\"small\"\nShort.\n```")
+            .contains(r#"class="admonition-inline admonish-tip admonition-no-icon small" data-admonish-directive="tip" role="note">Short.</span>"#));
+
+        // Never collapsible by default
+        let preprocess_result = preprocess(
+            "```admonish tip inline=true\nShort.\n```",
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    collapsible: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(preprocess_result.contains("<span id=\"admonition-tip\""));
+        assert!(!preprocess_result.contains("<details"));
+
+        // Explicitly collapsible blocks can't be inline
+        let rendered = prep("```admonish tip inline=true, collapsible=true\nShort.\n```");
+        assert!(rendered.contains("Error rendering admonishment"));
+        assert!(rendered.contains("Inline blocks can't be collapsible"));
+    }
+
+    #[test]
+    fn test_split_chapter_front_matter() {
+        assert_eq!(
+            split_chapter_front_matter("+++\nkey = 1\n+++\nBody\n+++\n"),
+            Some(("key = 1\n", "Body\n+++\n"))
+        );
+        assert_eq!(
+            split_chapter_front_matter("+++\r\nkey = 1\r\n+++\r\nBody"),
+            Some(("key = 1\r\n", "Body"))
+        );
+        assert_eq!(split_chapter_front_matter("+++\n+++"), Some(("", "")));
+        // Must be at the start, and closed
+        assert_eq!(split_chapter_front_matter("\n+++\nkey = 1\n+++\n"), None);
+        assert_eq!(split_chapter_front_matter("+++\nkey = 1\n"), None);
+        assert_eq!(split_chapter_front_matter("Body"), None);
+    }
+
+    #[test]
+    fn chapter_custom_directives() {
+        let content = r#"+++
+[admonish.custom.frog]
+icon = "builtin:tip"
+color = "green"
+aliases = ["toad"]
+
+[admonish.custom.alert]
+extends = "danger"
+title = "Alert!"
++++
+# Chapter
+
+```admonish toad
+Local.
+```
+
+```admonish alert
+Extended.
+```
+
+```admonish note
+Global.
+```
+"#;
+        let rendered = prep(content);
+        assert!(rendered.starts_with("<style>\n:root {\n"));
+        assert!(rendered.contains(
+            "  --md-admonition-icon--admonish-frog: var(--md-admonition-icon--admonish-tip);\n"
+        ));
+        assert!(
+            rendered.contains(":is(.admonition):is(.admonish-frog) {\n  border-color: #008000;\n}")
+        );
+        assert!(rendered
+            .contains(":is(.admonition):is(.admonish-alert) {\n  border-color: #ff1744;\n}"));
+        // Front matter is removed, keeping the line count
+        assert!(rendered.contains("</style>\n\n\n\n\n\n\n\n\n\n\n# Chapter\n"));
+        assert!(!rendered.contains("+++"));
+        assert!(
+            rendered.contains(r#"class="admonition admonish-frog" data-admonish-directive="toad""#)
+        );
+        assert!(rendered.contains("\nToad\n"));
+        assert!(rendered.contains(r#"class="admonition admonish-alert""#));
+        assert!(rendered.contains("\nAlert!\n"));
+        assert!(rendered.contains(r#"class="admonition admonish-note""#));
+
+        // Only styles the html output
+        let stripped = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides::default(),
+            RenderTextMode::Strip { annotate: false },
+            None,
+        )
+        .unwrap();
+        assert!(stripped.starts_with("\n\n\n\n\n\n\n\n\n\n# Chapter\n"));
+
+        // Directives are scoped to the chapter
+        let overrides = Overrides::default();
+        for _ in 0..2 {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap();
+        }
+        assert!(overrides.custom.get("frog").is_none());
+        assert!(overrides.used_directives.borrow().contains("frog"));
+    }
+
+    #[test]
+    fn chapter_front_matter_without_admonish() {
+        // Left for other tools
+        let content = "+++\nauthor = \"Me\"\n+++\n\n```admonish\nText\n```\n";
+        assert!(prep(content).starts_with("+++\nauthor = \"Me\"\n+++\n"));
+
+        // Invalid config fails the build if configured
+        let content = "+++\n[admonish.custom.frog]\ncolor = \"green\"\n+++\n";
+        assert!(prep(content).starts_with("+++\n"));
+        let error = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Bail,
+            &Overrides::default(),
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Custom directive 'frog' must set 'icon' and 'color', or 'extends' another directive"
+        );
+    }
+
+    #[test]
+    fn data_admonish_directive_unknown() {
+        // Unknown directives are styled as the fallback, but keep what was written
+        let rendered = prep("```admonish frog\nText\n```\n");
+        assert!(
+            rendered.contains(r#"class="admonition admonish-note" data-admonish-directive="frog""#)
+        );
+        // Without a directive, the fallback is used
+        let rendered = prep("```admonish\nText\n```\n");
+        assert!(rendered.contains(r#"data-admonish-directive="note""#));
+    }
+
+    #[test]
+    fn inline_icons() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                inline_icons: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+        let svg = crate::custom::builtin_icon_svg(BuiltinDirective::Tip);
+
+        let rendered = render("```admonish tip\nText\n```\n");
+        assert!(rendered.contains(
+            r#"class="admonition admonish-tip admonition-svg-icon" data-admonish-directive="tip""#
+        ));
+        assert!(rendered.contains(&format!(
+            "<div class=\"admonition-title\">\n<span class=\"admonition-icon\" aria-hidden=\"true\">{svg}</span>\n<div id=\"admonition-tip-title\">\n"
+        )));
+
+        // Inline blocks put the icon in their icon span
+        assert!(render("```admonish tip inline=true\nShort.\n```\n").contains(&format!(
+            r#"<span class="admonition-title" aria-hidden="true"><span class="admonition-icon" aria-hidden="true">{svg}</span></span>Short.</span>"#
+        )));
+
+        // Chapter directives reusing a builtin icon
+        let rendered = render("+++\n[admonish.custom.frog]\nicon = \"builtin:tip\"\ncolor = \"green\"\n+++\n```admonish frog\nText\n```\n");
+        assert!(rendered.contains(r#"class="admonition admonish-frog admonition-svg-icon""#));
+        assert!(rendered.contains(svg));
+
+        // No icon, or inline icons not enabled
+        let rendered = render("```admonish tip icon=false\nText\n```\n");
+        assert!(!rendered.contains("admonition-svg-icon"));
+        assert!(!rendered.contains("<svg"));
+        assert!(!prep("```admonish tip\nText\n```\n").contains("<svg"));
+    }
+
+    #[test]
+    fn collapse_controls() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                collapse_controls: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let render = |content: &str, render_text_mode| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                render_text_mode,
+                None,
+            )
+            .unwrap()
+        };
+
+        let rendered = render(
+            "# Chapter\n\n```admonish note collapsible=true\nText\n```\n",
+            RenderTextMode::Html,
+        );
+        assert!(rendered.starts_with(
+            r#"<div class="admonish-collapse-controls">
+<button type="button" data-admonish-expand="true">Expand all</button>
+<button type="button" data-admonish-expand="false">Collapse all</button>
+</div>
+<script>
+"#
+        ));
+        assert!(rendered.contains("</script>\n\n# Chapter\n"));
+        // The controls and script must stay a single HTML block
+        assert!(!COLLAPSE_CONTROLS_HTML.contains("\n\n"));
+
+        // Only for chapters with collapsible blocks, rendered as html
+        for (content, render_text_mode) in [
+            (
+                "# Chapter\n\n```admonish note\nText\n```\n",
+                RenderTextMode::Html,
+            ),
+            (
+                "# Chapter\n\n```admonish note collapsible=true\nText\n```\n",
+                RenderTextMode::Blockquote,
+            ),
+        ] {
+            assert!(!render(content, render_text_mode).contains("admonish-collapse-controls"));
+        }
+        assert!(!prep("```admonish note collapsible=true\nText\n```\n")
+            .contains("admonish-collapse-controls"));
+    }
+
+    #[test]
+    fn collapsible_impl_aria() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                collapsible_impl: CollapsibleImpl::Aria,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        let expected = r##"
+<div id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<div class="admonition-title">
+<button type="button" class="admonition-toggle" aria-expanded="false" aria-controls="admonition-note-content" aria-labelledby="admonition-note-title"></button>
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</div>
+<div id="admonition-note-content" role="region" aria-labelledby="admonition-note-title" hidden>
+
+Hidden text.
+
+</div>
+</div>
+"##;
+        let rendered = render("```admonish note collapsible=true\nHidden text.\n```\n");
+        assert_eq!(rendered, format!("{expected}\n\n{DISCLOSURE_SCRIPT_HTML}"));
+        assert!(!DISCLOSURE_SCRIPT_HTML.contains("\n\n"));
+
+        // Open blocks start expanded
+        let rendered = render("```admonish note collapsible=true, open=true\nText\n```\n");
+        assert!(
+            rendered.contains(r#"aria-expanded="true" aria-controls="admonition-note-content""#)
+        );
+        assert!(rendered.contains(
+            r#"<div id="admonition-note-content" role="region" aria-labelledby="admonition-note-title">"#
+        ));
+        assert!(!rendered.contains(" open>"));
+
+        // Only collapsible blocks use the widget, and need the script
+        let rendered = render("```admonish note\nText\n```\n");
+        assert!(!rendered.contains("admonition-toggle"));
+        assert!(!rendered.contains("<script>"));
+
+        // Native details are the default
+        let rendered = prep("```admonish note collapsible=true\nText\n```\n");
+        assert!(rendered.contains(r#"<details id="admonition-note""#));
+        assert!(rendered.contains(r#"<summary class="admonition-title">"#));
+        assert!(!rendered.contains("aria-expanded"));
+        assert!(!rendered.contains("<script>"));
+    }
+
+    #[test]
+    fn admonish_in_code_example_verbatim() {
+        // Only the outer fence is a code block, so the example is left alone
+        for content in [
+            "# Example\n\n````markdown\n```admonish note\nText\n```\n````\n",
+            "# Example\n\n```markdown\n```admonish note\nText\n```\n",
+            "# Example\n\n~~~markdown\n```admonish note\nText\n```\n~~~\n",
+            "# Example\n\n    ```admonish note\n    Text\n    ```\n",
+            "- Item\n\n  ````md\n  ```admonish note\n  Text\n  ```\n  ````\n",
+        ] {
+            assert_eq!(prep(content), content);
+        }
+    }
+
+    #[test]
+    fn presets() {
+        let overrides = Overrides {
+            presets: HashMap::from([(
+                "legal".to_owned(),
+                AdmonitionPreset {
+                    directive: Some("warning".to_owned()),
+                    title: Some("Legal".to_owned()),
+                    collapsible: None,
+                    body: Some("Not legal advice.".to_owned()),
+                },
+            )]),
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        let expected = r##"
+<div id="admonition-legal" class="admonition admonish-warning" data-admonish-directive="warning" role="note" aria-labelledby="admonition-legal-title">
+<div class="admonition-title">
+<div id="admonition-legal-title">
+
+Legal
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-legal"></a>
+</div>
+<div>
+
+Not legal advice.
+
+</div>
+</div>
+"##;
+        assert_eq!(render("```admonish use=\"legal\"\n```\n"), expected);
+
+        // Options and body given by the block take precedence
+        let rendered = render("```admonish tip use=\"legal\", title=\"Terms\"\nRead these.\n```\n");
+        assert!(rendered.contains(r#"class="admonition admonish-tip""#));
+        assert!(rendered.contains("\nTerms\n"));
+        assert!(rendered.contains("\nRead these.\n"));
+        assert!(!rendered.contains("Not legal advice."));
+
+        assert!(render("```admonish use=\"missing\"\n```\n").contains("Unknown preset 'missing'"));
+    }
+
+    #[test]
+    fn ids_from_non_ascii_titles() {
+        let ids = |content: &str| {
+            prep(content)
+                .lines()
+                .filter_map(|line| line.strip_prefix(r#"<div id=""#))
+                .filter_map(|line| line.split_once('"'))
+                .map(|(id, _)| id.to_owned())
+                .filter(|id| !id.ends_with("-title"))
+                .collect::<Vec<_>>()
+        };
+
+        // CJK characters are kept as is
+        assert_eq!(
+            ids("```admonish note title=\"注意事項\"\nText\n```\n"),
+            vec!["admonition-注意事項"]
+        );
+
+        // Emoji only titles are hashed, so different titles get different ids
+        let emoji = ids(
+            "```admonish note title=\"🦀\"\nText\n```\n\n```admonish note title=\"🐍\"\nText\n```\n\n```admonish note title=\"🦀\"\nText\n```\n",
+        );
+        assert_eq!(
+            emoji,
+            vec![
+                "admonition-081301e6",
+                "admonition-108d4437",
+                "admonition-081301e6-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn directive_role_and_aria_label() {
+        let overrides = Overrides {
+            builtin: HashMap::from([(
+                BuiltinDirective::Danger,
+                BuiltinDirectiveConfig {
+                    collapsible: None,
+                    body_prefix: None,
+                    body_suffix: None,
+                    role: Some("alert".to_owned()),
+                    aria_label: None,
+                },
+            )]),
+            custom: [CustomDirective {
+                directive: "frog".to_owned(),
+                aliases: Vec::new(),
+                renamed_from: Vec::new(),
+                title: None,
+                collapsible: None,
+                alias_collapsible: Default::default(),
+                body_prefix: None,
+                body_suffix: None,
+                role: Some("status".to_owned()),
+                aria_label: Some("{directive} says {title}".to_owned()),
+                icon_svg: None,
+            }]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        let content = r#"
+```admonish danger
+Hot.
+```
+"#;
+        let expected = r##"
+
+<div id="admonition-danger" class="admonition admonish-danger" data-admonish-directive="danger" role="alert" aria-labelledby="admonition-danger-title">
+<div class="admonition-title">
+<div id="admonition-danger-title">
+
+Danger
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-danger"></a>
+</div>
+<div>
+
+Hot.
+
+</div>
+</div>
+"##;
+        assert_eq!(expected, render(content));
+
+        // Instance options win over the directive config
+        let preprocess_result = render("```admonish danger role=\"status\"\nHot.\n```\n");
+        assert!(preprocess_result
+            .contains(r#"role="status" aria-labelledby="admonition-danger-title""#));
+
+        // A label replaces the reference to the title
+        let preprocess_result = render("```admonish frog title=\"Ribbit\"\nHop.\n```\n");
+        assert!(preprocess_result.contains(r#"role="status" aria-label="frog says Ribbit">"#));
+        let preprocess_result = render(
+            "```admonish frog title=\"Ribbit\" role=\"note\" aria_label=\"Croak\"\nHop.\n```\n",
+        );
+        assert!(preprocess_result.contains(r#"role="note" aria-label="Croak">"#));
+    }
+
+    #[test]
+    fn text_render_mode() {
+        let content = r#"
+```admonish warning title="Careful", collapsible=true
+Titled *markdown*.
+```
+
+```admonish note title=""
+Untitled.
+```
+
+- List item
+
+  ```admonish tip
+  Indented.
+  ```
+"#;
+
+        let expected = r#"
+
+Careful
+
+Titled *markdown*.
+
+
+
+Untitled.
+
+
+- List item
+
+  
+  Tip
+  
+  Indented.
+
+"#;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides::default(),
+            RenderTextMode::Text,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn figure_wrap() {
+        let content = r#"
+```admonish example
+Titled.
+```
+
+```admonish example title=""
+Untitled.
+```
+
+```admonish example
+Titled again.
+```
+"#;
+
+        let expected = r##"
+
+<figure id="admonition-example-figure" class="admonition-figure">
+<div id="admonition-example" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-example-title">
+<div class="admonition-title">
+<div id="admonition-example-title">
+
+Example 1
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-example"></a>
+</div>
+<div>
+
+Titled.
+
+</div>
+</div>
+<figcaption>
+
+Example 1
+
+</figcaption>
+</figure>
+
+
+<div id="admonition-default" class="admonition admonish-example" data-admonish-directive="example" role="note">
+<div>
+
+Untitled.
+
+</div>
+</div>
+
+
+<figure id="admonition-example-1-figure" class="admonition-figure">
+<div id="admonition-example-1" class="admonition admonish-example" data-admonish-directive="example" role="note" aria-labelledby="admonition-example-1-title">
+<div class="admonition-title">
+<div id="admonition-example-1-title">
+
+Example 2
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-example-1"></a>
+</div>
+<div>
+
+Titled again.
+
+</div>
+</div>
+<figcaption>
+
+Example 2
+
+</figcaption>
+</figure>
+"##;
+
+        let preprocess_result = preprocess(
+            content,
+            None,
+            None,
+            OnFailure::Continue,
+            &Overrides {
+                book: AdmonitionDefaults {
+                    figure_wrap: true,
+                    numbered: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert_eq!(expected, preprocess_result);
+    }
+
+    #[test]
+    fn body_trim() {
+        let render = |content: &str, trim: BodyTrim| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &Overrides {
+                    book: AdmonitionDefaults {
+                        trim,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        let content = "```admonish\n\n  \nText\n\n```\n";
+        for (trim, expected) in [
+            (BodyTrim::Both, "<div>\n\nText\n\n</div>"),
+            (BodyTrim::End, "<div>\n\n\n  \nText\n\n</div>"),
+            (BodyTrim::Neither, "<div>\n\n\n  \nText\n\n\n\n</div>"),
+        ] {
+            let actual = render(content, trim);
+            assert!(actual.contains(expected), "{trim:?}:\n{actual}");
+        }
+
+        // Front matter is followed by the trimmed body
+        let actual = render("```admonish\ntitle: Hi\n---\n\nText\n```\n", BodyTrim::Both);
+        assert!(actual.contains("<div>\n\nText\n\n</div>"), "{actual}");
+
+        // The first line keeps its indent, so indented code stays code
+        let actual = render("```admonish\n\n    code\n```\n", BodyTrim::Both);
+        assert!(actual.contains("<div>\n\n    code\n\n</div>"), "{actual}");
+    }
+
+    #[test]
+    fn collect_admonitions_positions() {
+        let content = r#"# Überschrift — 注意
+
+```admonish warning title="Wärme"
+Hot.
+```
+
+```rust
+fn main() {}
+```
+
+- Item
+
+  ```admonish
+  Nested.
+  ```
+
+```admonish collapsible=3
+Invalid.
+```
+"#;
+        let positions = collect_admonitions(content, &Overrides::default()).unwrap();
+        assert_eq!(
+            positions
+                .iter()
+                .map(|position| (
+                    position.directive.as_str(),
+                    position.title.as_str(),
+                    position.line
+                ))
+                .collect::<Vec<_>>(),
+            vec![("warning", "Wärme", 3), ("note", "Note", 13)]
+        );
+        // Ranges are on char boundaries, and cover the whole block
+        assert_eq!(
+            &content[positions[0].byte_range.clone()],
+            "```admonish warning title=\"Wärme\"\nHot.\n```"
+        );
+        assert!(content[positions[1].byte_range.clone()].starts_with("```admonish\n"));
+
+        // Positions include chapter front matter
+        let content = "+++\n[admonish.custom.frog]\nicon = \"builtin:tip\"\ncolor = \"green\"\ntitle = \"Ribbit\"\n+++\n\n```admonish frog\nHop.\n```\n";
+        let positions = collect_admonitions(content, &Overrides::default()).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].directive, "frog");
+        assert_eq!(positions[0].title, "Ribbit");
+        assert_eq!(positions[0].line, 8);
+        assert!(content[positions[0].byte_range.clone()].starts_with("```admonish frog"));
+    }
+
+    #[test]
+    fn title_link() {
+        let content = r#"
+```admonish info title="RFC 9110", title_link="https://www.rfc-editor.org/rfc/rfc9110?a=1&b=2"
+Content.
+```
+"#;
+
+        let expected = r##"
+
+<div id="admonition-rfc-9110" class="admonition admonish-info" data-admonish-directive="info" role="note" aria-labelledby="admonition-rfc-9110-title">
+<div class="admonition-title">
+<div id="admonition-rfc-9110-title">
+
+<a class="admonition-title-link" href="https://www.rfc-editor.org/rfc/rfc9110?a=1&amp;b=2">RFC 9110</a>
+
+</div>
+<a class="admonition-anchor-link" href="#admonition-rfc-9110"></a>
+</div>
+<div>
+
+Content.
+
+</div>
+</div>
+"##;
+        assert_eq!(expected, prep(content));
+
+        // Only web and mail URLs, or relative paths, are allowed
+        assert!(
+            prep("```admonish title_link=\"../other.md#section\"\nText\n```\n")
+                .contains(r#"href="../other.md#section">Note</a>"#)
+        );
+        assert!(
+            prep("```admonish title_link=\"javascript:alert(1)\"\nText\n```\n")
+                .contains("'javascript:alert(1)' is not a valid title link")
+        );
+    }
+
+    #[test]
+    fn a11y_state_labels() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                a11y_state_labels: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        let expected = r##"
+<details id="admonition-note" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-note-title">
+<summary class="admonition-title" aria-expanded="false">
+<div id="admonition-note-title">
+
+Note
+
+</div>
+<span class="admonition-state-label">collapsed</span>
+<a class="admonition-anchor-link" href="#admonition-note"></a>
+</summary>
+<div>
+
+Hidden text.
+
+</div>
+</details>
+"##;
+        let rendered = render("```admonish note collapsible=true\nHidden text.\n```\n");
+        assert_eq!(
+            rendered,
+            format!("{expected}\n\n{STATE_LABELS_SCRIPT_HTML}")
+        );
+        assert!(!STATE_LABELS_SCRIPT_HTML.contains("\n\n"));
+
+        // Open blocks start expanded
+        let rendered = render("```admonish note collapsible=true, open=true\nText\n```\n");
+        assert!(rendered.contains(r#"<summary class="admonition-title" aria-expanded="true">"#));
+        assert!(rendered.contains(r#"<span class="admonition-state-label">expanded</span>"#));
+
+        // Only collapsible blocks are labelled, and need the script
+        let rendered = render("```admonish note\nText\n```\n");
+        assert!(!rendered.contains("aria-expanded"));
+        assert!(!rendered.contains("admonition-state-label"));
+        assert!(!rendered.contains("<script>"));
+
+        // Disabled by default
+        let rendered = prep("```admonish note collapsible=true\nText\n```\n");
+        assert!(!rendered.contains("admonition-state-label"));
+        assert!(!rendered.contains("<script>"));
+    }
+
+    #[test]
+    fn force_expand_non_html() {
+        let content = "```admonish note collapsible=true\nText\n```\n";
+        // Renderers other than html only see blocks with `render_mode = "html"`
+        let render = |overrides: &Overrides, renderer| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                overrides,
+                RenderTextMode::Html,
+                renderer,
+            )
+            .unwrap()
+        };
+
+        let overrides = Overrides::default();
+        assert!(render(&overrides, Some("html")).contains(r#"<details id="admonition-note""#));
+        assert!(render(&overrides, None).contains(r#"<details id="admonition-note""#));
+        let rendered = render(&overrides, Some("epub"));
+        assert!(rendered.contains(r#"<div id="admonition-note""#));
+        assert!(!rendered.contains("<details"));
+
+        // Can be disabled, for html based renderers that support it
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                force_expand_non_html: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(render(&overrides, Some("epub")).contains(r#"<details id="admonition-note""#));
+    }
+
+    #[test]
+    fn max_nesting() {
+        // Each level needs a longer fence than the one inside it
+        let nested = |depth: usize| {
+            let mut content = "Text\n".to_owned();
+            for level in 0..depth {
+                let fence = "`".repeat(3 + level);
+                content = format!("{fence}admonish\n{content}{fence}\n");
+            }
+            content
+        };
+        let render = |max_nesting, on_failure, content: &str| {
+            let overrides = Overrides {
+                book: AdmonitionDefaults {
+                    max_nesting,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            preprocess(
+                content,
+                None,
+                None,
+                on_failure,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+        };
+
+        let rendered = render(Some(3), OnFailure::Continue, &nested(3)).unwrap();
+        assert!(!rendered.contains("Error rendering admonishment"));
+        let rendered = render(Some(3), OnFailure::Continue, &nested(4)).unwrap();
+        assert!(rendered.contains("Error rendering admonishment"));
+        assert!(rendered.contains("Blocks are nested more than 3 deep."));
+        assert!(render(Some(3), OnFailure::Bail, &nested(4))
+            .unwrap_err()
+            .to_string()
+            .starts_with("Error processing admonition, bailing:"));
+
+        // Generous by default
+        assert!(render(None, OnFailure::Bail, &nested(8)).is_ok());
+        assert!(render(None, OnFailure::Bail, &nested(9)).is_err());
+
+        // Other code blocks don't count
+        let content = "`````admonish\n````markdown\n```rust\n```\n````\n`````\n";
+        assert!(render(Some(1), OnFailure::Bail, content).is_ok());
+    }
+
+    #[test]
+    fn disabled_directive_warned_once_per_block() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                disabled_directives: vec!["quote".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rendered = preprocess(
+            "```admonish quote\nOne\n```\n\n```admonish note\nTwo\n```\n",
+            None,
+            None,
+            OnFailure::Continue,
+            &overrides,
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(!rendered.contains("admonish-quote"));
+        assert_eq!(overrides.warnings.get(), 1);
+    }
+
+    #[test]
+    fn auto_collapse_lines() {
+        let render = |auto_collapse_lines, content: &str| {
+            let overrides = Overrides {
+                book: AdmonitionDefaults {
+                    auto_collapse_lines,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Bail,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+        let block = |options: &str, lines: usize| {
+            let body: String = (1..=lines).map(|line| format!("Line {line}\n")).collect();
+            format!("```admonish note{options}\n{body}\n\n```\n")
+        };
+
+        // Just under and just over the threshold, trailing blank lines don't count
+        assert!(!render(Some(3), &block("", 3)).contains("<details"));
+        let rendered = render(Some(3), &block("", 4));
+        assert!(rendered.contains(r#"<details id="admonition-note""#));
+        assert!(!rendered.contains(" open"));
+
+        // Explicit options always win
+        assert!(!render(Some(3), &block(" collapsible=false", 4)).contains("<details"));
+        assert!(render(Some(3), &block(" collapsible=true", 1)).contains("<details"));
+
+        // Off by default
+        assert!(!render(None, &block("", 100)).contains("<details"));
+    }
+
+    #[test]
+    fn style_bar() {
+        let content = r#"
+```admonish
+Box.
+```
+```admonish style=bar
+Bar.
+```
+"#;
+        let rendered = prep(content);
+        assert!(rendered.contains(r#"<div id="admonition-note" class="admonition admonish-note" "#));
+        assert!(rendered.contains(
+            r#"<div id="admonition-note-1" class="admonition admonish-note admonition-bar" "#
+        ));
+
+        // Per-block values override the book default
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                style: BlockStyle::Bar,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rendered = preprocess(
+            content.replace("style=bar", "style=box").as_str(),
+            None,
+            None,
+            OnFailure::Continue,
+            &overrides,
+            RenderTextMode::Html,
+            None,
+        )
+        .unwrap();
+        assert!(rendered.contains(
+            r#"<div id="admonition-note" class="admonition admonish-note admonition-bar" "#
+        ));
+        assert!(
+            rendered.contains(r#"<div id="admonition-note-1" class="admonition admonish-note" "#)
+        );
+    }
+
+    #[test]
+    fn summary_marker() {
+        let render = |marker, content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &Overrides {
+                    book: AdmonitionDefaults {
+                        marker,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+        let collapsible = "```admonish collapsible=true\nText\n```\n";
+        for (marker, summary) in [
+            (
+                SummaryMarker::Start,
+                r#"<summary class="admonition-title admonition-marker-start">"#,
+            ),
+            (SummaryMarker::End, r#"<summary class="admonition-title">"#),
+            (
+                SummaryMarker::Hidden,
+                r#"<summary class="admonition-title admonition-marker-none">"#,
+            ),
+        ] {
+            assert!(render(marker, collapsible).contains(summary), "{marker:?}");
+            // Ignored for blocks that aren't collapsible
+            assert!(render(marker, "```admonish\nText\n```\n")
+                .contains(r#"<div class="admonition-title">"#));
+        }
+    }
+
+    #[test]
+    fn class_style_bem() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                class_style: ClassStyle::Bem,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        assert!(render("```admonish warning\nText\n```\n").contains(
+            r#"<div id="admonition-warning" class="admonition admonition--warning" data-admonish-directive="warning""#
+        ));
+        // Aliases resolve to their directive, as with the default scheme
+        assert!(render("```admonish caution density=compact\nText\n```\n")
+            .contains(r#"class="admonition admonition--warning admonition-compact""#));
+        assert!(prep("```admonish warning\nText\n```\n")
+            .contains(r#"class="admonition admonish-warning""#));
+    }
+
+    #[test]
+    fn found_blocks_counted() {
+        let overrides = Overrides::default();
+        let render = |content: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                Some("html"),
+            )
+            .unwrap()
+        };
+        render("Mentions admonish, but has no blocks.\n");
+        assert_eq!(overrides.found_blocks.get(), 0);
+        render("```admonish\nOne\n```\n\n```rust\nNot a block\n```\n");
+        assert_eq!(overrides.found_blocks.get(), 1);
+        // Including those in chapters with front matter, or for other renderers
+        render(
+            "+++\n[admonish.default]\ntitle = \"Chapter\"\n+++\n```admonish\nTwo\n```\n\n```admonish renderers=\"epub\"\nThree\n```\n",
+        );
+        assert_eq!(overrides.found_blocks.get(), 3);
+    }
+
+    #[test]
+    fn anchor_glyph() {
+        let content = "```admonish\nText\n```\n";
+        let render = |anchor_glyph: &str| {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &Overrides {
+                    book: AdmonitionDefaults {
+                        anchor_glyph: anchor_glyph.to_owned(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                RenderTextMode::Html,
+                None,
+            )
+            .unwrap()
+        };
+
+        assert!(render("#")
+            .contains(r##"<a class="admonition-anchor-link" href="#admonition-note">#</a>"##));
+        assert!(render("🔗")
+            .contains(r##"<a class="admonition-anchor-link" href="#admonition-note">🔗</a>"##));
+        assert!(render("<>").contains(r##"href="#admonition-note">&lt;&gt;</a>"##));
+        // Empty by default, drawn with CSS
+        assert!(prep(content)
+            .contains(r##"<a class="admonition-anchor-link" href="#admonition-note"></a>"##));
+    }
 }
@@ -0,0 +1,243 @@
+//! Conversion of mkdocs-material admonition syntax to `admonish` blocks.
+//!
+//! Blocks like `!!! note "Title"` are followed by their body as indented lines.
+//! These are rewritten to fenced `admonish` blocks before the usual parsing,
+//! so they support the same features.
+
+use once_cell::sync::Lazy;
+use pulldown_cmark::{CodeBlockKind::*, Event, Parser, Tag};
+use regex::Regex;
+use std::ops::Range;
+
+/// The first line of a block, e.g. `!!! note "Title"` or `???+ tip inline`.
+static RX_MKDOCS_MARKER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"^( *)(!!!|\?\?\?\+?) *([\w-]+)((?: +[\w-]+)*)(?: +"([^"]*)")? *\r?\n?$"#)
+        .expect("mkdocs marker regex")
+});
+
+/// Indentation of the body of a block, relative to the marker.
+const BODY_INDENT: &str = "    ";
+
+struct Marker<'a> {
+    indent: &'a str,
+    collapsible: bool,
+    open: bool,
+    directive: &'a str,
+    classes: &'a str,
+    title: Option<&'a str>,
+}
+
+impl<'a> Marker<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let captures = RX_MKDOCS_MARKER.captures(line)?;
+        let kind = captures.get(2)?.as_str();
+        Some(Self {
+            indent: captures.get(1)?.as_str(),
+            collapsible: kind != "!!!",
+            open: kind == "???+",
+            directive: captures.get(3)?.as_str(),
+            classes: captures.get(4)?.as_str().trim(),
+            title: captures.get(5).map(|title| title.as_str()),
+        })
+    }
+
+    /// The info string of the equivalent `admonish` block.
+    fn info_string(&self) -> String {
+        let mut info_string = format!("admonish {}", self.directive);
+        if let Some(title) = self.title {
+            info_string.push_str(&format!(" title={}", toml::Value::from(title)));
+        }
+        if !self.classes.is_empty() {
+            info_string.push_str(&format!(r#" class="{}""#, self.classes));
+        }
+        if self.collapsible {
+            info_string.push_str(" collapsible=true");
+        }
+        if self.open {
+            info_string.push_str(" open=true");
+        }
+        info_string
+    }
+}
+
+/// Whether `content` may contain blocks, as a cheap check before converting.
+pub(crate) fn may_contain_mkdocs_blocks(content: &str) -> bool {
+    content.contains("!!!") || content.contains("???")
+}
+
+/// Byte ranges of fenced code blocks, which must be left alone.
+fn fenced_code_blocks(content: &str) -> Vec<Range<usize>> {
+    Parser::new(content)
+        .into_offset_iter()
+        .filter_map(|(event, span)| match event {
+            Event::Start(Tag::CodeBlock(Fenced(_))) => Some(span),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Strip the body indent from a line, returning `None` if it is not indented enough.
+fn dedent<'a>(line: &'a str, indent: &str) -> Option<&'a str> {
+    if line.trim().is_empty() {
+        return Some(line.trim_start_matches([' ', '\t']));
+    }
+    let line = line.strip_prefix(indent)?;
+    line.strip_prefix(BODY_INDENT)
+        .or_else(|| line.strip_prefix('\t'))
+}
+
+/// A backtick fence longer than any used in `body`.
+fn fence_for(body: &str) -> String {
+    let longest = body
+        .lines()
+        .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count())
+        .max()
+        .unwrap_or_default();
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Rewrite mkdocs-material style blocks in `content` to fenced `admonish` blocks.
+pub(crate) fn convert_mkdocs_syntax(content: &str) -> String {
+    let fenced = fenced_code_blocks(content);
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+
+    let mut converted = String::with_capacity(content.len());
+    let mut index = 0;
+    while index < lines.len() {
+        let (offset, line) = lines[index];
+        index += 1;
+
+        let marker = match Marker::parse(line) {
+            Some(marker) if !fenced.iter().any(|range| range.contains(&offset)) => marker,
+            _ => {
+                converted.push_str(line);
+                continue;
+            }
+        };
+
+        // The body is all following lines that are indented (or blank), not
+        // including any trailing blank lines
+        let mut body_lines = Vec::new();
+        let mut body_end = index;
+        while let Some(body_line) = lines
+            .get(body_end)
+            .and_then(|(_, body_line)| dedent(body_line, marker.indent))
+        {
+            body_lines.push(body_line);
+            body_end += 1;
+        }
+        while body_lines.last().is_some_and(|line| line.trim().is_empty()) {
+            body_lines.pop();
+            body_end -= 1;
+        }
+        index = body_end;
+
+        // Blocks may be nested
+        let body = convert_mkdocs_syntax(&body_lines.concat());
+        let fence = fence_for(&body);
+        let indent = marker.indent;
+        converted.push_str(&format!("{indent}{fence}{}\n", marker.info_string()));
+        for body_line in body.split_inclusive('\n') {
+            if !body_line.trim().is_empty() {
+                converted.push_str(indent);
+            }
+            converted.push_str(body_line);
+        }
+        if !converted.ends_with('\n') {
+            converted.push('\n');
+        }
+        converted.push_str(&format!("{indent}{fence}"));
+        // Keep the line ending of the last consumed line
+        if lines[index - 1].1.ends_with('\n') {
+            converted.push('\n');
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn converts_blocks() {
+        for (input, expected) in [
+            // Without a title
+            (
+                "!!! note\n    Body text.\n",
+                "```admonish note\nBody text.\n```\n",
+            ),
+            // With a title, and a paragraph after
+            (
+                "!!! tip \"Phasellus posuere\"\n    First.\n\n    Second.\n\nAfter\n",
+                "```admonish tip title=\"Phasellus posuere\"\nFirst.\n\nSecond.\n```\n\nAfter\n",
+            ),
+            // Removing the title
+            (
+                "!!! note \"\"\n    Body",
+                "```admonish note title=\"\"\nBody\n```",
+            ),
+            // Collapsible, and expanded
+            (
+                "??? question\n    Body\n",
+                "```admonish question collapsible=true\nBody\n```\n",
+            ),
+            (
+                "???+ question\n    Body\n",
+                "```admonish question collapsible=true open=true\nBody\n```\n",
+            ),
+            // Additional classes
+            (
+                "!!! info inline end \"Lorem ipsum\"\n    Body\n",
+                "```admonish info title=\"Lorem ipsum\" class=\"inline end\"\nBody\n```\n",
+            ),
+        ] {
+            assert_eq!(convert_mkdocs_syntax(input), expected);
+        }
+    }
+
+    #[test]
+    fn converts_nested_blocks_with_fences() {
+        let input = r#"- Item
+
+    !!! note
+        ```rust
+        let x = 1;
+        ```
+
+        !!! warning
+            Nested.
+"#;
+        let expected = r#"- Item
+
+    ````admonish note
+    ```rust
+    let x = 1;
+    ```
+
+    ```admonish warning
+    Nested.
+    ```
+    ````
+"#;
+        assert_eq!(convert_mkdocs_syntax(input), expected);
+    }
+
+    #[test]
+    fn ignores_code_blocks_and_text() {
+        for input in [
+            "```\n!!! note\n    Not a block.\n```\n",
+            "~~~markdown\n??? note\n    Not a block.\n~~~\n",
+            "Text with !!! note in it.\n",
+            "!!!note \"Title\" extra\n",
+        ] {
+            assert_eq!(convert_mkdocs_syntax(input), input);
+        }
+    }
+}
@@ -1,13 +1,38 @@
 use anyhow::{anyhow, Result};
 use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
 
 use crate::{
     book_config::OnFailure,
+    config::split_front_matter,
+    markdown::admonitions_nested_deeper_than,
     render::Admonition,
-    resolve::AdmonitionMeta,
-    types::{BuiltinDirective, CssId, Overrides},
+    resolve::{error_directive, AdmonitionMeta},
+    types::{BodyFormat, BodyTrim, CssId, Overrides},
 };
 
+/// How deeply blocks may be nested, if `default.max_nesting` is not set.
+const DEFAULT_MAX_NESTING: usize = 8;
+
+/// Where an admonition was found in the book, for use in diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Location<'a> {
+    /// Path of the chapter source file, if known.
+    pub path: Option<&'a Path>,
+    /// One-indexed line number of the opening code fence.
+    pub line: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path {
+            Some(path) => write!(f, "{}:{}", path.display(), self.line),
+            None => write!(f, "line {}", self.line),
+        }
+    }
+}
+
 /// Given the content in the span of the code block, and the info string,
 /// return `Some(Admonition)` if the code block is an admonition.
 ///
@@ -23,12 +48,55 @@ pub(crate) fn parse_admonition<'a>(
     content: &'a str,
     on_failure: OnFailure,
     indent: usize,
+    location: Location<'_>,
+    src_dir: Option<&Path>,
 ) -> Option<Result<Admonition<'a>>> {
     // We need to know fence details anyway for error messages
-    let extracted = extract_admonish_body(content);
+    let extracted = extract_admonish_body(content, overrides.book.trim);
 
-    let info = AdmonitionMeta::from_info_string(info_string, overrides)?;
-    let info = match info {
+    let (front_matter, body) = match split_front_matter(extracted.body) {
+        Some((front_matter, body)) => (Some(front_matter), overrides.book.trim.apply(body)),
+        None => (None, extracted.body),
+    };
+
+    let info = AdmonitionMeta::from_info_string(info_string, front_matter, overrides)?;
+    let info = info.and_then(|mut info| {
+        check_fence_characters(content, &extracted)?;
+        if let Some(warning) = info.deprecation_warning(location) {
+            overrides.warn(&warning);
+        }
+        if let Some(warning) = info.disabled_warning(location) {
+            overrides.warn(&warning);
+        }
+        let body = match &info.src {
+            Some(src) => {
+                if !body.trim().is_empty() {
+                    return Err(format!(
+                        "Block with 'src=\"{src}\"' must have an empty body."
+                    ));
+                }
+                Cow::Owned(read_src(src_dir, src)?)
+            }
+            None => match &info.default_body {
+                Some(default_body) if body.trim().is_empty() => Cow::Owned(default_body.clone()),
+                _ => Cow::Borrowed(body),
+            },
+        };
+        let max_nesting = overrides.book.max_nesting.unwrap_or(DEFAULT_MAX_NESTING);
+        // The block itself is the first level of nesting
+        if max_nesting == 0 || admonitions_nested_deeper_than(&body, max_nesting - 1) {
+            return Err(format!(
+                "Blocks are nested more than {max_nesting} deep. To allow deeper nesting, increase 'default.max_nesting'."
+            ));
+        }
+        let body = normalize_line_endings(body);
+        info.auto_collapse(&body);
+        if let Some(warning) = info.drop_ignored_summary(location) {
+            overrides.warn(&warning);
+        }
+        Ok((info, body))
+    });
+    let (info, body) = match info {
         Ok(info) => info,
         Err(message) => {
             // Construct a fence capable of enclosing whatever we wrote for the
@@ -39,15 +107,53 @@ pub(crate) fn parse_admonition<'a>(
                 .collect();
             return Some(match on_failure {
                 OnFailure::Continue => {
-                    log::warn!(
-                        r#"Error processing admonition. To fail the build instead of continuing, set 'on_failure = "bail"'"#
-                    );
+                    overrides
+                        .failed_blocks
+                        .set(overrides.failed_blocks.get() + 1);
+                    overrides.warn(&format!(
+                        r#"Error processing admonition at {location}. To fail the build instead of continuing, set 'on_failure = "bail"'"#
+                    ));
+                    let (directive, raw_directive) = error_directive(overrides);
                     Ok(Admonition {
-                        directive: BuiltinDirective::Bug.to_string(),
+                        directive,
+                        written_directive: raw_directive.clone(),
+                        raw_directive,
                         title: "Error rendering admonishment".to_owned(),
+                        number_label: None,
                         css_id: CssId::Prefix("admonition-".to_owned()),
+                        untitled_id_source: overrides.book.untitled_id_source,
                         additional_classnames: Vec::new(),
-                        collapsible: false,
+                        attributes: Vec::new(),
+                        collapsible: overrides.book.error_collapsible,
+                        open: false,
+                        summary: None,
+                        element: overrides.book.element,
+                        collapsible_impl: overrides.book.collapsible_impl,
+                        density: overrides.book.density,
+                        style: overrides.book.style,
+                        title_layout: overrides.book.title_layout,
+                        marker: overrides.book.marker,
+                        class_style: overrides.book.class_style,
+                        title_anchor: overrides.book.title_anchor.unwrap_or(true),
+                        anchor_glyph: overrides.book.anchor_glyph.clone(),
+                        badge: overrides.book.show_badge,
+                        icon: true,
+                        icon_svg: None,
+                        avoid_page_break: overrides.book.avoid_page_break,
+                        figure_wrap: false,
+                        state_labels: overrides.book.a11y_state_labels,
+                        title_link: None,
+                        caption: None,
+                        renderers: None,
+                        width: None,
+                        align: None,
+                        lang: None,
+                        body_format: BodyFormat::Markdown,
+                        inline: false,
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: "note".to_owned(),
+                        aria_label: None,
                         content: Cow::Owned(format!(
                             r#"Failed with:
 
@@ -71,8 +177,7 @@ Original markdown input:
     };
 
     Some(Ok(Admonition::new(
-        info,
-        extracted.body,
+        info, body,
         // Note that this is a bit hacky - the fence information comes from the start
         // of the block, and includes the whole line.
         //
@@ -90,6 +195,45 @@ Original markdown input:
     )))
 }
 
+/// Resolve the options of an admonition, without reading or rendering its body.
+///
+/// If the code block is not an admonition, return `None`.
+pub(crate) fn admonition_meta(
+    info_string: &str,
+    overrides: &Overrides,
+    content: &str,
+) -> Option<Result<AdmonitionMeta, String>> {
+    let extracted = extract_admonish_body(content, overrides.book.trim);
+    let front_matter = split_front_matter(extracted.body).map(|(front_matter, _)| front_matter);
+    AdmonitionMeta::from_info_string(info_string, front_matter, overrides)
+}
+
+/// Read the body of an admonition from `src`, relative to the book source directory.
+///
+/// Files outside of the source directory are rejected.
+fn read_src(src_dir: Option<&Path>, src: &str) -> Result<String, String> {
+    let src_dir = src_dir
+        .ok_or_else(|| format!("Cannot load '{src}', the book source directory is not known."))?;
+    let canonical_src_dir = src_dir.canonicalize().map_err(|error| {
+        format!(
+            "Cannot resolve book source directory '{}': {error}",
+            src_dir.display()
+        )
+    })?;
+    let path = canonical_src_dir
+        .join(src)
+        .canonicalize()
+        .map_err(|error| format!("Cannot read file '{src}': {error}"))?;
+    if !path.starts_with(&canonical_src_dir) {
+        return Err(format!(
+            "Cannot read file '{src}': path is outside the book source directory."
+        ));
+    }
+    let body = std::fs::read_to_string(&path)
+        .map_err(|error| format!("Cannot read file '{src}': {error}"))?;
+    Ok(body.trim_end().to_owned())
+}
+
 /// We can't trust the info string length to find the start of the body
 /// it may change length if it contains HTML or character escapes.
 ///
@@ -116,8 +260,28 @@ fn extract_admonish_body_start_index(content: &str) -> usize {
     }
 }
 
+/// The fence that opens the block.
+fn opening_fence(content: &str) -> Fence {
+    let content = content.trim_start();
+    let character = content.chars().next().unwrap_or('`');
+    let length = content.chars().take_while(|c| *c == character).count();
+    Fence::new(character, length)
+}
+
+/// Whether `character` can be used for a code fence.
+fn is_fence_character(character: char) -> bool {
+    matches!(character, '`' | '~')
+}
+
 fn extract_admonish_body_end_index(content: &str) -> (usize, Fence) {
+    // The closing fence may be followed by whitespace, such as the `\r` of a
+    // CRLF line ending
+    let content = content.trim_end_matches([' ', '\t', '\r']);
     let fence_character = content.chars().next_back().unwrap_or('`');
+    // Unclosed blocks run to the end of the document
+    if !is_fence_character(fence_character) {
+        return (content.len(), opening_fence(content));
+    }
     let number_fence_characters = content
         .chars()
         .rev()
@@ -129,6 +293,48 @@ fn extract_admonish_body_end_index(content: &str) -> (usize, Fence) {
     (index, fence)
 }
 
+/// Return an error if the block was meant to be closed by a fence of the other
+/// character, such as opening with backticks and closing with tildes.
+///
+/// Fences must use the same character, so such a block runs to the end of the
+/// document (or to a later matching fence), which is unlikely to be intended.
+fn check_fence_characters(content: &str, extracted: &Extracted) -> Result<(), String> {
+    let opening = opening_fence(content);
+    let closing = if extracted.fence.character != opening.character {
+        // Unclosed at the very end of the document
+        Some(extracted.fence.character)
+    } else if !content.trim_end().ends_with(opening.character) {
+        // Unclosed, but with a line that looks like it was meant to close the block
+        let other = if opening.character == '`' { '~' } else { '`' };
+        extracted
+            .body
+            .lines()
+            .map(str::trim)
+            .any(|line| line.len() >= 3 && line.chars().all(|c| c == other))
+            .then_some(other)
+    } else {
+        None
+    };
+    match closing {
+        Some(closing) => Err(format!(
+            "Block opened with '{opening}' looks like it was closed with '{closing}{closing}{closing}'. \
+Opening and closing fences must use the same character, so this block runs to the end of the chapter.",
+            opening = String::from(opening.character).repeat(opening.length),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Convert CRLF line endings to LF, so blocks from Windows-authored files
+/// render the same as any other.
+fn normalize_line_endings(body: Cow<'_, str>) -> Cow<'_, str> {
+    if body.contains("\r\n") {
+        Cow::Owned(body.replace("\r\n", "\n"))
+    } else {
+        body
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct Fence {
     character: char,
@@ -153,14 +359,14 @@ struct Extracted<'a> {
 /// but it's not really clear a good way of doing that.
 ///
 /// ref: https://spec.commonmark.org/0.30/#fenced-code-blocks
-fn extract_admonish_body(content: &str) -> Extracted<'_> {
+fn extract_admonish_body(content: &str, trim: BodyTrim) -> Extracted<'_> {
     let start_index = extract_admonish_body_start_index(content);
     let (end_index, fence) = extract_admonish_body_end_index(content);
 
     let admonish_content = &content[start_index..end_index];
-    // The newline after a code block is technically optional, so we have to
-    // trim it off dynamically.
-    let body = admonish_content.trim_end();
+    // The newline after a code block is technically optional, so by default
+    // we trim it off dynamically.
+    let body = trim.apply(admonish_content);
     Extracted { body, fence }
 }
 
@@ -169,6 +375,26 @@ mod test {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn test_location_display() {
+        assert_eq!(
+            Location {
+                path: Some(Path::new("chapter_1.md")),
+                line: 12
+            }
+            .to_string(),
+            "chapter_1.md:12"
+        );
+        assert_eq!(
+            Location {
+                path: None,
+                line: 3
+            }
+            .to_string(),
+            "line 3"
+        );
+    }
+
     #[test]
     fn test_extract_start() {
         for (text, expected) in [
@@ -194,6 +420,11 @@ mod test {
             // whitespace before fence end
             ("\n   ```", (4, Fence::new('`', 3))),
             ("content\n```", (8, Fence::new('`', 3))),
+            // whitespace after fence end
+            ("content\n```  ", (8, Fence::new('`', 3))),
+            ("content\r\n```\r", (9, Fence::new('`', 3))),
+            // unclosed, so the opening fence is used
+            ("````admonish\ncontent\n", (21, Fence::new('`', 4))),
         ] {
             let actual = extract_admonish_body_end_index(text);
             assert_eq!(actual, expected);
@@ -232,9 +463,74 @@ mod test {
                 // longer (end) fence returned
                 content_fence("content", '~', 5),
             ),
+            // CRLF line endings
+            (
+                "```admonish\r\ncontent\r\n```",
+                content_fence("content", '`', 3),
+            ),
+            (
+                "```admonish\r\n\r\n  content\r\nmore\r\n\r\n  ```\r",
+                content_fence("\r\n  content\r\nmore", '`', 3),
+            ),
+            ("```\r\n```", content_fence("", '`', 3)),
         ] {
-            let actual = extract_admonish_body(text);
+            let actual = extract_admonish_body(text, BodyTrim::End);
             assert_eq!(actual, expected);
         }
     }
+
+    #[test]
+    fn test_check_fence_characters() {
+        let check = |content: &str| {
+            check_fence_characters(content, &extract_admonish_body(content, BodyTrim::End))
+        };
+        for content in [
+            "```admonish\ncontent\n```",
+            "~~~admonish\ncontent\n~~~~",
+            // Fences of the other character inside the block
+            "```admonish\n~~~rust\ncode\n~~~\n```",
+            // Unclosed, but without anything that looks like a closing fence
+            "```admonish\ncontent\n",
+        ] {
+            assert_eq!(check(content), Ok(()), "{content}");
+        }
+
+        let message = "Block opened with '```' looks like it was closed with '~~~'. \
+Opening and closing fences must use the same character, so this block runs to the end of the chapter.";
+        assert_eq!(check("```admonish\ncontent\n~~~"), Err(message.to_owned()));
+        assert_eq!(
+            check("```admonish\ncontent\n~~~\n\nMore text.\n"),
+            Err(message.to_owned())
+        );
+        assert!(check("~~~~admonish\ncontent\n```\n")
+            .unwrap_err()
+            .starts_with("Block opened with '~~~~' looks like it was closed with '```'."));
+    }
+
+    #[test]
+    fn test_read_src() {
+        let root = std::env::temp_dir().join(format!(
+            "mdbook-admonish-test-read-src-{}",
+            std::process::id()
+        ));
+        let src_dir = root.join("src");
+        std::fs::create_dir_all(src_dir.join("examples")).unwrap();
+        std::fs::write(src_dir.join("examples").join("foo.toml"), "key = 1\n").unwrap();
+        std::fs::write(root.join("secret.txt"), "secret").unwrap();
+
+        assert_eq!(
+            read_src(Some(&src_dir), "examples/foo.toml"),
+            Ok("key = 1".to_owned())
+        );
+        assert!(read_src(Some(&src_dir), "examples/missing.toml")
+            .unwrap_err()
+            .starts_with("Cannot read file 'examples/missing.toml': "));
+        assert_eq!(
+            read_src(Some(&src_dir), "../secret.txt").unwrap_err(),
+            "Cannot read file '../secret.txt': path is outside the book source directory."
+        );
+        assert!(read_src(None, "examples/foo.toml").is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
 }
@@ -1,17 +1,42 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use mdbook::{
     book::{Book, BookItem},
     errors::Result as MdbookResult,
     preprocess::{Preprocessor, PreprocessorContext},
 };
+use path_slash::PathExt;
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::{
-    book_config::{admonish_config_from_context, Config, RenderMode},
-    markdown::preprocess,
-    types::{Overrides, RenderTextMode},
+    book_config::{
+        admonish_config_from_context, admonish_config_from_str, Config, OnFailure, RenderMode,
+    },
+    custom::{icon_problems, runtime_directives},
+    markdown::{collect_admonitions, may_contain_admonitions, preprocess},
+    render::{AdmonitionRenderHook, RenderHook},
+    types::{AdmonitionPosition, Overrides, RenderTextMode},
 };
 
-pub struct Admonish;
+/// The mdbook preprocessor.
+#[derive(Debug, Clone, Default)]
+pub struct Admonish {
+    render_hook: Option<RenderHook>,
+}
+
+impl Admonish {
+    /// Create the preprocessor, rendering blocks as configured in `book.toml`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Post-process the html generated for each block with `hook`.
+    pub fn with_render_hook(mut self, hook: impl AdmonitionRenderHook + 'static) -> Self {
+        self.render_hook = Some(RenderHook(Arc::new(hook)));
+        self
+    }
+}
 
 impl Preprocessor for Admonish {
     fn name(&self) -> &str {
@@ -19,62 +44,115 @@ impl Preprocessor for Admonish {
     }
 
     fn run(&self, ctx: &PreprocessorContext, mut book: Book) -> MdbookResult<Book> {
-        let config = admonish_config_from_context(ctx)?;
-        ensure_compatible_assets_version(&config)?;
+        let mut config = admonish_config_from_context(ctx)?;
+        if let Err(error) = ensure_compatible_assets_version(&config) {
+            if !config.default.skip_assets_check {
+                return Err(error);
+            }
+            config
+                .warnings
+                .push(format!("Skipping assets version check:\n{error}"));
+        }
 
-        let custom_directives = config
-            .directive
-            .custom
-            .into_iter()
-            .map(Into::into)
-            .collect();
-        let on_failure = config.on_failure;
-        let admonition_defaults = config.default;
+        let problems = icon_problems(&config.directive.custom, &ctx.root);
+        if !problems.is_empty() {
+            let message = format!("Invalid custom directive icons:\n{}", problems.join("\n"));
+            match config.on_failure {
+                OnFailure::Continue => config.warnings.push(message),
+                OnFailure::Bail => return Err(anyhow!(message)),
+            }
+        }
 
-        let overrides = Overrides {
-            book: admonition_defaults,
-            custom: custom_directives,
-            builtin: config.directive.builtin,
-        };
+        // Follow mdbook's sidebar folding, unless explicitly configured
+        if config.default.collapsible.is_none() {
+            config.default.collapsible = ctx
+                .config
+                .get("output.html.fold.enable")
+                .and_then(|value| value.as_bool());
+        }
 
-        // Load what rendering we should do from config, falling back to a default
-        let render_mode = config
-            .renderer
-            .get(&ctx.renderer)
-            .and_then(|renderer| renderer.render_mode)
-            .unwrap_or_else(|| {
-                // By default only render html for the html renderer
-                // For everything else, do nothing
-                if &ctx.renderer == "html" {
-                    RenderMode::Html
-                } else {
-                    RenderMode::Preserve
-                }
-            });
-        let render_text_mode = match render_mode {
+        let render_text_mode = match render_mode(&config, &ctx.renderer) {
             RenderMode::Preserve => return Ok(book),
             RenderMode::Html => RenderTextMode::Html,
-            RenderMode::Strip => RenderTextMode::Strip,
+            RenderMode::Blockquote => RenderTextMode::Blockquote,
+            RenderMode::Text => RenderTextMode::Text,
+            RenderMode::Strip => RenderTextMode::Strip {
+                annotate: config
+                    .renderer
+                    .get(&ctx.renderer)
+                    .is_some_and(|renderer| renderer.strip_annotate),
+            },
+        };
+
+        let on_failure = config.on_failure;
+        let chapter_filter = ChapterFilter::new(&config.default.include, &config.default.exclude)?;
+        let overrides = Overrides {
+            render_hook: self.render_hook.clone(),
+            ..overrides(config, &ctx.root)?
         };
 
+        let src_dir = ctx.root.join(&ctx.config.book.src);
+
         let mut res = None;
+        let mut scanned_chapters = 0;
         book.for_each_mut(|item: &mut BookItem| {
             if let Some(Err(_)) = res {
                 return;
             }
 
             if let BookItem::Chapter(ref mut chapter) = *item {
+                if !chapter_filter.matches(chapter.source_path.as_deref()) {
+                    return;
+                }
+                // Skip parsing chapters that can't contain any admonitions
+                if !may_contain_admonitions(&chapter.content, &overrides) {
+                    return;
+                }
+                scanned_chapters += 1;
                 res = Some(
-                    preprocess(&chapter.content, on_failure, &overrides, render_text_mode).map(
-                        |md| {
-                            chapter.content = md;
-                        },
-                    ),
+                    preprocess(
+                        &chapter.content,
+                        chapter.source_path.as_deref(),
+                        Some(&src_dir),
+                        on_failure,
+                        &overrides,
+                        render_text_mode,
+                        Some(&ctx.renderer),
+                    )
+                    .map(|md| {
+                        chapter.content = md;
+                    }),
                 );
             }
         });
 
-        res.unwrap_or(Ok(())).map(|_| book)
+        res.unwrap_or(Ok(()))?;
+        log::debug!(
+            "Found {} admonitions in {scanned_chapters} chapters",
+            overrides.found_blocks.get()
+        );
+
+        if let Some(summary) = failed_blocks_summary(&overrides) {
+            log::warn!("{summary}");
+        }
+
+        if overrides.book.warn_unused_directives {
+            let unused = unused_custom_directives(&overrides);
+            if !unused.is_empty() {
+                overrides.warn(&format!(
+                    "Custom directives not used by any block: {}",
+                    unused.join(", ")
+                ));
+            }
+        }
+
+        if overrides.book.deny_warnings {
+            if let Some(message) = denied_warnings_message(&overrides) {
+                return Err(anyhow!(message));
+            }
+        }
+
+        Ok(book)
     }
 
     fn supports_renderer(&self, _renderer: &str) -> bool {
@@ -84,11 +162,190 @@ impl Preprocessor for Admonish {
     }
 }
 
+/// Which chapters to process, from `default.include` and `default.exclude`.
+struct ChapterFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl ChapterFilter {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: if include.is_empty() {
+                None
+            } else {
+                Some(glob_set(include)?)
+            },
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    /// Whether to process the chapter with this source path.
+    ///
+    /// Chapters without a source path are only processed if there are no
+    /// `include` globs.
+    fn matches(&self, source_path: Option<&Path>) -> bool {
+        let Some(source_path) = source_path else {
+            return self.include.is_none();
+        };
+        // Globs always use `/` as a separator, whatever the platform
+        let source_path = source_path.to_slash_lossy();
+        let source_path = source_path.as_ref();
+        self.include
+            .as_ref()
+            .map_or(true, |include| include.is_match(source_path))
+            && !self.exclude.is_match(source_path)
+    }
+}
+
+/// Build a set of globs, where `*` does not match across directories.
+fn glob_set(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(
+            GlobBuilder::new(glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid chapter glob '{glob}'"))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
+/// Resolve the overrides used while rendering each block, logging any
+/// warnings from loading `config`.
+///
+/// Custom icon paths are relative to `book_dir`.
+fn overrides(config: Config, book_dir: &Path) -> Result<Overrides> {
+    let icon_dir = config.default.inline_icons.then_some(book_dir);
+    let overrides = Overrides {
+        custom: runtime_directives(config.directive.custom, icon_dir)?
+            .into_iter()
+            .collect(),
+        book: config.default,
+        builtin: config.directive.builtin,
+        presets: config.presets,
+        render_hook: None,
+        used_directives: Default::default(),
+        failed_blocks: Default::default(),
+        found_blocks: Default::default(),
+        warnings: Default::default(),
+    };
+    for warning in &config.warnings {
+        overrides.warn(warning);
+    }
+    Ok(overrides)
+}
+
+/// Summary of the blocks that failed to parse, if any.
+fn failed_blocks_summary(overrides: &Overrides) -> Option<String> {
+    match overrides.failed_blocks.get() {
+        0 => None,
+        1 => Some("1 admonition failed to parse".to_owned()),
+        count => Some(format!("{count} admonitions failed to parse")),
+    }
+}
+
+/// The error for warnings logged while processing, if any.
+fn denied_warnings_message(overrides: &Overrides) -> Option<String> {
+    let count = match overrides.warnings.get() {
+        0 => return None,
+        1 => "1 warning".to_owned(),
+        count => format!("{count} warnings"),
+    };
+    Some(format!(
+        "Failing the build after {count}, as 'default.deny_warnings' is set"
+    ))
+}
+
+/// Custom directives that no rendered block has used, in name order.
+///
+/// Blocks using an alias count as using its directive.
+fn unused_custom_directives(overrides: &Overrides) -> Vec<&str> {
+    let used = overrides.used_directives.borrow();
+    let mut unused: Vec<&str> = overrides
+        .custom
+        .directives()
+        .filter(|directive| !used.contains(*directive))
+        .collect();
+    unused.sort_unstable();
+    unused
+}
+
+/// Load what rendering we should do from config, falling back to a default
+fn render_mode(config: &Config, renderer: &str) -> RenderMode {
+    config
+        .renderer
+        .get(renderer)
+        .and_then(|renderer| renderer.render_mode)
+        .unwrap_or_else(|| {
+            // By default only render html for the html renderer
+            // For everything else, do nothing
+            if renderer == "html" {
+                RenderMode::Html
+            } else {
+                RenderMode::Preserve
+            }
+        })
+}
+
+/// Returns the name of the render mode that would be used for `renderer`.
+///
+/// Used for diagnostics only.
+#[doc(hidden)]
+pub fn render_mode_from_config(book_dir: &Path, config: &str, renderer: &str) -> Result<String> {
+    let config = admonish_config_from_str(config, book_dir)?;
+    Ok(render_mode(&config, renderer).to_string())
+}
+
+/// Render the admonitions in a single markdown document to html, outside of a book.
+///
+/// If no config is given, the default config is used.
+#[doc(hidden)]
+pub fn render_markdown_from_config(
+    book_dir: &Path,
+    config: Option<&str>,
+    src_dir: Option<&Path>,
+    content: &str,
+) -> Result<String> {
+    let config = match config {
+        Some(config) => admonish_config_from_str(config, book_dir)?,
+        None => Config::default(),
+    };
+    let on_failure = config.on_failure;
+    preprocess(
+        content,
+        None,
+        src_dir,
+        on_failure,
+        &overrides(config, book_dir)?,
+        RenderTextMode::Html,
+        None,
+    )
+}
+
+/// List the admonitions in a single markdown document, without rendering them.
+///
+/// If no config is given, the default config is used. Blocks with invalid
+/// options, and mkdocs-material style blocks, are not included.
+pub fn list_admonitions(
+    book_dir: &Path,
+    config: Option<&str>,
+    content: &str,
+) -> Result<Vec<AdmonitionPosition>> {
+    let config = match config {
+        Some(config) => admonish_config_from_str(config, book_dir)?,
+        None => Config::default(),
+    };
+    collect_admonitions(content, &overrides(config, book_dir)?)
+}
+
 fn ensure_compatible_assets_version(config: &Config) -> Result<()> {
     use semver::{Version, VersionReq};
 
     const REQUIRES_ASSETS_VERSION: &str = std::include_str!("./REQUIRED_ASSETS_VERSION");
-    let requirement = VersionReq::parse(REQUIRES_ASSETS_VERSION.trim()).unwrap();
+    let requirement = VersionReq::parse(REQUIRES_ASSETS_VERSION.trim())
+        .context("Invalid required assets version")?;
 
     const USER_ACTION: &str = "Please run `mdbook-admonish install` to update installed assets.";
     const DOCS_REFERENCE: &str = "For more information, see: https://github.com/tommilligan/mdbook-admonish#semantic-versioning";
@@ -105,7 +362,14 @@ fn ensure_compatible_assets_version(config: &Config) -> Result<()> {
         }
     };
 
-    let version = Version::parse(version).unwrap();
+    let version = Version::parse(version).map_err(|error| {
+        anyhow!(
+            r#"ERROR:
+  Invalid assets version '{version}': {error}.
+  {USER_ACTION}
+  {DOCS_REFERENCE}"#
+        )
+    })?;
 
     if !requirement.matches(&version) {
         return Err(anyhow!(
@@ -178,7 +442,7 @@ x = 20;
 "#;
         let expected_content = r##"
 
-<div id="admonition-title" class="admonition admonish-note" role="note" aria-labelledby="admonition-title-title">
+<div id="admonition-title" class="admonition admonish-note" data-admonish-directive="note" role="note" aria-labelledby="admonition-title-title">
 <div class="admonition-title">
 <div id="admonition-title-title">
 
@@ -200,14 +464,14 @@ x = 20;
 
         let ctx = mock_context(
             &json!({
-                "assets_version": "3.0.0"
+                "assets_version": "3.1.0"
             }),
             "html",
         );
         let book = mock_book(content);
         let expected_book = mock_book(expected_content);
 
-        assert_eq!(Admonish.run(&ctx, book).unwrap(), expected_book)
+        assert_eq!(Admonish::new().run(&ctx, book).unwrap(), expected_book)
     }
 
     #[test]
@@ -222,14 +486,14 @@ x = 20;
 "#;
         let ctx = mock_context(
             &json!({
-                "assets_version": "3.0.0"
+                "assets_version": "3.1.0"
             }),
             "test",
         );
         let book = mock_book(content);
         let expected_book = book.clone();
 
-        assert_eq!(Admonish.run(&ctx, book).unwrap(), expected_book)
+        assert_eq!(Admonish::new().run(&ctx, book).unwrap(), expected_book)
     }
 
     #[test]
@@ -252,7 +516,7 @@ x = 20;
 "#;
         let ctx = mock_context(
             &json!({
-                "assets_version": "3.0.0",
+                "assets_version": "3.1.0",
                 "renderer": {
                     "test": {
                         "render_mode": "strip",
@@ -264,6 +528,333 @@ x = 20;
         let book = mock_book(content);
         let expected_book = mock_book(expected_content);
 
-        assert_eq!(Admonish.run(&ctx, book).unwrap(), expected_book)
+        assert_eq!(Admonish::new().run(&ctx, book).unwrap(), expected_book)
+    }
+
+    #[test]
+    fn run_force_expand_non_html() {
+        let content = "```admonish collapsible=true\nText\n```\n";
+        let admonish = json!({
+            "assets_version": "3.1.0",
+            "renderer": { "epub": { "render_mode": "html" } },
+        });
+        let render = |renderer| {
+            let ctx = mock_context(&admonish, renderer);
+            let book = Admonish::new().run(&ctx, mock_book(content)).unwrap();
+            serde_json::to_string(&book).unwrap()
+        };
+        assert!(render("html").contains("<details"));
+        let rendered = render("epub");
+        assert!(rendered.contains("admonition admonish-note"));
+        assert!(!rendered.contains("<details"));
+    }
+
+    #[test]
+    fn run_numbered_directives_alias() {
+        let admonish = json!({
+            "assets_version": "3.1.0",
+            "default": { "numbered_directives": ["hint"] },
+        });
+        let ctx = mock_context(&admonish, "html");
+        let content = "```admonish tip\nOne\n```\n\n```admonish important\nTwo\n```\n";
+        let book = Admonish::new().run(&ctx, mock_book(content)).unwrap();
+        let actual = serde_json::to_string(&book).unwrap();
+        assert!(actual.contains("Tip 1"));
+        assert!(actual.contains("Important 2"));
+    }
+
+    #[test]
+    fn run_follows_fold_unless_configured() {
+        let content = r#"```admonish
+Text
+```"#;
+        for (admonish, expected_collapsible) in [
+            (json!({ "assets_version": "3.1.0" }), true),
+            (
+                json!({ "assets_version": "3.1.0", "default": { "collapsible": false } }),
+                false,
+            ),
+        ] {
+            let mut ctx = mock_context(&admonish, "html");
+            ctx.config.set("output.html.fold.enable", true).unwrap();
+            let book = Admonish::new().run(&ctx, mock_book(content)).unwrap();
+            let actual = serde_json::to_string(&book).unwrap();
+            assert_eq!(actual.contains("<details"), expected_collapsible);
+        }
+    }
+
+    #[test]
+    fn run_invalid_assets_version() {
+        let ctx = mock_context(&json!({ "assets_version": "not-a-version" }), "html");
+        let error = Admonish::new().run(&ctx, mock_book("")).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid assets version 'not-a-version'"));
+    }
+
+    #[test]
+    fn run_outdated_assets_version() {
+        // Assets from before the styles of new options were added
+        let ctx = mock_context(&json!({ "assets_version": "3.0.3" }), "html");
+        let error = Admonish::new().run(&ctx, mock_book("")).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("required mdbook-admonish assets version '^3.1.0', but found '3.0.3'"));
+    }
+
+    #[test]
+    fn run_skip_assets_check() {
+        let ctx = mock_context(&json!({}), "html");
+        assert!(Admonish::new().run(&ctx, mock_book("")).is_err());
+
+        let ctx = mock_context(&json!({ "default": { "skip_assets_check": true } }), "html");
+        assert!(Admonish::new().run(&ctx, mock_book("")).is_ok());
+    }
+
+    #[test]
+    fn render_mode_defaults_and_overrides() -> Result<()> {
+        let config = r#"
+[renderer.test]
+render_mode = "strip"
+
+[renderer.search]
+render_mode = "text"
+"#;
+        for (renderer, expected) in [
+            ("html", "html"),
+            ("test", "strip"),
+            ("search", "text"),
+            ("markdown", "preserve"),
+        ] {
+            assert_eq!(
+                render_mode_from_config(Path::new("."), config, renderer)?,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn render_markdown_with_and_without_config() -> Result<()> {
+        let content = r#"```admonish
+Text
+```"#;
+
+        let actual = render_markdown_from_config(Path::new("."), None, None, content)?;
+        assert!(actual.contains(r#"<div id="admonition-note" class="admonition admonish-note""#));
+
+        let config = r#"
+[default]
+title = "Custom"
+"#;
+        let actual = render_markdown_from_config(Path::new("."), Some(config), None, content)?;
+        assert!(actual.contains(r#"<div id="admonition-custom" class="admonition admonish-note""#));
+        Ok(())
+    }
+
+    #[test]
+    fn unused_custom_directives_reported() -> Result<()> {
+        let config = admonish_config_from_str(
+            r#"
+[directive.custom]
+frog = { icon = "builtin:tip", color = "green", aliases = ["toad"] }
+newt = { icon = "builtin:tip", color = "orange" }
+yak = { icon = "builtin:tip", color = "brown" }
+"#,
+            Path::new("."),
+        )?;
+        let overrides = overrides(config, Path::new("."))?;
+        assert_eq!(
+            unused_custom_directives(&overrides),
+            vec!["frog", "newt", "yak"]
+        );
+
+        preprocess(
+            "```admonish toad\nText\n```\n\n```admonish yak\nText\n```\n",
+            None,
+            None,
+            OnFailure::Continue,
+            &overrides,
+            RenderTextMode::Html,
+            None,
+        )?;
+        assert_eq!(unused_custom_directives(&overrides), vec!["newt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn failed_blocks_counted() -> Result<()> {
+        let overrides = overrides(Config::default(), Path::new("."))?;
+        assert_eq!(failed_blocks_summary(&overrides), None);
+
+        for content in [
+            "```admonish title=\"\nText\n```\n",
+            "```admonish note\nFine\n```\n\n```admonish collapsible=3\nText\n```\n\n```admonish src=\"missing.md\"\n```\n",
+        ] {
+            preprocess(
+                content,
+                None,
+                None,
+                OnFailure::Continue,
+                &overrides,
+                RenderTextMode::Html,
+                None,
+            )?;
+        }
+        assert_eq!(
+            failed_blocks_summary(&overrides).as_deref(),
+            Some("3 admonitions failed to parse")
+        );
+        // Failed blocks are still found
+        assert_eq!(overrides.found_blocks.get(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn run_missing_custom_icon() {
+        let admonish = json!({
+            "assets_version": "3.1.0",
+            "directive": { "custom": { "frog": { "icon": "missing.svg", "color": "#9B4F96" } } },
+        });
+        // Blocks still render by default
+        let ctx = mock_context(&admonish, "html");
+        assert!(Admonish::new().run(&ctx, mock_book("")).is_ok());
+
+        let mut admonish = admonish;
+        admonish["on_failure"] = json!("bail");
+        let ctx = mock_context(&admonish, "html");
+        let error = Admonish::new().run(&ctx, mock_book("")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid custom directive icons:\ncustom directive 'frog': icon file 'missing.svg' does not exist"
+        );
+    }
+
+    #[test]
+    fn list_admonitions_with_and_without_config() -> Result<()> {
+        let content = "```admonish\nText\n```\n";
+        let positions = list_admonitions(Path::new("."), None, content)?;
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].title, "Note");
+        assert_eq!(positions[0].byte_range, 0..20);
+
+        let config = r#"
+[default]
+title = "Custom"
+"#;
+        let positions = list_admonitions(Path::new("."), Some(config), content)?;
+        assert_eq!(positions[0].title, "Custom");
+        Ok(())
+    }
+
+    #[test]
+    fn run_include_exclude_chapters() -> Result<()> {
+        let content = "```admonish\nText\n```\n";
+        let chapter = |path: &str| {
+            json!({
+                "Chapter": {
+                    "name": path,
+                    "content": content,
+                    "number": null,
+                    "sub_items": [],
+                    "path": path,
+                    "source_path": path,
+                    "parent_names": []
+                }
+            })
+        };
+        let book: Book = serde_json::from_value(json!({
+            "sections": [
+                chapter("reference/options.md"),
+                chapter("reference/deprecated/old.md"),
+                chapter("guide.md"),
+            ],
+            "__non_exhaustive": null
+        }))?;
+        let processed = |book: &Book| -> Vec<bool> {
+            book.iter()
+                .map(|item| match item {
+                    BookItem::Chapter(chapter) => chapter.content != content,
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+
+        let ctx = mock_context(&json!({ "assets_version": "3.1.0" }), "html");
+        let result = Admonish::new().run(&ctx, book.clone())?;
+        assert_eq!(processed(&result), vec![true, true, true]);
+
+        let ctx = mock_context(
+            &json!({
+                "assets_version": "3.1.0",
+                "default": { "include": ["reference/**"], "exclude": ["**/deprecated/*"] },
+            }),
+            "html",
+        );
+        let result = Admonish::new().run(&ctx, book.clone())?;
+        assert_eq!(processed(&result), vec![true, false, false]);
+
+        // `*` does not match across directories
+        let ctx = mock_context(
+            &json!({ "assets_version": "3.1.0", "default": { "include": ["*.md"] } }),
+            "html",
+        );
+        let result = Admonish::new().run(&ctx, book.clone())?;
+        assert_eq!(processed(&result), vec![false, false, true]);
+
+        let ctx = mock_context(
+            &json!({ "assets_version": "3.1.0", "default": { "exclude": ["[guide"] } }),
+            "html",
+        );
+        let error = Admonish::new().run(&ctx, book).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Invalid chapter glob '[guide'"));
+        Ok(())
+    }
+
+    #[test]
+    fn run_deny_warnings() {
+        // Summaries of blocks that aren't collapsible are ignored with a warning
+        let book = mock_book("```admonish summary=\"Ignored\"\nText\n```\n");
+        let admonish = json!({ "assets_version": "3.1.0" });
+        let ctx = mock_context(&admonish, "html");
+        assert!(Admonish::new().run(&ctx, book.clone()).is_ok());
+
+        let mut admonish = admonish;
+        admonish["default"] = json!({ "deny_warnings": true });
+        let ctx = mock_context(&admonish, "html");
+        assert_eq!(
+            Admonish::new().run(&ctx, book).unwrap_err().to_string(),
+            "Failing the build after 1 warning, as 'default.deny_warnings' is set"
+        );
+
+        // Duplicate ids, and warnings from chapter front matter, are counted too
+        let book = mock_book(
+            "+++\n[admonish.default]\ntitle = \"Chapter\"\n+++\n```admonish id=\"a\"\nOne\n```\n\n```admonish id=\"a\"\nTwo\n```\n\n```admonish collapsible=3\n```\n",
+        );
+        assert_eq!(
+            Admonish::new().run(&ctx, book).unwrap_err().to_string(),
+            "Failing the build after 2 warnings, as 'default.deny_warnings' is set"
+        );
+        assert!(Admonish::new()
+            .run(&ctx, mock_book("```admonish\nText\n```\n"))
+            .is_ok());
+
+        // Warnings from loading the config are counted too
+        let book = mock_book("```admonish\nText\n```\n");
+        for admonish in [
+            json!({ "default": { "deny_warnings": true, "skip_assets_check": true } }),
+            json!({ "assets_version": "3.1.0", "default": { "deny_warnings": true, "directive": "frog" } }),
+        ] {
+            let ctx = mock_context(&admonish, "html");
+            assert_eq!(
+                Admonish::new()
+                    .run(&ctx, book.clone())
+                    .unwrap_err()
+                    .to_string(),
+                "Failing the build after 1 warning, as 'default.deny_warnings' is set"
+            );
+        }
     }
 }
@@ -1,132 +1,725 @@
-use mdbook::utils::unique_id_from_content;
+use mdbook::utils::{normalize_id, unique_id_from_content};
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
-use crate::{resolve::AdmonitionMeta, types::CssId};
+use crate::{
+    resolve::AdmonitionMeta,
+    types::{
+        AdmonitionElement, Align, BlockStyle, BodyFormat, ClassStyle, CollapsibleImpl, CssId,
+        Density, SummaryMarker, TitleLayout, UntitledIdSource,
+    },
+};
+
+/// A block being rendered to html, as passed to an [`AdmonitionRenderHook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RenderedAdmonition<'a> {
+    /// The canonical directive, used in CSS classnames.
+    pub directive: &'a str,
+    /// The directive as given by the user, before aliases are resolved.
+    pub raw_directive: &'a str,
+    /// The title of the block, before any number label is added.
+    pub title: &'a str,
+    /// The id of the block element.
+    pub id: &'a str,
+    /// Whether the block is collapsible.
+    pub collapsible: bool,
+}
+
+/// Post-process the html generated for each block.
+///
+/// Set on the preprocessor with [`Admonish::with_render_hook`](crate::Admonish::with_render_hook).
+/// Only called when rendering blocks to html.
+pub trait AdmonitionRenderHook: Send + Sync {
+    /// Returns the html to use for `admonition`, given the `html` generated for it.
+    fn render(&self, admonition: &RenderedAdmonition<'_>, html: String) -> String;
+}
+
+/// A shared [`AdmonitionRenderHook`].
+#[derive(Clone)]
+pub(crate) struct RenderHook(pub(crate) Arc<dyn AdmonitionRenderHook>);
+
+impl fmt::Debug for RenderHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RenderHook")
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub(crate) struct Admonition<'a> {
     pub(crate) directive: String,
+    pub(crate) raw_directive: String,
+    /// Directive for the `data-admonish-directive` attribute.
+    pub(crate) written_directive: String,
     pub(crate) title: String,
+    pub(crate) number_label: Option<String>,
     pub(crate) content: Cow<'a, str>,
     pub(crate) css_id: CssId,
+    pub(crate) untitled_id_source: UntitledIdSource,
     pub(crate) additional_classnames: Vec<String>,
+    pub(crate) attributes: Vec<(String, String)>,
     pub(crate) collapsible: bool,
+    pub(crate) open: bool,
+    pub(crate) summary: Option<String>,
+    pub(crate) element: AdmonitionElement,
+    pub(crate) collapsible_impl: CollapsibleImpl,
+    pub(crate) density: Density,
+    pub(crate) style: BlockStyle,
+    pub(crate) title_layout: TitleLayout,
+    pub(crate) marker: SummaryMarker,
+    pub(crate) class_style: ClassStyle,
+    pub(crate) title_anchor: bool,
+    pub(crate) anchor_glyph: String,
+    pub(crate) badge: bool,
+    pub(crate) icon: bool,
+    pub(crate) icon_svg: Option<String>,
+    pub(crate) avoid_page_break: bool,
+    pub(crate) figure_wrap: bool,
+    pub(crate) state_labels: bool,
+    pub(crate) title_link: Option<String>,
+    pub(crate) caption: Option<String>,
+    pub(crate) renderers: Option<Vec<String>>,
+    pub(crate) width: Option<String>,
+    pub(crate) align: Option<Align>,
+    pub(crate) lang: Option<String>,
+    pub(crate) body_format: BodyFormat,
+    pub(crate) inline: bool,
+    pub(crate) body_prefix: Option<String>,
+    pub(crate) body_suffix: Option<String>,
+    pub(crate) role: String,
+    pub(crate) aria_label: Option<String>,
     pub(crate) indent: usize,
 }
 
 impl<'a> Admonition<'a> {
-    pub(crate) fn new(info: AdmonitionMeta, content: &'a str, indent: usize) -> Self {
+    pub(crate) fn new(info: AdmonitionMeta, content: Cow<'a, str>, indent: usize) -> Self {
         let AdmonitionMeta {
             directive,
+            raw_directive,
+            written_directive,
             title,
+            number_label,
             css_id,
+            untitled_id_source,
             additional_classnames,
+            attributes,
             collapsible,
+            open,
+            summary,
+            element,
+            collapsible_impl,
+            density,
+            style,
+            title_layout,
+            marker,
+            class_style,
+            title_anchor,
+            anchor_glyph,
+            badge,
+            icon,
+            icon_svg,
+            avoid_page_break,
+            figure_wrap,
+            state_labels,
+            title_link,
+            caption,
+            src: _,
+            renderers,
+            width,
+            align,
+            lang,
+            body_format,
+            inline,
+            body_prefix,
+            body_suffix,
+            default_body: _,
+            role,
+            aria_label,
+            renamed_from: _,
+            // Applied once the body is known
+            auto_collapse_lines: _,
+            // Logged while parsing
+            directive_disabled: _,
         } = info;
         Self {
             directive,
+            raw_directive,
+            written_directive,
             title,
-            content: Cow::Borrowed(content),
+            number_label,
+            content,
             css_id,
+            untitled_id_source,
             additional_classnames,
+            attributes,
             collapsible,
+            open,
+            summary,
+            element,
+            collapsible_impl,
+            density,
+            style,
+            title_layout,
+            marker,
+            class_style,
+            title_anchor,
+            anchor_glyph,
+            badge,
+            icon,
+            icon_svg,
+            avoid_page_break,
+            figure_wrap,
+            state_labels,
+            title_link,
+            caption,
+            renderers,
+            width,
+            align,
+            lang,
+            body_format,
+            inline,
+            body_prefix,
+            body_suffix,
+            role,
+            aria_label,
             indent,
         }
     }
 
-    pub(crate) fn html(self, id_counter: &mut HashMap<String, usize>) -> String {
+    /// Whether this block should be shown for `renderer`.
+    ///
+    /// If the renderer is not known, all blocks are shown.
+    pub(crate) fn is_for_renderer(&self, renderer: Option<&str>) -> bool {
+        match (&self.renderers, renderer) {
+            (Some(renderers), Some(renderer)) => renderers.iter().any(|name| name == renderer),
+            _ => true,
+        }
+    }
+
+    pub(crate) fn html(
+        self,
+        id_counter: &mut HashMap<String, usize>,
+        number_counter: &mut HashMap<String, usize>,
+        anchor_page: Option<&str>,
+        render_hook: Option<&RenderHook>,
+    ) -> String {
         let anchor_id = match &self.css_id {
             CssId::Verbatim(id) => Cow::Borrowed(id.as_str()),
             CssId::Prefix(prefix) => {
-                let id = unique_id_from_content(
-                    if !self.title.is_empty() {
-                        &self.title
-                    } else {
-                        ANCHOR_ID_DEFAULT
-                    },
-                    id_counter,
-                );
+                let id = if !self.title.is_empty() {
+                    unique_id_from_title(&self.title, id_counter)
+                } else {
+                    match self.untitled_id_source {
+                        UntitledIdSource::Default => {
+                            unique_id_from_content(ANCHOR_ID_DEFAULT, id_counter)
+                        }
+                        UntitledIdSource::Directive => {
+                            unique_id_from_content(&self.directive, id_counter)
+                        }
+                        UntitledIdSource::Index => {
+                            // Generated ids never contain whitespace, so this key
+                            // can't clash with them
+                            let index =
+                                id_counter.entry(UNTITLED_INDEX_KEY.to_owned()).or_default();
+                            *index += 1;
+                            let index = index.to_string();
+                            unique_id_from_content(&index, id_counter)
+                        }
+                    }
+                };
 
                 Cow::Owned(format!("{}{}", prefix, id))
             }
         };
 
-        let title = &self.title;
-        let content = &self.content;
+        // Inline blocks have no titlebar
+        let title = if self.inline {
+            Cow::Borrowed("")
+        } else {
+            self.numbered_title(number_counter)
+        };
+        // Titled blocks may be wrapped in a figure, captioned with the title
+        let figure_caption = (self.figure_wrap && !title.is_empty()).then(|| title.clone());
+        // A summary replaces the title in the titlebar, and the title is shown
+        // above the content instead
+        let (title, expanded_title) = match &self.summary {
+            Some(summary) => (
+                Cow::Borrowed(summary.as_str()),
+                Some(title).filter(|title| !title.is_empty()),
+            ),
+            None => (title, None),
+        };
+        let content = self.body();
         let indent = " ".repeat(self.indent);
+        let icon_html = match &self.icon_svg {
+            Some(svg) => Cow::Owned(format!(
+                r#"<span class="admonition-icon" aria-hidden="true">{svg}</span>"#
+            )),
+            None => Cow::Borrowed(""),
+        };
 
+        // Disclosure widgets are toggled from the titlebar, so blocks without
+        // one are left expanded
+        let native_collapsible =
+            self.collapsible && self.collapsible_impl == CollapsibleImpl::Native;
+        let aria_collapsible =
+            self.collapsible && self.collapsible_impl == CollapsibleImpl::Aria && !title.is_empty();
+        let state_labels = native_collapsible && self.state_labels;
+        let content_id = format!("{anchor_id}-content");
+
+        let badge_html = if self.badge {
+            Cow::Owned(format!(
+                r#"{indent}<span class="admonition-badge">{directive}</span>
+"#,
+                directive = escape_html(&self.directive),
+            ))
+        } else {
+            Cow::Borrowed("")
+        };
         let (titlebar_html, title_id) = if !title.is_empty() {
-            let titlebar_element = if self.collapsible { "summary" } else { "div" };
+            let titlebar_element = if native_collapsible { "summary" } else { "div" };
             let title_id = format!("{anchor_id}-title");
+            let toggle_html = if aria_collapsible {
+                Cow::Owned(format!(
+                    r#"{indent}<button type="button" class="admonition-toggle" aria-expanded="{open}" aria-controls="{content_id}" aria-labelledby="{title_id}"></button>
+"#,
+                    open = self.open,
+                    content_id = escape_html(&content_id),
+                    title_id = escape_html(&title_id),
+                ))
+            } else {
+                Cow::Borrowed("")
+            };
+            let anchor_link_html = if self.title_anchor {
+                let href = escape_html(&format!(
+                    "{anchor_page}#{anchor_id}",
+                    anchor_page = anchor_page.unwrap_or_default()
+                ));
+                Cow::Owned(format!(
+                    r##"{indent}<a class="admonition-anchor-link" href="{href}">{glyph}</a>
+"##,
+                    glyph = escape_html(&self.anchor_glyph),
+                ))
+            } else {
+                Cow::Borrowed("")
+            };
+            let mut titlebar_class = Cow::Borrowed(match self.title_layout {
+                TitleLayout::Inline => "admonition-title",
+                TitleLayout::Stacked => "admonition-title admonition-title-stacked",
+            });
+            if native_collapsible {
+                match self.marker {
+                    SummaryMarker::Start => titlebar_class += " admonition-marker-start",
+                    SummaryMarker::End => {}
+                    SummaryMarker::Hidden => titlebar_class += " admonition-marker-none",
+                }
+            }
+            // Kept up to date by a script included in the chapter
+            let (titlebar_state_attribute, state_label_html) = if state_labels {
+                (
+                    Cow::Owned(format!(r#" aria-expanded="{}""#, self.open)),
+                    Cow::Owned(format!(
+                        r#"{indent}<span class="admonition-state-label">{label}</span>
+"#,
+                        label = state_label(self.open),
+                    )),
+                )
+            } else {
+                (Cow::Borrowed(""), Cow::Borrowed(""))
+            };
+            let titlebar_icon_html = if icon_html.is_empty() {
+                Cow::Borrowed("")
+            } else {
+                Cow::Owned(format!("{indent}{icon_html}\n"))
+            };
+            // Links are validated when parsing
+            let title = match &self.title_link {
+                Some(title_link) => Cow::Owned(format!(
+                    r#"<a class="admonition-title-link" href="{href}">{title}</a>"#,
+                    href = escape_html(title_link),
+                )),
+                None => title,
+            };
             let titlebar_html = Cow::Owned(format!(
-                r##"{indent}<{titlebar_element} class="admonition-title">
-{indent}<div id="{title_id}">
+                r##"{indent}<{titlebar_element} class="{titlebar_class}"{titlebar_state_attribute}>
+{toggle_html}{titlebar_icon_html}{indent}<div id="{escaped_title_id}">
 {indent}
 {indent}{title}
 {indent}
 {indent}</div>
-{indent}<a class="admonition-anchor-link" href="#{anchor_id}"></a>
-{indent}</{titlebar_element}>
-"##
+{state_label_html}{badge_html}{anchor_link_html}{indent}</{titlebar_element}>
+"##,
+                escaped_title_id = escape_html(&title_id),
             ));
             (titlebar_html, Some(title_id))
         } else {
-            (Cow::Borrowed(""), None)
+            // Untitled blocks still show the badge, above the content
+            (badge_html, None)
+        };
+
+        let expanded_title_html = match expanded_title {
+            Some(title) => Cow::Owned(format!(
+                r#"{indent}<div class="admonition-expanded-title">
+{indent}
+{indent}{title}
+{indent}
+{indent}</div>
+"#
+            )),
+            None => Cow::Borrowed(""),
+        };
+
+        let caption_html = match &self.caption {
+            Some(caption) => Cow::Owned(format!(
+                r#"{indent}<div class="admonition-caption">{caption}</div>
+"#,
+                caption = escape_html(caption)
+            )),
+            None => Cow::Borrowed(""),
         };
 
         let mut classes = vec![
-            "admonition".to_owned(),
-            format!("admonish-{}", self.directive),
+            if self.inline {
+                "admonition-inline"
+            } else {
+                "admonition"
+            }
+            .to_owned(),
+            self.class_style.directive_class(&self.directive),
         ];
-        classes.extend(self.additional_classnames);
+        if self.density == Density::Compact {
+            classes.push("admonition-compact".to_owned());
+        }
+        if self.style == BlockStyle::Bar {
+            classes.push("admonition-bar".to_owned());
+        }
+        if !self.icon {
+            classes.push("admonition-no-icon".to_owned());
+        }
+        if self.icon_svg.is_some() {
+            classes.push("admonition-svg-icon".to_owned());
+        }
+        if let Some(align) = self.align {
+            classes.push(align.classname().to_owned());
+        }
+        // Keep the first of any duplicate classes
+        for classname in &self.additional_classnames {
+            if !classes.contains(classname) {
+                classes.push(classname.clone());
+            }
+        }
         let classes = classes.join(" ");
 
-        let mut attributes = vec![
-            ("id", anchor_id),
+        let mut attributes: Vec<(&str, Cow<str>)> = vec![
+            ("id", Cow::Borrowed(anchor_id.as_ref())),
             ("class", Cow::Owned(classes)),
-            ("role", Cow::Borrowed("note")),
+            (
+                "data-admonish-directive",
+                Cow::Borrowed(self.written_directive.as_str()),
+            ),
+            ("role", Cow::Borrowed(self.role.as_str())),
         ];
-        if let Some(title_id) = title_id {
-            attributes.push(("aria-labelledby", Cow::Owned(title_id)));
+        // An explicit label takes precedence over referencing the title
+        if let Some(aria_label) = &self.aria_label {
+            attributes.push(("aria-label", Cow::Borrowed(aria_label.as_str())));
+        } else if let Some(title_id) = &title_id {
+            attributes.push(("aria-labelledby", Cow::Borrowed(title_id.as_str())));
+        }
+        let mut styles = Vec::new();
+        if self.avoid_page_break {
+            styles.push(Cow::Borrowed("break-inside: avoid"));
+        }
+        // Width is validated as a CSS length when parsing
+        if let Some(width) = &self.width {
+            styles.push(Cow::Owned(format!("width: {width}")));
+        }
+        if !styles.is_empty() {
+            attributes.push(("style", Cow::Owned(styles.join("; "))));
+        }
+        if native_collapsible && self.open {
+            attributes.push(("open", Cow::Borrowed("")));
+        }
+        if let Some(lang) = &self.lang {
+            attributes.push(("lang", Cow::Borrowed(lang.as_str())));
+        }
+        // Names are validated when parsing
+        for (name, value) in &self.attributes {
+            attributes.push((name.as_str(), Cow::Borrowed(value.as_str())));
         }
         let attributes = join_attributes(&attributes);
 
-        let admonition_element = if self.collapsible { "details" } else { "div" };
+        let admonition_element = if native_collapsible {
+            "details"
+        } else {
+            self.element.tag()
+        };
+        let content_open_tag = match &title_id {
+            Some(title_id) if aria_collapsible => Cow::Owned(format!(
+                r#"<div id="{content_id}" role="region" aria-labelledby="{title_id}"{hidden}>"#,
+                content_id = escape_html(&content_id),
+                title_id = escape_html(title_id),
+                hidden = if self.open { "" } else { " hidden" },
+            )),
+            _ => Cow::Borrowed("<div>"),
+        };
         // Notes on the HTML template:
         // - the additional whitespace around the content are deliberate
         //   In line with the commonmark spec, this allows the inner content to be
         //   rendered as markdown paragraphs.
-        format!(
-            r#"
+        // - html bodies are left without it, so they stay part of the
+        //   surrounding html block rather than being parsed as markdown.
+        let body_html = match self.body_format {
+            BodyFormat::Markdown => format!(
+                r#"{indent}
+{indent}{content}
+{indent}
+"#
+            ),
+            BodyFormat::Html => format!(
+                r#"{indent}{content}
+"#
+            ),
+        };
+        let html = if self.inline {
+            // Reuses the titlebar class, so the directive's icon rules apply
+            let icon_html = if self.icon {
+                Cow::Owned(format!(
+                    r#"<span class="admonition-title" aria-hidden="true">{icon_html}</span>"#
+                ))
+            } else {
+                Cow::Borrowed("")
+            };
+            format!(
+                r#"
+{indent}<span {attributes}>{icon_html}{content}</span>"#,
+                content = self.content.trim(),
+            )
+        } else {
+            format!(
+                r#"
 {indent}<{admonition_element} {attributes}>
-{titlebar_html}{indent}<div>
+{titlebar_html}{indent}{content_open_tag}
+{expanded_title_html}{body_html}{indent}</div>
+{caption_html}{indent}</{admonition_element}>"#,
+            )
+        };
+        let html = match figure_caption {
+            Some(figure_caption) => format!(
+                r#"
+{indent}<figure id="{figure_id}" class="admonition-figure">{html}
+{indent}<figcaption>
 {indent}
+{indent}{figure_caption}
+{indent}
+{indent}</figcaption>
+{indent}</figure>"#,
+                figure_id = escape_html(&format!("{anchor_id}-figure")),
+            ),
+            None => html,
+        };
+        match render_hook {
+            Some(RenderHook(hook)) => hook.render(
+                &RenderedAdmonition {
+                    directive: &self.directive,
+                    raw_directive: &self.raw_directive,
+                    title: &self.title,
+                    id: &anchor_id,
+                    collapsible: self.collapsible,
+                },
+                html,
+            ),
+            None => html,
+        }
+    }
+
+    /// Render as a `<blockquote>`, with the title in bold.
+    ///
+    /// Blocks are never collapsible, and only the title and content are kept.
+    pub(crate) fn blockquote(self, number_counter: &mut HashMap<String, usize>) -> String {
+        let title = self.numbered_title(number_counter);
+        let indent = " ".repeat(self.indent);
+        let title_html = if !title.is_empty() {
+            Cow::Owned(format!(
+                r#"{indent}<p><strong>{title}</strong></p>
+"#
+            ))
+        } else {
+            Cow::Borrowed("")
+        };
+        format!(
+            r#"
+{indent}<blockquote>
+{title_html}{indent}
 {indent}{content}
 {indent}
-{indent}</div>
-{indent}</{admonition_element}>"#,
+{indent}</blockquote>"#,
+            content = self.body()
+        )
+    }
+
+    /// Plain text output, with the title as its own paragraph above the body.
+    ///
+    /// Markdown in the body is left as is, only the fences and options are
+    /// removed.
+    pub(crate) fn to_text(&self, number_counter: &mut HashMap<String, usize>) -> String {
+        let title = self.numbered_title(number_counter);
+        let indent = " ".repeat(self.indent);
+        let title_text = if !title.is_empty() {
+            Cow::Owned(format!("{indent}{title}\n{indent}\n"))
+        } else {
+            Cow::Borrowed("")
+        };
+        // The first line of the body keeps its indent in the document, which
+        // is given again here
+        let body = self.body();
+        let content = body.trim_end();
+        let content = content.strip_prefix(indent.as_str()).unwrap_or(content);
+        format!("\n{title_text}{indent}{content}\n")
+    }
+
+    /// The content, with the prefix and suffix of the directive around it.
+    ///
+    /// Each part is separated by a blank line, so they render as separate
+    /// markdown paragraphs.
+    fn body(&self) -> Cow<'_, str> {
+        if self.body_prefix.is_none() && self.body_suffix.is_none() {
+            return Cow::Borrowed(&self.content);
+        }
+        // Lines after the first must be indented to stay inside the block
+        let indent = " ".repeat(self.indent);
+        let indent_lines = |text: &str| {
+            text.trim()
+                .lines()
+                .collect::<Vec<_>>()
+                .join(&format!("\n{indent}"))
+        };
+        let parts = [
+            self.body_prefix.as_deref().map(indent_lines),
+            Some(self.content.trim_end().to_owned()),
+            self.body_suffix.as_deref().map(indent_lines),
+        ];
+        Cow::Owned(
+            parts
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(&format!("\n{indent}\n{indent}")),
         )
     }
 
+    /// The title, with the number of the block if it is numbered.
+    fn numbered_title(&self, number_counter: &mut HashMap<String, usize>) -> Cow<'_, str> {
+        match &self.number_label {
+            Some(label) => {
+                let number = number_counter.entry(self.directive.clone()).or_default();
+                *number += 1;
+                if &self.title == label {
+                    Cow::Owned(format!("{label} {number}"))
+                } else {
+                    Cow::Owned(format!("{label} {number}: {}", self.title))
+                }
+            }
+            None => Cow::Borrowed(self.title.as_str()),
+        }
+    }
+
     /// Strips all admonish syntax, leaving the plain content of the block.
-    pub(crate) fn strip(self) -> String {
-        // Add in newlines to preserve line numbering for test output
-        // These replace the code fences we stripped out
-        format!("\n{}\n", self.content)
+    ///
+    /// If `annotate` is set, the options of the block are kept as JSON in an
+    /// HTML comment, so that a later tool can rebuild the block.
+    pub(crate) fn strip(self, annotate: bool) -> String {
+        if !annotate {
+            // Add in newlines to preserve line numbering for test output
+            // These replace the code fences we stripped out
+            return format!("\n{}\n", self.content);
+        }
+
+        let id = match &self.css_id {
+            CssId::Verbatim(id) => Some(id.as_str()),
+            CssId::Prefix(_) => None,
+        };
+        let annotation = serde_json::json!({
+            "directive": self.raw_directive,
+            "title": self.title,
+            "id": id,
+            "class": self.additional_classnames,
+            "collapsible": self.collapsible,
+        });
+        // `>` only occurs inside JSON strings, so escaping it keeps the
+        // comment from being closed early by `-->`
+        let annotation = annotation.to_string().replace('>', "\\u003e");
+        // The comments replace the code fences, preserving line numbering
+        format!(
+            "<!-- admonish: {annotation} -->\n{}\n<!-- /admonish -->",
+            self.content
+        )
+    }
+}
+
+/// Visually hidden text announcing whether a collapsible block is expanded.
+///
+/// Must match the labels set by `state_labels.js`.
+fn state_label(open: bool) -> &'static str {
+    if open {
+        "expanded"
+    } else {
+        "collapsed"
     }
 }
 
+/// Join attributes for use in an HTML tag.
+///
+/// Values are escaped, but names must already be valid attribute names.
 fn join_attributes(attributes: &[(impl AsRef<str>, impl AsRef<str>)]) -> String {
     let mut buffer = String::new();
     for (key, value) in attributes {
         buffer.push_str(key.as_ref());
         buffer.push_str(r#"=""#);
-        buffer.push_str(value.as_ref());
+        buffer.push_str(&escape_html(value.as_ref()));
         buffer.push_str(r#"" "#);
     }
     buffer.pop();
     buffer
 }
 
+/// Escape text for use as HTML element content, or in a quoted attribute value.
+fn escape_html(input: &str) -> String {
+    let mut buffer = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => buffer.push_str("&amp;"),
+            '<' => buffer.push_str("&lt;"),
+            '>' => buffer.push_str("&gt;"),
+            '"' => buffer.push_str("&quot;"),
+            _ => buffer.push(c),
+        }
+    }
+    buffer
+}
+
+/// An id for a block with `title`, unique within the chapter.
+///
+/// Titles without any characters allowed in ids, such as emoji only titles,
+/// would all get the same empty id, so use a hash of the title instead.
+fn unique_id_from_title(title: &str, id_counter: &mut HashMap<String, usize>) -> String {
+    if normalize_id(title).is_empty() {
+        unique_id_from_content(&format!("{:08x}", fnv1a_hash(title)), id_counter)
+    } else {
+        unique_id_from_content(title, id_counter)
+    }
+}
+
+/// 32 bit FNV-1a hash. Unlike the std hasher, this is stable between releases,
+/// so ids don't change when rebuilding a book.
+fn fnv1a_hash(input: &str) -> u32 {
+    const OFFSET_BASIS: u32 = 0x811c_9dc5;
+    const PRIME: u32 = 0x0100_0193;
+    input.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
 const ANCHOR_ID_DEFAULT: &str = "default";
+const UNTITLED_INDEX_KEY: &str = "untitled index";
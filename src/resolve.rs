@@ -1,5 +1,10 @@
 use crate::config::InstanceConfig;
-use crate::types::{BuiltinDirective, CssId, CustomDirective, CustomDirectiveMap, Overrides};
+use crate::custom::builtin_icon_svg;
+use crate::types::{
+    AdmonitionElement, Align, BlockStyle, BodyFormat, BuiltinDirective, ClassStyle,
+    CollapsibleImpl, CssId, CustomDirective, Density, Overrides, SummaryMarker, TitleLayout,
+    UntitledIdSource,
+};
 use std::fmt;
 use std::str::FromStr;
 
@@ -9,16 +14,84 @@ use std::str::FromStr;
 #[derive(Debug, PartialEq)]
 pub(crate) struct AdmonitionMeta {
     pub directive: String,
+    /// The directive as given by the user, before aliases are resolved.
+    pub raw_directive: String,
+    /// The directive exactly as written in the block, even if it is unknown
+    /// or deprecated. Blocks without a directive use the fallback directive.
+    pub written_directive: String,
     pub title: String,
+    /// Label to number this block with, if it should be numbered.
+    pub number_label: Option<String>,
     pub css_id: CssId,
+    pub untitled_id_source: UntitledIdSource,
     pub additional_classnames: Vec<String>,
+    /// Extra attributes for the outer element, as `(name, value)` pairs.
+    pub attributes: Vec<(String, String)>,
     pub collapsible: bool,
+    /// Whether a collapsible block starts expanded.
+    pub open: bool,
+    /// Text of the `<summary>` of a collapsible block, if different to the title.
+    pub summary: Option<String>,
+    pub element: AdmonitionElement,
+    pub collapsible_impl: CollapsibleImpl,
+    pub density: Density,
+    pub style: BlockStyle,
+    pub title_layout: TitleLayout,
+    pub marker: SummaryMarker,
+    pub class_style: ClassStyle,
+    pub title_anchor: bool,
+    /// Text inside the anchor link, may be empty.
+    pub anchor_glyph: String,
+    pub badge: bool,
+    /// Whether to show the icon of the directive.
+    pub icon: bool,
+    /// SVG markup of the icon, to include in the titlebar rather than
+    /// drawing it with CSS.
+    pub icon_svg: Option<String>,
+    pub avoid_page_break: bool,
+    /// Wrap the block in a `<figure>`, if it has a title.
+    pub figure_wrap: bool,
+    /// Label collapsible blocks with whether they are expanded.
+    pub state_labels: bool,
+    /// Link to wrap the title in.
+    pub title_link: Option<String>,
+    /// Plain text shown below the content.
+    pub caption: Option<String>,
+    /// File to load the body from, relative to the book source directory.
+    pub src: Option<String>,
+    /// Renderers to show this block for. If not set, shown for all renderers.
+    pub renderers: Option<Vec<String>>,
+    /// Width of the block, as a CSS length.
+    pub width: Option<String>,
+    /// Side to float the block to.
+    pub align: Option<Align>,
+    /// Language of the content, for the `lang` attribute.
+    pub lang: Option<String>,
+    pub body_format: BodyFormat,
+    /// Render as an inline span, rather than a block.
+    pub inline: bool,
+    /// Markdown added before the body, from the directive config.
+    pub body_prefix: Option<String>,
+    /// Markdown added after the body, from the directive config.
+    pub body_suffix: Option<String>,
+    /// Body to use if the block's body is empty, from a preset.
+    pub default_body: Option<String>,
+    /// ARIA role of the block.
+    pub role: String,
+    /// Accessible name of the block, instead of referencing the title.
+    pub aria_label: Option<String>,
+    /// Deprecated directive given by the block, if the directive was renamed.
+    pub renamed_from: Option<String>,
+    /// Make the block collapsible if its body is longer than this many lines.
+    pub auto_collapse_lines: Option<usize>,
+    /// Whether the block's directive is a disabled builtin directive.
+    pub directive_disabled: bool,
 }
 
 /// Wrapper type to hold any value directive configuration.
 enum Directive {
     Builtin(BuiltinDirective),
-    Custom(CustomDirective),
+    Custom(Box<CustomDirective>),
 }
 
 impl fmt::Display for Directive {
@@ -31,36 +104,90 @@ impl fmt::Display for Directive {
 }
 
 impl Directive {
-    fn from_str(custom_directive_map: &CustomDirectiveMap, string: &str) -> Result<Self, ()> {
+    /// Find an enabled builtin or custom directive.
+    fn lookup(overrides: &Overrides, string: &str) -> Option<Self> {
         if let Ok(builtin) = BuiltinDirective::from_str(string) {
-            return Ok(Self::Builtin(builtin));
+            if !is_disabled(&overrides.book.disabled_directives, builtin) {
+                return Some(Self::Builtin(builtin));
+            }
         }
+        overrides
+            .custom
+            .get(string)
+            .map(|config| Self::Custom(Box::new(config.clone())))
+    }
 
-        if let Some(config) = custom_directive_map.get(string) {
-            return Ok(Self::Custom(config.clone()));
+    /// The directive to use when none, or an unknown one, is given.
+    ///
+    /// `default.directive` is validated when the config is loaded.
+    ///
+    /// Returns the directive, and the name it was given by.
+    fn fallback(overrides: &Overrides) -> (Self, String) {
+        if let Some(name) = &overrides.book.directive {
+            if let Some(directive) = Self::lookup(overrides, name) {
+                return (directive, name.clone());
+            }
         }
-
-        Err(())
+        (
+            Self::Builtin(BuiltinDirective::Note),
+            BuiltinDirective::Note.to_string(),
+        )
     }
 
-    fn title(self, raw_directive: &str) -> String {
+    fn title(self, raw_directive: &str, id: Option<&str>, overrides: &Overrides) -> String {
         match self {
-            Directive::Builtin(_) => format_builtin_directive_title(raw_directive),
+            Directive::Builtin(builtin) => {
+                let titles = &overrides.book.titles;
+                titles
+                    .get(raw_directive)
+                    .or_else(|| titles.get(&builtin.to_string()))
+                    .cloned()
+                    .unwrap_or_else(|| format_builtin_directive_title(raw_directive))
+            }
             Directive::Custom(custom) => custom
                 .title
-                .clone()
+                .map(|template| interpolate_title(&template, raw_directive, id))
                 .unwrap_or_else(|| uppercase_first(raw_directive)),
         }
     }
 }
 
+/// The directive to use for the error block shown in place of an invalid block.
+///
+/// Returns the directive, and the name it was given by.
+pub(crate) fn error_directive(overrides: &Overrides) -> (String, String) {
+    if let Some(name) = &overrides.book.error_directive {
+        if let Some(directive) = Directive::lookup(overrides, name) {
+            return (directive.to_string(), name.clone());
+        }
+    }
+    (
+        BuiltinDirective::Bug.to_string(),
+        BuiltinDirective::Bug.to_string(),
+    )
+}
+
 impl AdmonitionMeta {
     pub fn from_info_string(
         info_string: &str,
+        front_matter: Option<&str>,
         overrides: &Overrides,
     ) -> Option<Result<Self, String>> {
-        InstanceConfig::from_info_string(info_string)
-            .map(|raw| raw.map(|raw| Self::resolve(raw, overrides)))
+        InstanceConfig::from_info_string(info_string).map(|raw| {
+            raw.and_then(|mut raw| {
+                if let Some(front_matter) = front_matter {
+                    raw.merge_front_matter(front_matter)?;
+                }
+                let default_body = raw.apply_preset(overrides)?;
+                if raw.inline == Some(true) && raw.collapsible == Some(true) {
+                    return Err("Inline blocks can't be collapsible".to_owned());
+                }
+                Ok(Self {
+                    default_body,
+                    ..Self::resolve(raw, overrides)
+                })
+            })
+        })
     }
 
     /// Combine the per-admonition configuration with global defaults (and
@@ -70,68 +197,300 @@ impl AdmonitionMeta {
             directive: raw_directive,
             title,
             id,
+            id_prefix,
             additional_classnames,
+            attributes,
             collapsible,
+            src,
+            badge,
+            icon,
+            caption,
+            density,
+            style,
+            renderers,
+            open,
+            summary,
+            width,
+            align,
+            lang,
+            body_format,
+            inline,
+            // Applied before resolving
+            preset: _,
+            role,
+            aria_label,
+            title_link,
         } = raw;
 
         // Use values from block, else load default value
         let title = title.or_else(|| overrides.book.title.clone());
 
-        let directive = Directive::from_str(&overrides.custom, &raw_directive);
-
-        let collapsible = match directive {
-            // If the directive is a builin one, use collapsible from block, else use default
-            // value of the builtin directive, else use global default value
-            Ok(Directive::Builtin(directive)) => collapsible.unwrap_or(
-                overrides
-                    .builtin
-                    .get(&directive)
-                    .and_then(|config| config.collapsible)
-                    .unwrap_or(overrides.book.collapsible),
-            ),
-            // If the directive is a custom one, use collapsible from block, else use default
-            // value of the custom directive, else use global default value
-            Ok(Directive::Custom(ref custom_dir)) => {
-                collapsible.unwrap_or(custom_dir.collapsible.unwrap_or(overrides.book.collapsible))
+        let written_directive = raw_directive.clone();
+        let directive_disabled = BuiltinDirective::from_str(&raw_directive)
+            .is_ok_and(|builtin| is_disabled(&overrides.book.disabled_directives, builtin));
+        let (directive, mut raw_directive, is_fallback) =
+            match Directive::lookup(overrides, &raw_directive) {
+                Some(directive) => (directive, raw_directive, false),
+                None => {
+                    let (directive, name) = Directive::fallback(overrides);
+                    (directive, name, true)
+                }
+            };
+        // Blocks without a known directive look like the fallback directive,
+        // but only book wide defaults apply to them, not its directive config
+        let directive_config = (!is_fallback).then_some(&directive);
+        let written_directive = if written_directive.trim().is_empty() {
+            raw_directive.clone()
+        } else {
+            written_directive
+        };
+
+        // Blocks using a deprecated name are rendered as the renamed directive
+        let renamed_from = match (
+            directive_config,
+            overrides.custom.renamed_to(&raw_directive),
+        ) {
+            (Some(Directive::Custom(_)), Some(renamed_to)) => {
+                Some(std::mem::replace(&mut raw_directive, renamed_to.to_owned()))
             }
-            Err(_) => collapsible.unwrap_or(overrides.book.collapsible),
+            _ => None,
         };
 
-        // Load the directive (and title, if one still not given)
-        let (directive, title) = match (directive, title) {
-            (Ok(directive), None) => (directive.to_string(), directive.title(&raw_directive)),
-            (Err(_), None) => (BuiltinDirective::Note.to_string(), "Note".to_owned()),
-            (Ok(directive), Some(title)) => (directive.to_string(), title),
-            (Err(_), Some(title)) => (BuiltinDirective::Note.to_string(), title),
+        // Inline blocks are never collapsible. Otherwise use collapsible from
+        // block, else use default value of the directive, else use the book wide
+        // list of collapsible directives, else use global default value
+        let inline = inline.unwrap_or_default();
+        let explicit_collapsible = collapsible.is_some();
+        let collapsible = !inline
+            && collapsible.unwrap_or_else(|| {
+                let directive_collapsible = match directive_config {
+                    Some(Directive::Builtin(builtin)) => overrides
+                        .builtin
+                        .get(builtin)
+                        .and_then(|config| config.collapsible),
+                    Some(Directive::Custom(custom)) => custom
+                        .alias_collapsible
+                        .get(&raw_directive)
+                        .copied()
+                        .or(custom.collapsible),
+                    None => None,
+                };
+                directive_collapsible.unwrap_or_else(|| {
+                    directive_config.is_some_and(|directive| {
+                        overrides
+                            .book
+                            .collapsible_directives
+                            .contains(&directive.to_string())
+                    }) || overrides.book.collapsible.unwrap_or_default()
+                })
+            });
+
+        let (body_prefix, body_suffix) = match directive_config {
+            Some(Directive::Builtin(builtin)) => overrides
+                .builtin
+                .get(builtin)
+                .map(|config| (config.body_prefix.clone(), config.body_suffix.clone()))
+                .unwrap_or_default(),
+            Some(Directive::Custom(custom)) => {
+                (custom.body_prefix.clone(), custom.body_suffix.clone())
+            }
+            None => Default::default(),
+        };
+
+        // Use ARIA semantics from block, else from the directive config
+        let (directive_role, directive_aria_label) = match directive_config {
+            Some(Directive::Builtin(builtin)) => overrides
+                .builtin
+                .get(builtin)
+                .map(|config| (config.role.clone(), config.aria_label.clone()))
+                .unwrap_or_default(),
+            Some(Directive::Custom(custom)) => (custom.role.clone(), custom.aria_label.clone()),
+            None => Default::default(),
         };
+        const DEFAULT_ROLE: &str = "note";
+        let role = role
+            .or(directive_role)
+            .unwrap_or_else(|| DEFAULT_ROLE.to_owned());
+        let aria_label = aria_label.or(directive_aria_label);
+
+        // The `lang` option takes precedence over one given in `attrs`
+        let mut attributes = attributes;
+        if lang.is_some() {
+            attributes.retain(|(name, _)| name != "lang");
+        }
+
+        // Only blocks that could be collapsible, but aren't yet, are checked
+        let auto_collapse_lines = overrides
+            .book
+            .auto_collapse_lines
+            .filter(|_| !explicit_collapsible && !inline && !collapsible);
+
+        let icon = icon.unwrap_or(true);
+        let icon_svg = if icon && overrides.book.inline_icons {
+            match &directive {
+                Directive::Builtin(builtin) => Some(builtin_icon_svg(*builtin).to_owned()),
+                Directive::Custom(custom) => custom.icon_svg.clone(),
+            }
+        } else {
+            None
+        };
+
+        // Load the directive (and title, if one still not given)
+        let directive_name = directive.to_string();
+        let default_title = directive.title(&raw_directive, id.as_deref(), overrides);
+
+        // Blocks without a titlebar have nowhere to show a number
+        let numbered =
+            overrides.book.numbered || overrides.book.numbered_directives.contains(&directive_name);
+        let title = title.unwrap_or_else(|| default_title.clone());
+        let number_label = (numbered && !title.is_empty()).then_some(default_title);
+        let aria_label = aria_label.map(|template| {
+            template
+                .replace("{directive}", &directive_name)
+                .replace("{title}", &title)
+        });
 
         let css_id = if let Some(verbatim) = id {
             CssId::Verbatim(verbatim)
         } else {
             const DEFAULT_CSS_ID_PREFIX: &str = "admonition-";
             CssId::Prefix(
-                overrides
-                    .book
-                    .css_id_prefix
-                    .clone()
+                id_prefix
+                    .or_else(|| overrides.book.css_id_prefix.clone())
                     .unwrap_or_else(|| DEFAULT_CSS_ID_PREFIX.to_owned()),
             )
         };
 
         Self {
-            directive,
+            directive: directive_name,
+            raw_directive,
+            written_directive,
             title,
+            number_label,
             css_id,
+            untitled_id_source: overrides.book.untitled_id_source,
             additional_classnames,
+            attributes,
             collapsible,
+            open: open.unwrap_or_default(),
+            summary,
+            element: overrides.book.element,
+            collapsible_impl: overrides.book.collapsible_impl,
+            density: density.unwrap_or(overrides.book.density),
+            style: style.unwrap_or(overrides.book.style),
+            title_layout: overrides.book.title_layout,
+            marker: overrides.book.marker,
+            class_style: overrides.book.class_style,
+            title_anchor: overrides.book.title_anchor.unwrap_or(true),
+            anchor_glyph: overrides.book.anchor_glyph.clone(),
+            badge: badge.unwrap_or(overrides.book.show_badge),
+            icon,
+            icon_svg,
+            avoid_page_break: overrides.book.avoid_page_break,
+            figure_wrap: overrides.book.figure_wrap,
+            state_labels: overrides.book.a11y_state_labels,
+            title_link,
+            caption,
+            src,
+            renderers,
+            width,
+            align,
+            lang,
+            body_format: body_format.unwrap_or_default(),
+            inline,
+            body_prefix,
+            body_suffix,
+            default_body: None,
+            role,
+            aria_label,
+            renamed_from,
+            auto_collapse_lines,
+            directive_disabled,
+        }
+    }
+
+    /// Make the block collapsible if `body` is longer than
+    /// `default.auto_collapse_lines`. Trailing blank lines are not counted.
+    pub fn auto_collapse(&mut self, body: &str) {
+        if let Some(max_lines) = self.auto_collapse_lines {
+            if body.trim_end().lines().nth(max_lines).is_some() {
+                self.collapsible = true;
+            }
+        }
+    }
+
+    /// Warning to log for a block using a deprecated directive, found at
+    /// `location`.
+    pub fn deprecation_warning(&self, location: impl fmt::Display) -> Option<String> {
+        self.renamed_from.as_ref().map(|renamed_from| {
+            format!(
+                "Directive '{renamed_from}' at {location} is deprecated, use '{}' instead",
+                self.raw_directive
+            )
+        })
+    }
+
+    /// Drop the summary of a block that is not collapsible, found at
+    /// `location`, returning a warning to log.
+    ///
+    /// Call once the body is known, as it may make the block collapsible.
+    pub fn drop_ignored_summary(&mut self, location: impl fmt::Display) -> Option<String> {
+        if self.collapsible {
+            return None;
         }
+        self.summary.take().map(|summary| {
+            format!("Ignoring summary '{summary}' at {location}, as the block is not collapsible")
+        })
+    }
+
+    /// Warning to log for a block using a disabled directive, found at
+    /// `location`.
+    pub fn disabled_warning(&self, location: impl fmt::Display) -> Option<String> {
+        self.directive_disabled.then(|| {
+            format!(
+                "Directive '{}' at {location} is disabled",
+                self.written_directive
+            )
+        })
     }
 }
 
+impl InstanceConfig {
+    /// Fill in unset options from the preset named by `use`, if any.
+    ///
+    /// Returns the body of the preset.
+    fn apply_preset(&mut self, overrides: &Overrides) -> Result<Option<String>, String> {
+        let Some(name) = &self.preset else {
+            return Ok(None);
+        };
+        let preset = overrides
+            .presets
+            .get(name)
+            .ok_or_else(|| format!("Unknown preset '{name}'"))?;
+        if self.directive.is_empty() {
+            self.directive = preset.directive.clone().unwrap_or_default();
+        }
+        if self.title.is_none() {
+            self.title = preset.title.clone();
+        }
+        if self.collapsible.is_none() {
+            self.collapsible = preset.collapsible;
+        }
+        Ok(preset.body.clone())
+    }
+}
+
+/// Whether `builtin` is listed in `disabled`, by name or by alias.
+fn is_disabled(disabled: &[String], builtin: BuiltinDirective) -> bool {
+    disabled
+        .iter()
+        .any(|name| BuiltinDirective::from_str(name) == Ok(builtin))
+}
+
 /// Format the title of an admonition directive
 ///
 /// We special case a few words to make them look nicer (e.g. "tldr" -> "TL;DR" and "faq" -> "FAQ").
-fn format_builtin_directive_title(input: &str) -> String {
+pub(crate) fn format_builtin_directive_title(input: &str) -> String {
     match input {
         "tldr" => "TL;DR".to_owned(),
         "faq" => "FAQ".to_owned(),
@@ -139,10 +498,21 @@ fn format_builtin_directive_title(input: &str) -> String {
     }
 }
 
+/// Substitute `{directive}` and `{id}` tokens in a custom directive title.
+///
+/// Tokens without a value (such as `{id}` on a block with no `id`) are left as is.
+fn interpolate_title(template: &str, directive: &str, id: Option<&str>) -> String {
+    let title = template.replace("{directive}", directive);
+    match id {
+        Some(id) => title.replace("{id}", id),
+        None => title,
+    }
+}
+
 /// Make the first letter of `input` uppercase.
 ///
 /// source: https://stackoverflow.com/a/38406885
-fn uppercase_first(input: &str) -> String {
+pub(crate) fn uppercase_first(input: &str) -> String {
     let mut chars = input.chars();
     match chars.next() {
         None => String::new(),
@@ -171,6 +541,25 @@ mod test {
         assert_eq!(format_builtin_directive_title("🦀"), "🦀");
     }
 
+    #[test]
+    fn test_interpolate_title() {
+        assert_eq!(interpolate_title("Plain", "issue", None), "Plain");
+        assert_eq!(
+            interpolate_title("Issue #{id}", "issue", Some("42")),
+            "Issue #42"
+        );
+        assert_eq!(
+            interpolate_title("{directive}: {id}", "bug", Some("a")),
+            "bug: a"
+        );
+        // Tokens without a value are left as is
+        assert_eq!(
+            interpolate_title("Issue #{id}", "issue", None),
+            "Issue #{id}"
+        );
+        assert_eq!(interpolate_title("{other}", "issue", None), "{other}");
+    }
+
     #[test]
     fn test_admonition_info_from_raw() {
         assert_eq!(
@@ -181,15 +570,55 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides::default(),
             ),
             AdmonitionMeta {
                 directive: "note".to_owned(),
+                raw_directive: "note".to_owned(),
+                written_directive: "note".to_owned(),
                 title: "Note".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: false,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -204,22 +633,63 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     book: AdmonitionDefaults {
                         title: Some("Important!!!".to_owned()),
                         css_id_prefix: Some("custom-prefix-".to_owned()),
-                        collapsible: true,
+                        collapsible: Some(true),
+                        ..Default::default()
                     },
                     ..Default::default()
                 }
             ),
             AdmonitionMeta {
                 directive: "note".to_owned(),
+                raw_directive: "note".to_owned(),
+                written_directive: "note".to_owned(),
                 title: "Important!!!".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("custom-prefix-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: true,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -234,26 +704,106 @@ mod test {
                     id: Some("my-custom-id".to_owned()),
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     book: AdmonitionDefaults {
                         title: Some("Important!!!".to_owned()),
                         css_id_prefix: Some("ignored-custom-prefix-".to_owned()),
-                        collapsible: true,
+                        collapsible: Some(true),
+                        ..Default::default()
                     },
                     ..Default::default()
                 }
             ),
             AdmonitionMeta {
                 directive: "note".to_owned(),
+                raw_directive: "note".to_owned(),
+                written_directive: "note".to_owned(),
                 title: "Important!!!".to_owned(),
+                number_label: None,
                 css_id: CssId::Verbatim("my-custom-id".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: true,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
 
+    #[test]
+    fn test_admonition_info_from_raw_with_id_prefix() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                css_id_prefix: Some("book-prefix-".to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for (title, id, expected) in [
+            // With a title
+            (None, None, CssId::Prefix("glossary-".to_owned())),
+            // Without a title
+            (
+                Some("".to_owned()),
+                None,
+                CssId::Prefix("glossary-".to_owned()),
+            ),
+            // Verbatim ids take precedence
+            (
+                None,
+                Some("my-id".to_owned()),
+                CssId::Verbatim("my-id".to_owned()),
+            ),
+        ] {
+            let actual = AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: "note".to_owned(),
+                    title,
+                    id,
+                    id_prefix: Some("glossary-".to_owned()),
+                    ..Default::default()
+                },
+                &overrides,
+            );
+            assert_eq!(actual.css_id, expected);
+        }
+    }
+
     #[test]
     fn test_admonition_info_from_raw_with_custom_directive() {
         assert_eq!(
@@ -264,13 +814,21 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     custom: [CustomDirective {
                         directive: "frog".to_owned(),
                         aliases: Vec::new(),
+                        renamed_from: Vec::new(),
                         title: None,
                         collapsible: None,
+                        alias_collapsible: Default::default(),
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: None,
+                        aria_label: None,
+                        icon_svg: None,
                     }]
                     .into_iter()
                     .collect(),
@@ -279,10 +837,49 @@ mod test {
             ),
             AdmonitionMeta {
                 directive: "frog".to_owned(),
+                raw_directive: "frog".to_owned(),
+                written_directive: "frog".to_owned(),
                 title: "Frog".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: false,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -297,13 +894,21 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     custom: [CustomDirective {
                         directive: "frog".to_owned(),
                         aliases: Vec::new(),
+                        renamed_from: Vec::new(),
                         title: Some("🏳️‍🌈".to_owned()),
                         collapsible: None,
+                        alias_collapsible: Default::default(),
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: None,
+                        aria_label: None,
+                        icon_svg: None,
                     }]
                     .into_iter()
                     .collect(),
@@ -312,10 +917,126 @@ mod test {
             ),
             AdmonitionMeta {
                 directive: "frog".to_owned(),
+                raw_directive: "frog".to_owned(),
+                written_directive: "frog".to_owned(),
                 title: "🏳️‍🌈".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: false,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_with_custom_directive_title_template() {
+        assert_eq!(
+            AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: "issue".to_owned(),
+                    id: Some("42".to_owned()),
+                    ..Default::default()
+                },
+                &Overrides {
+                    custom: [CustomDirective {
+                        directive: "issue".to_owned(),
+                        aliases: Vec::new(),
+                        renamed_from: Vec::new(),
+                        title: Some("Issue #{id}".to_owned()),
+                        collapsible: None,
+                        alias_collapsible: Default::default(),
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: None,
+                        aria_label: None,
+                        icon_svg: None,
+                    }]
+                    .into_iter()
+                    .collect(),
+                    ..Default::default()
+                }
+            ),
+            AdmonitionMeta {
+                directive: "issue".to_owned(),
+                raw_directive: "issue".to_owned(),
+                written_directive: "issue".to_owned(),
+                title: "Issue #42".to_owned(),
+                number_label: None,
+                css_id: CssId::Verbatim("42".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
+                additional_classnames: Vec::new(),
+                attributes: Vec::new(),
+                collapsible: false,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -330,13 +1051,21 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     custom: [CustomDirective {
                         directive: "frog".to_owned(),
                         aliases: vec!["newt".to_owned(), "toad".to_owned()],
+                        renamed_from: Vec::new(),
                         title: Some("🏳️‍🌈".to_owned()),
                         collapsible: None,
+                        alias_collapsible: Default::default(),
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: None,
+                        aria_label: None,
+                        icon_svg: None,
                     }]
                     .into_iter()
                     .collect(),
@@ -345,10 +1074,49 @@ mod test {
             ),
             AdmonitionMeta {
                 directive: "frog".to_owned(),
+                raw_directive: "toad".to_owned(),
+                written_directive: "toad".to_owned(),
                 title: "Still a frog".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: false,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -363,13 +1131,21 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     custom: [CustomDirective {
                         directive: "frog".to_owned(),
                         aliases: Vec::new(),
+                        renamed_from: Vec::new(),
                         title: None,
                         collapsible: Some(true),
+                        alias_collapsible: Default::default(),
+                        body_prefix: None,
+                        body_suffix: None,
+                        role: None,
+                        aria_label: None,
+                        icon_svg: None,
                     }]
                     .into_iter()
                     .collect(),
@@ -378,10 +1154,49 @@ mod test {
             ),
             AdmonitionMeta {
                 directive: "frog".to_owned(),
+                raw_directive: "frog".to_owned(),
+                written_directive: "frog".to_owned(),
                 title: "Frog".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: true,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -396,17 +1211,23 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     book: AdmonitionDefaults {
                         title: None,
                         css_id_prefix: None,
-                        collapsible: false,
+                        collapsible: Some(false),
+                        ..Default::default()
                     },
                     builtin: HashMap::from([(
                         BuiltinDirective::Abstract,
                         BuiltinDirectiveConfig {
                             collapsible: Some(true),
+                            body_prefix: None,
+                            body_suffix: None,
+                            role: None,
+                            aria_label: None,
                         }
                     )]),
                     ..Default::default()
@@ -414,10 +1235,49 @@ mod test {
             ),
             AdmonitionMeta {
                 directive: "abstract".to_owned(),
+                raw_directive: "abstract".to_owned(),
+                written_directive: "abstract".to_owned(),
                 title: "Abstract".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: true,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
@@ -432,17 +1292,23 @@ mod test {
                     id: None,
                     additional_classnames: Vec::new(),
                     collapsible: None,
+                    ..Default::default()
                 },
                 &Overrides {
                     book: AdmonitionDefaults {
                         title: None,
                         css_id_prefix: None,
-                        collapsible: true,
+                        collapsible: Some(true),
+                        ..Default::default()
                     },
                     builtin: HashMap::from([(
                         BuiltinDirective::Abstract,
                         BuiltinDirectiveConfig {
                             collapsible: Some(false),
+                            body_prefix: None,
+                            body_suffix: None,
+                            role: None,
+                            aria_label: None,
                         }
                     )]),
                     ..Default::default()
@@ -450,11 +1316,450 @@ mod test {
             ),
             AdmonitionMeta {
                 directive: "abstract".to_owned(),
+                raw_directive: "abstract".to_owned(),
+                written_directive: "abstract".to_owned(),
                 title: "Abstract".to_owned(),
+                number_label: None,
                 css_id: CssId::Prefix("admonition-".to_owned()),
+                untitled_id_source: UntitledIdSource::Default,
                 additional_classnames: Vec::new(),
+                attributes: Vec::new(),
                 collapsible: false,
+                open: false,
+                summary: None,
+                width: None,
+                align: None,
+                lang: None,
+                body_format: BodyFormat::Markdown,
+                inline: false,
+                body_prefix: None,
+                body_suffix: None,
+                default_body: None,
+                role: "note".to_owned(),
+                aria_label: None,
+                renamed_from: None,
+                auto_collapse_lines: None,
+                directive_disabled: false,
+                element: AdmonitionElement::Div,
+                collapsible_impl: CollapsibleImpl::Native,
+                density: Density::Normal,
+                style: BlockStyle::Box,
+                title_layout: TitleLayout::Inline,
+                marker: SummaryMarker::End,
+                class_style: ClassStyle::Admonish,
+                title_anchor: true,
+                anchor_glyph: String::new(),
+                badge: false,
+                icon: true,
+                icon_svg: None,
+                avoid_page_break: false,
+                figure_wrap: false,
+                state_labels: false,
+                title_link: None,
+                caption: None,
+                src: None,
+                renderers: None,
             }
         );
     }
+
+    #[test]
+    fn test_admonition_info_from_raw_collapsible_precedence() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                collapsible: Some(false),
+                collapsible_directives: vec![
+                    "warning".to_owned(),
+                    "danger".to_owned(),
+                    "frog".to_owned(),
+                ],
+                ..Default::default()
+            },
+            builtin: HashMap::from([(
+                BuiltinDirective::Danger,
+                BuiltinDirectiveConfig {
+                    collapsible: Some(false),
+                    body_prefix: None,
+                    body_suffix: None,
+                    role: None,
+                    aria_label: None,
+                },
+            )]),
+            custom: [
+                CustomDirective {
+                    directive: "frog".to_owned(),
+                    aliases: vec!["toad".to_owned()],
+                    renamed_from: Vec::new(),
+                    title: None,
+                    collapsible: None,
+                    alias_collapsible: Default::default(),
+                    body_prefix: None,
+                    body_suffix: None,
+                    role: None,
+                    aria_label: None,
+                    icon_svg: None,
+                },
+                CustomDirective {
+                    directive: "newt".to_owned(),
+                    aliases: vec!["eft".to_owned(), "details".to_owned()],
+                    renamed_from: Vec::new(),
+                    title: None,
+                    collapsible: Some(false),
+                    alias_collapsible: HashMap::from([("details".to_owned(), true)]),
+                    body_prefix: None,
+                    body_suffix: None,
+                    role: None,
+                    aria_label: None,
+                    icon_svg: None,
+                },
+            ]
+            .into_iter()
+            .collect(),
+            presets: HashMap::new(),
+            render_hook: None,
+            used_directives: Default::default(),
+            failed_blocks: Default::default(),
+            found_blocks: Default::default(),
+            warnings: Default::default(),
+        };
+
+        for (directive, instance_collapsible, expected) in [
+            // Global default
+            ("note", None, false),
+            // Unknown directives fall back to the global default
+            ("unknown", None, false),
+            // Listed in collapsible_directives
+            ("warning", None, true),
+            // Aliases resolve to the listed directive
+            ("caution", None, true),
+            ("toad", None, true),
+            // Per-directive config wins over collapsible_directives
+            ("danger", None, false),
+            // Per-alias config wins over per-directive config
+            ("newt", None, false),
+            ("eft", None, false),
+            ("details", None, true),
+            // Instance config wins over everything
+            ("details", Some(false), false),
+            ("warning", Some(false), false),
+            ("danger", Some(true), true),
+            ("note", Some(true), true),
+        ] {
+            let actual = AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: directive.to_owned(),
+                    collapsible: instance_collapsible,
+                    ..Default::default()
+                },
+                &overrides,
+            );
+            assert_eq!(
+                actual.collapsible, expected,
+                "directive '{directive}' with instance value {instance_collapsible:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_with_default_directive() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                directive: Some("caution".to_owned()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for raw_directive in ["", "unknown"] {
+            let actual = AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: raw_directive.to_owned(),
+                    ..Default::default()
+                },
+                &overrides,
+            );
+            assert_eq!(actual.directive, "warning");
+            assert_eq!(actual.title, "Caution");
+        }
+
+        // Invalid default directives fall back to note
+        let actual = AdmonitionMeta::resolve(
+            InstanceConfig::default(),
+            &Overrides {
+                book: AdmonitionDefaults {
+                    directive: Some("unknown".to_owned()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        assert_eq!(actual.directive, "note");
+        assert_eq!(actual.title, "Note");
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_unknown_directive_ignores_fallback_config() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                collapsible_directives: vec!["note".to_owned()],
+                ..Default::default()
+            },
+            builtin: HashMap::from([(
+                BuiltinDirective::Note,
+                BuiltinDirectiveConfig {
+                    collapsible: Some(true),
+                    body_prefix: Some("Prefix".to_owned()),
+                    body_suffix: None,
+                    role: Some("status".to_owned()),
+                    aria_label: None,
+                },
+            )]),
+            ..Default::default()
+        };
+        let resolve = |directive: &str| {
+            AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: directive.to_owned(),
+                    ..Default::default()
+                },
+                &overrides,
+            )
+        };
+
+        let actual = resolve("note");
+        assert!(actual.collapsible);
+        assert_eq!(actual.body_prefix.as_deref(), Some("Prefix"));
+        assert_eq!(actual.role, "status");
+
+        // Unknown directives look like a note, but don't use its config
+        for directive in ["", "frog"] {
+            let actual = resolve(directive);
+            assert_eq!(actual.directive, "note");
+            assert!(!actual.collapsible);
+            assert_eq!(actual.body_prefix, None);
+            assert_eq!(actual.role, "note");
+        }
+    }
+
+    #[test]
+    fn test_drop_ignored_summary() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                auto_collapse_lines: Some(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let resolve = || {
+            AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: "note".to_owned(),
+                    summary: Some("Details".to_owned()),
+                    ..Default::default()
+                },
+                &overrides,
+            )
+        };
+
+        let mut actual = resolve();
+        assert_eq!(
+            actual.drop_ignored_summary("chapter.md:3"),
+            Some(
+                "Ignoring summary 'Details' at chapter.md:3, as the block is not collapsible"
+                    .to_owned()
+            )
+        );
+        assert_eq!(actual.summary, None);
+
+        // Blocks collapsed for their length keep the summary
+        let mut actual = resolve();
+        actual.auto_collapse("One\nTwo\n");
+        assert_eq!(actual.drop_ignored_summary("chapter.md:3"), None);
+        assert_eq!(actual.summary.as_deref(), Some("Details"));
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_with_disabled_directive() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                disabled_directives: vec!["quote".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // Aliases of a disabled directive are also disabled
+        for raw_directive in ["quote", "cite"] {
+            let actual = AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: raw_directive.to_owned(),
+                    ..Default::default()
+                },
+                &overrides,
+            );
+            assert_eq!(actual.directive, "note");
+            assert_eq!(actual.title, "Note");
+            assert_eq!(
+                actual.disabled_warning("chapter.md:3"),
+                Some(format!(
+                    "Directive '{raw_directive}' at chapter.md:3 is disabled"
+                ))
+            );
+        }
+
+        let actual = AdmonitionMeta::resolve(
+            InstanceConfig {
+                directive: "bug".to_owned(),
+                ..Default::default()
+            },
+            &overrides,
+        );
+        assert_eq!(actual.directive, "bug");
+        assert_eq!(actual.disabled_warning("chapter.md:3"), None);
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_with_localized_titles() {
+        let overrides = Overrides {
+            book: AdmonitionDefaults {
+                titles: HashMap::from([
+                    ("note".to_owned(), "Anmerkung".to_owned()),
+                    ("warning".to_owned(), "Warnung".to_owned()),
+                    ("caution".to_owned(), "Vorsicht".to_owned()),
+                ]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        for (directive, title, expected) in [
+            ("note", None, "Anmerkung"),
+            // Aliases use the title of their directive, unless given their own
+            ("attention", None, "Warnung"),
+            ("caution", None, "Vorsicht"),
+            // Unlisted directives fall back to the builtin titles
+            ("tldr", None, "TL;DR"),
+            ("tip", None, "Tip"),
+            // Instance config wins
+            ("note", Some("Hinweis"), "Hinweis"),
+        ] {
+            let actual = AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: directive.to_owned(),
+                    title: title.map(ToOwned::to_owned),
+                    ..Default::default()
+                },
+                &overrides,
+            );
+            assert_eq!(actual.title, expected, "directive '{directive}'");
+        }
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_role_precedence() {
+        let overrides = Overrides {
+            builtin: HashMap::from([(
+                BuiltinDirective::Danger,
+                BuiltinDirectiveConfig {
+                    collapsible: None,
+                    body_prefix: None,
+                    body_suffix: None,
+                    role: Some("alert".to_owned()),
+                    aria_label: Some("{directive}: {title}".to_owned()),
+                },
+            )]),
+            ..Default::default()
+        };
+
+        for (directive, instance_role, expected_role, expected_label) in [
+            // Built-in default
+            ("note", None, "note", None),
+            // Directive config, also applied to aliases
+            ("danger", None, "alert", Some("danger: Danger")),
+            ("error", None, "alert", Some("danger: Error")),
+            // Instance config wins over directive config
+            ("danger", Some("status"), "status", Some("danger: Danger")),
+            ("note", Some("status"), "status", None),
+        ] {
+            let actual = AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: directive.to_owned(),
+                    role: instance_role.map(ToOwned::to_owned),
+                    ..Default::default()
+                },
+                &overrides,
+            );
+            assert_eq!(
+                (actual.role.as_str(), actual.aria_label.as_deref()),
+                (expected_role, expected_label),
+                "directive '{directive}' with instance role {instance_role:?}"
+            );
+        }
+
+        // Instance label wins over directive config
+        let actual = AdmonitionMeta::resolve(
+            InstanceConfig {
+                directive: "danger".to_owned(),
+                title: Some("Hot".to_owned()),
+                aria_label: Some("Careful, {title}".to_owned()),
+                ..Default::default()
+            },
+            &overrides,
+        );
+        assert_eq!(actual.aria_label.as_deref(), Some("Careful, Hot"));
+    }
+
+    #[test]
+    fn test_admonition_info_from_raw_renamed_directive() {
+        let overrides = Overrides {
+            custom: [CustomDirective {
+                directive: "frog".to_owned(),
+                aliases: vec!["toad".to_owned()],
+                renamed_from: vec!["newt".to_owned(), "warning".to_owned()],
+                title: None,
+                collapsible: None,
+                alias_collapsible: Default::default(),
+                body_prefix: None,
+                body_suffix: None,
+                role: None,
+                aria_label: None,
+                icon_svg: None,
+            }]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        let resolve = |directive: &str| {
+            AdmonitionMeta::resolve(
+                InstanceConfig {
+                    directive: directive.to_owned(),
+                    ..Default::default()
+                },
+                &overrides,
+            )
+        };
+
+        // Renamed directives resolve to the new directive, with a warning
+        let actual = resolve("newt");
+        assert_eq!(actual.directive, "frog");
+        assert_eq!(actual.raw_directive, "frog");
+        assert_eq!(actual.written_directive, "newt");
+        assert_eq!(actual.title, "Frog");
+        assert_eq!(actual.renamed_from.as_deref(), Some("newt"));
+        assert_eq!(
+            actual.deprecation_warning("chapter.md:3").as_deref(),
+            Some("Directive 'newt' at chapter.md:3 is deprecated, use 'frog' instead")
+        );
+
+        // Aliases are not deprecated
+        for directive in ["frog", "toad"] {
+            let actual = resolve(directive);
+            assert_eq!(actual.directive, "frog");
+            assert_eq!(actual.renamed_from, None);
+            assert_eq!(actual.deprecation_warning("chapter.md:3"), None);
+        }
+
+        // Builtin directives take precedence
+        let actual = resolve("warning");
+        assert_eq!(actual.directive, "warning");
+        assert_eq!(actual.renamed_from, None);
+    }
 }
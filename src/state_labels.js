@@ -0,0 +1,30 @@
+// Update the state labels of collapsible blocks, with
+// `default.a11y_state_labels = true`, when they are opened or closed. This is
+// inlined into each chapter, inside an HTML block, so must not contain blank
+// lines.
+(function () {
+  // Pages such as print.html include several chapters
+  if ("admonishStateLabels" in document.documentElement.dataset) {
+    return;
+  }
+  document.documentElement.dataset.admonishStateLabels = "";
+  // Toggle events don't bubble, so listen in the capture phase
+  document.addEventListener(
+    "toggle",
+    (event) => {
+      const summary = event.target.querySelector(
+        ":scope > summary[aria-expanded]",
+      );
+      if (!summary) {
+        return;
+      }
+      const open = event.target.open;
+      summary.setAttribute("aria-expanded", open);
+      const label = summary.querySelector(":scope > .admonition-state-label");
+      if (label) {
+        label.textContent = open ? "expanded" : "collapsed";
+      }
+    },
+    true,
+  );
+})();
@@ -1,22 +1,476 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
 
+use crate::render::RenderHook;
+
 /// Book wide defaults that may be provided by the user.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
 pub(crate) struct AdmonitionDefaults {
     #[serde(default)]
     pub(crate) title: Option<String>,
 
+    /// Default titles of builtin directives, by directive or alias, e.g. for
+    /// a book in another language.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) titles: HashMap<String, String>,
+
+    /// Directive to use for blocks without a (valid) directive. Defaults to `note`.
+    #[serde(default)]
+    pub(crate) directive: Option<String>,
+
+    /// Whether blocks are collapsible by default.
+    ///
+    /// If not set, follows `output.html.fold.enable` from the book config.
+    #[serde(default)]
+    pub(crate) collapsible: Option<bool>,
+
+    /// Make error blocks for invalid admonitions collapsible.
+    #[serde(default)]
+    pub(crate) error_collapsible: bool,
+
+    /// Directive to use for error blocks for invalid admonitions. Defaults to `bug`.
     #[serde(default)]
-    pub(crate) collapsible: bool,
+    pub(crate) error_directive: Option<String>,
+
+    /// Directives that should be collapsible by default.
+    ///
+    /// Takes precedence over `collapsible`, but not over per-directive config.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) collapsible_directives: Vec<String>,
+
+    /// Builtin directives that may not be used. Aliases of a listed directive are
+    /// also disabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) disabled_directives: Vec<String>,
+
+    /// Number blocks of each directive within a chapter, e.g. "Example 1".
+    #[serde(default)]
+    pub(crate) numbered: bool,
+
+    /// Directives that should be numbered, when `numbered` is not set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) numbered_directives: Vec<String>,
 
     #[serde(default)]
     // For backwards compatibility, we support this field with kebab-case style
     // naming, even though this was introduced in error.
     #[serde(alias = "css-id-prefix")]
     pub(crate) css_id_prefix: Option<String>,
+
+    #[serde(default)]
+    pub(crate) untitled_id_source: UntitledIdSource,
+
+    #[serde(default)]
+    pub(crate) element: AdmonitionElement,
+
+    #[serde(default)]
+    pub(crate) collapsible_impl: CollapsibleImpl,
+
+    #[serde(default)]
+    pub(crate) density: Density,
+
+    #[serde(default)]
+    pub(crate) style: BlockStyle,
+
+    #[serde(default)]
+    pub(crate) title_layout: TitleLayout,
+
+    /// Where the disclosure marker of collapsible blocks is shown.
+    #[serde(default)]
+    pub(crate) marker: SummaryMarker,
+
+    /// Whether to render an anchor link in the titlebar. Defaults to `true`.
+    #[serde(default)]
+    pub(crate) title_anchor: Option<bool>,
+
+    /// Text shown inside the anchor link, rather than drawing it with CSS.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(crate) anchor_glyph: String,
+
+    /// Include the page of the chapter in anchor links, e.g. `chapter.html#anchor`.
+    #[serde(default)]
+    pub(crate) absolute_anchor_links: bool,
+
+    /// Show the directive as a badge in the titlebar.
+    #[serde(default)]
+    pub(crate) show_badge: bool,
+
+    /// Fail the build if any warnings are logged while processing blocks.
+    #[serde(default)]
+    pub(crate) deny_warnings: bool,
+
+    /// Warn about custom directives that no block in the book uses.
+    #[serde(default)]
+    pub(crate) warn_unused_directives: bool,
+
+    /// Include the SVG of each icon in the titlebar markup, rather than
+    /// drawing it with a CSS mask.
+    #[serde(default)]
+    pub(crate) inline_icons: bool,
+
+    /// Add "expand all" and "collapse all" buttons to the top of chapters with
+    /// collapsible blocks.
+    #[serde(default)]
+    pub(crate) collapse_controls: bool,
+
+    /// Hint that blocks should not be split across pages when printed.
+    #[serde(default)]
+    pub(crate) avoid_page_break: bool,
+
+    /// Wrap titled blocks in a `<figure>`, with the title as its caption.
+    #[serde(default)]
+    pub(crate) figure_wrap: bool,
+
+    /// Render collapsible blocks expanded for renderers other than html, which
+    /// can't toggle them. Defaults to `true`.
+    #[serde(default)]
+    pub(crate) force_expand_non_html: Option<bool>,
+
+    /// How deeply blocks may be nested inside each other. Defaults to 8.
+    #[serde(default)]
+    pub(crate) max_nesting: Option<usize>,
+
+    /// Make blocks collapsible if their body is longer than this many lines,
+    /// unless they set `collapsible` themselves.
+    #[serde(default)]
+    pub(crate) auto_collapse_lines: Option<usize>,
+
+    /// Announce whether collapsible blocks are expanded to screen readers.
+    #[serde(default)]
+    pub(crate) a11y_state_labels: bool,
+
+    #[serde(default)]
+    pub(crate) color_mode: ColorMode,
+
+    #[serde(default)]
+    pub(crate) class_style: ClassStyle,
+
+    /// Expand collapsed blocks when printing, in generated custom CSS.
+    #[serde(default)]
+    pub(crate) print_expand_collapsed: bool,
+
+    /// Also convert mkdocs-material style `!!! note` blocks.
+    #[serde(default)]
+    pub(crate) mkdocs_syntax: bool,
+
+    /// Warn about incompatible installed assets, rather than failing the build.
+    #[serde(default)]
+    pub(crate) skip_assets_check: bool,
+
+    /// Which blank lines around the body of blocks are removed.
+    #[serde(default)]
+    pub(crate) trim: BodyTrim,
+
+    /// Globs of chapter source paths to process. If empty, all chapters are processed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<String>,
+
+    /// Globs of chapter source paths not to process. Takes precedence over `include`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) exclude: Vec<String>,
+}
+
+/// Which whitespace around the body of a block is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BodyTrim {
+    /// Leading blank lines and trailing whitespace.
+    Both,
+    /// Trailing whitespace only.
+    #[default]
+    End,
+    /// Nothing, the body is used as written.
+    #[serde(rename = "none")]
+    Neither,
+}
+
+impl BodyTrim {
+    /// Trim `body` as configured.
+    ///
+    /// Only whole blank lines are removed from the start, so the first line
+    /// keeps its indent, such as that of an indented code block.
+    pub(crate) fn apply(self, body: &str) -> &str {
+        let body = match self {
+            Self::Both => {
+                let leading = body.len() - body.trim_start().len();
+                match body[..leading].rfind('\n') {
+                    Some(index) => &body[index + 1..],
+                    None => body,
+                }
+            }
+            Self::End | Self::Neither => body,
+        };
+        match self {
+            Self::Both | Self::End => body.trim_end(),
+            Self::Neither => body,
+        }
+    }
+}
+
+/// How colors are written when generating CSS for custom directives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ColorMode {
+    /// Use the configured hex color directly.
+    #[default]
+    Fixed,
+    /// Reference a CSS variable, falling back to the configured hex color.
+    Variable,
+}
+
+/// How the classname of the directive of a block is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ClassStyle {
+    /// `admonish-note`, as used by the bundled stylesheet.
+    #[default]
+    Admonish,
+    /// `admonition--note`, a BEM modifier of the `admonition` block.
+    Bem,
+}
+
+impl ClassStyle {
+    /// The classname of blocks with `directive`.
+    pub(crate) fn directive_class(self, directive: &str) -> String {
+        match self {
+            Self::Admonish => format!("admonish-{directive}"),
+            Self::Bem => format!("admonition--{directive}"),
+        }
+    }
+}
+
+/// What the generated id of a block without a title is based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum UntitledIdSource {
+    /// The fixed string `default`.
+    #[default]
+    Default,
+    /// The (canonical) directive of the block.
+    Directive,
+    /// A running count of untitled blocks in the chapter.
+    Index,
+}
+
+/// How much padding blocks are rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Density {
+    #[default]
+    Normal,
+    /// Reduced padding and smaller icons, via the `admonition-compact` class.
+    Compact,
+}
+
+/// How the outline of blocks is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BlockStyle {
+    /// A full border around the block.
+    #[default]
+    Box,
+    /// Only a colored bar on the leading edge, via the `admonition-bar` class.
+    Bar,
+}
+
+/// How the body of a block is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BodyFormat {
+    /// Rendered as markdown, so wrapped in blank lines.
+    #[default]
+    Markdown,
+    /// Literal html, emitted without surrounding blank lines.
+    Html,
+}
+
+/// How the icon and title are arranged in the titlebar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TitleLayout {
+    /// Icon and title on the same row.
+    #[default]
+    Inline,
+    /// Icon above the title, via the `admonition-title-stacked` class.
+    Stacked,
+}
+
+/// Where the disclosure marker is shown in the titlebar of collapsible blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SummaryMarker {
+    /// Before the icon, via the `admonition-marker-start` class.
+    Start,
+    /// After the title.
+    #[default]
+    End,
+    /// Not shown, via the `admonition-marker-none` class.
+    #[serde(rename = "none")]
+    Hidden,
+}
+
+/// Which side a block floats to, letting surrounding content wrap around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Align {
+    /// Via the `admonition-float-left` class.
+    Left,
+    /// Via the `admonition-float-right` class.
+    Right,
+}
+
+impl Align {
+    pub(crate) fn classname(self) -> &'static str {
+        match self {
+            Self::Left => "admonition-float-left",
+            Self::Right => "admonition-float-right",
+        }
+    }
+}
+
+/// How collapsible admonitions are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CollapsibleImpl {
+    /// A native `<details>` element, with a `<summary>` titlebar.
+    #[default]
+    Native,
+    /// A disclosure widget, with a `<button aria-expanded>` in the titlebar
+    /// toggling the content region. Requires the script included in the chapter.
+    Aria,
+}
+
+/// The HTML element used to wrap non-collapsible admonitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AdmonitionElement {
+    #[default]
+    Div,
+    Aside,
+}
+
+impl AdmonitionElement {
+    pub(crate) fn tag(self) -> &'static str {
+        match self {
+            Self::Div => "div",
+            Self::Aside => "aside",
+        }
+    }
+}
+
+/// An admonition found in a chapter, for use by other tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AdmonitionPosition {
+    /// The canonical directive, used in CSS classnames.
+    pub directive: String,
+    /// The title of the block, before any number label is added.
+    pub title: String,
+    /// Byte range of the block in the chapter, from the opening fence to the
+    /// end of the closing fence.
+    ///
+    /// Always on char boundaries, so can be used to slice the chapter.
+    pub byte_range: Range<usize>,
+    /// One-indexed line number of the opening fence.
+    pub line: usize,
+}
+
+/// Information about a builtin directive, for use by other tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectiveInfo {
+    /// The canonical directive, used in CSS classnames.
+    pub directive: &'static str,
+    /// Alternative names that resolve to this directive.
+    pub aliases: &'static [&'static str],
+    /// The default title of blocks using the canonical directive.
+    pub title: &'static str,
+    /// The color of the directive, as a hex color.
+    pub color: &'static str,
+}
+
+/// Builtin directives, in the same order as [`BuiltinDirective`].
+const BUILTIN_DIRECTIVES: [DirectiveInfo; 12] = [
+    DirectiveInfo {
+        directive: "note",
+        aliases: &[],
+        title: "Note",
+        color: "#448aff",
+    },
+    DirectiveInfo {
+        directive: "abstract",
+        aliases: &["summary", "tldr"],
+        title: "Abstract",
+        color: "#00b0ff",
+    },
+    DirectiveInfo {
+        directive: "info",
+        aliases: &["todo"],
+        title: "Info",
+        color: "#00b8d4",
+    },
+    DirectiveInfo {
+        directive: "tip",
+        aliases: &["hint", "important"],
+        title: "Tip",
+        color: "#00bfa5",
+    },
+    DirectiveInfo {
+        directive: "success",
+        aliases: &["check", "done"],
+        title: "Success",
+        color: "#00c853",
+    },
+    DirectiveInfo {
+        directive: "question",
+        aliases: &["help", "faq"],
+        title: "Question",
+        color: "#64dd17",
+    },
+    DirectiveInfo {
+        directive: "warning",
+        aliases: &["caution", "attention"],
+        title: "Warning",
+        color: "#ff9100",
+    },
+    DirectiveInfo {
+        directive: "failure",
+        aliases: &["fail", "missing"],
+        title: "Failure",
+        color: "#ff5252",
+    },
+    DirectiveInfo {
+        directive: "danger",
+        aliases: &["error"],
+        title: "Danger",
+        color: "#ff1744",
+    },
+    DirectiveInfo {
+        directive: "bug",
+        aliases: &[],
+        title: "Bug",
+        color: "#f50057",
+    },
+    DirectiveInfo {
+        directive: "example",
+        aliases: &[],
+        title: "Example",
+        color: "#7c4dff",
+    },
+    DirectiveInfo {
+        directive: "quote",
+        aliases: &["cite"],
+        title: "Quote",
+        color: "#9e9e9e",
+    },
+];
+
+/// All builtin directives, in the order they are documented.
+pub fn builtin_directives() -> &'static [DirectiveInfo] {
+    &BUILTIN_DIRECTIVES
 }
 
 /// First class supported directives by the crate.
@@ -41,45 +495,49 @@ pub(crate) enum BuiltinDirective {
     Quote,
 }
 
+impl BuiltinDirective {
+    /// All builtin directives, in the order they are documented.
+    pub(crate) const ALL: [Self; 12] = [
+        Self::Note,
+        Self::Abstract,
+        Self::Info,
+        Self::Tip,
+        Self::Success,
+        Self::Question,
+        Self::Warning,
+        Self::Failure,
+        Self::Danger,
+        Self::Bug,
+        Self::Example,
+        Self::Quote,
+    ];
+
+    pub(crate) fn info(self) -> &'static DirectiveInfo {
+        &BUILTIN_DIRECTIVES[self as usize]
+    }
+
+    /// Alternative names that resolve to this directive.
+    pub(crate) fn aliases(self) -> &'static [&'static str] {
+        self.info().aliases
+    }
+}
+
 impl FromStr for BuiltinDirective {
     type Err = ();
 
     fn from_str(string: &str) -> Result<Self, ()> {
-        match string {
-            "note" => Ok(Self::Note),
-            "abstract" | "summary" | "tldr" => Ok(Self::Abstract),
-            "info" | "todo" => Ok(Self::Info),
-            "tip" | "hint" | "important" => Ok(Self::Tip),
-            "success" | "check" | "done" => Ok(Self::Success),
-            "question" | "help" | "faq" => Ok(Self::Question),
-            "warning" | "caution" | "attention" => Ok(Self::Warning),
-            "failure" | "fail" | "missing" => Ok(Self::Failure),
-            "danger" | "error" => Ok(Self::Danger),
-            "bug" => Ok(Self::Bug),
-            "example" => Ok(Self::Example),
-            "quote" | "cite" => Ok(Self::Quote),
-            _ => Err(()),
-        }
+        Self::ALL
+            .into_iter()
+            .find(|directive| {
+                directive.to_string() == string || directive.aliases().contains(&string)
+            })
+            .ok_or(())
     }
 }
 
 impl fmt::Display for BuiltinDirective {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let value = match self {
-            Self::Note => "note",
-            Self::Abstract => "abstract",
-            Self::Info => "info",
-            Self::Tip => "tip",
-            Self::Success => "success",
-            Self::Question => "question",
-            Self::Warning => "warning",
-            Self::Failure => "failure",
-            Self::Danger => "danger",
-            Self::Bug => "bug",
-            Self::Example => "example",
-            Self::Quote => "quote",
-        };
-        f.write_str(value)
+        f.write_str(self.info().directive)
     }
 }
 
@@ -90,23 +548,45 @@ impl fmt::Display for BuiltinDirective {
 pub(crate) struct CustomDirective {
     pub directive: String,
     pub aliases: Vec<String>,
+    /// Deprecated names, which resolve to this directive with a warning.
+    pub renamed_from: Vec<String>,
     pub title: Option<String>,
     pub collapsible: Option<bool>,
+    pub alias_collapsible: HashMap<String, bool>,
+    pub body_prefix: Option<String>,
+    pub body_suffix: Option<String>,
+    pub role: Option<String>,
+    pub aria_label: Option<String>,
+    /// SVG markup of the icon, only loaded when `default.inline_icons` is set.
+    pub icon_svg: Option<String>,
 }
 
 impl From<(String, crate::book_config::CustomDirective)> for CustomDirective {
     fn from((directive, config): (String, crate::book_config::CustomDirective)) -> Self {
         let crate::book_config::CustomDirective {
             aliases,
+            renamed_from,
             title,
             collapsible,
+            alias_collapsible,
+            body_prefix,
+            body_suffix,
+            role,
+            aria_label,
             ..
         } = config;
         Self {
             directive,
             aliases,
+            renamed_from,
             title,
             collapsible,
+            alias_collapsible,
+            body_prefix,
+            body_suffix,
+            role,
+            aria_label,
+            icon_svg: None,
         }
     }
 }
@@ -126,6 +606,31 @@ impl CustomDirectiveMap {
     pub fn get(&self, key: &str) -> Option<&CustomDirective> {
         self.inner.get(key)
     }
+
+    /// A map with `directives` added, taking precedence over existing ones.
+    pub fn extended_with(&self, directives: impl IntoIterator<Item = CustomDirective>) -> Self {
+        let mut inner = directives.into_iter().collect::<Self>().inner;
+        for (name, config) in &self.inner {
+            inner.entry(name.clone()).or_insert_with(|| config.clone());
+        }
+        Self { inner }
+    }
+
+    /// The directive that `key` was renamed to, if `key` is a deprecated name.
+    pub fn renamed_to(&self, key: &str) -> Option<&str> {
+        self.inner
+            .get(key)
+            .filter(|config| config.renamed_from.iter().any(|name| name == key))
+            .map(|config| config.directive.as_str())
+    }
+
+    /// The canonical names of all directives, without aliases.
+    pub fn directives(&self) -> impl Iterator<Item = &str> {
+        self.inner
+            .iter()
+            .filter(|(name, config)| **name == config.directive)
+            .map(|(name, _)| name.as_str())
+    }
 }
 
 impl FromIterator<CustomDirective> for CustomDirectiveMap {
@@ -136,7 +641,7 @@ impl FromIterator<CustomDirective> for CustomDirectiveMap {
                 .entry(config.directive.clone())
                 .or_insert(config.clone());
 
-            for alias in config.aliases.iter() {
+            for alias in config.aliases.iter().chain(&config.renamed_from) {
                 inner.entry(alias.clone()).or_insert(config.clone());
             }
         }
@@ -150,12 +655,56 @@ pub(crate) struct BuiltinDirectiveConfig {
     /// Default collapsible value.
     #[serde(default)]
     pub collapsible: Option<bool>,
+
+    /// Markdown added before the body of every block.
+    #[serde(default)]
+    pub body_prefix: Option<String>,
+
+    /// Markdown added after the body of every block.
+    #[serde(default)]
+    pub body_suffix: Option<String>,
+
+    /// ARIA role of every block, instead of `note`.
+    #[serde(default)]
+    pub role: Option<String>,
+
+    /// Template for the `aria-label` of every block. `{directive}` and
+    /// `{title}` are replaced with those of the block.
+    #[serde(default)]
+    pub aria_label: Option<String>,
+}
+
+/// A named, reusable admonition, used by blocks with `use="<name>"`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct AdmonitionPreset {
+    /// Directive of blocks without one.
+    #[serde(default)]
+    pub directive: Option<String>,
+
+    /// Title of blocks without one.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Collapsible value of blocks without one.
+    #[serde(default)]
+    pub collapsible: Option<bool>,
+
+    /// Markdown body of blocks with an empty body.
+    #[serde(default)]
+    pub body: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum RenderTextMode {
-    Strip,
+    Strip {
+        /// Keep block options in an HTML comment, so the block can be rebuilt.
+        annotate: bool,
+    },
     Html,
+    /// Minimal `<blockquote>` output, for renderers that drop other elements.
+    Blockquote,
+    /// Plain text, with the title above the body.
+    Text,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -175,4 +724,50 @@ pub(crate) struct Overrides {
     pub book: AdmonitionDefaults,
     pub builtin: HashMap<BuiltinDirective, BuiltinDirectiveConfig>,
     pub custom: CustomDirectiveMap,
+    pub presets: HashMap<String, AdmonitionPreset>,
+    pub render_hook: Option<RenderHook>,
+    /// Directives of the blocks rendered so far.
+    pub used_directives: RefCell<HashSet<String>>,
+    /// Number of blocks that failed to parse, and were rendered as errors.
+    pub failed_blocks: Cell<usize>,
+    /// Number of blocks found so far, including those that failed to parse.
+    pub found_blocks: Cell<usize>,
+    /// Number of warnings logged while processing blocks.
+    pub warnings: Cell<usize>,
+}
+
+impl Overrides {
+    /// Log a warning, counting it for `default.deny_warnings`.
+    pub(crate) fn warn(&self, message: &str) {
+        log::warn!("{message}");
+        self.warnings.set(self.warnings.get() + 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn builtin_directives_match_enum_and_css() {
+        const CSS: &str = include_str!("./bin/assets/mdbook-admonish.css");
+        for (builtin, info) in BuiltinDirective::ALL.into_iter().zip(builtin_directives()) {
+            assert_eq!(builtin.to_string(), info.directive);
+            assert_eq!(BuiltinDirective::from_str(info.directive), Ok(builtin));
+            let classes = std::iter::once(&info.directive)
+                .chain(info.aliases)
+                .map(|directive| format!(".admonish-{directive}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            assert!(
+                CSS.contains(&format!(
+                    ":is(.admonition):is({classes}) {{\n  border-color: {};",
+                    info.color
+                )),
+                "color of '{}' does not match the stylesheet",
+                info.directive
+            );
+        }
+    }
 }